@@ -0,0 +1,947 @@
+//! LiteSVM harness for the capstone_ethanbackhus payment program.
+//!
+//! Loads the program built by `anchor build` from `target/deploy` into an
+//! in-process SVM, so the suites in `tests/` run offline without a validator.
+
+use anchor_lang::{
+    prelude::{Clock, Pubkey},
+    solana_program::{
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        instruction::{error::InstructionError, AccountMeta, Instruction},
+        system_program,
+    },
+    AccountDeserialize, AccountSerialize, Discriminator, InstructionData, Space, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address},
+    token::{self, spl_token::native_mint, TokenAccount},
+};
+use capstone_ethanbackhus::{
+    accounts,
+    client::{next_index_page, SessionIndexKey},
+    instruction,
+    state::{
+        merchant_id_hash, EscrowLimits, MilestoneInput, PaymentSession, ProgramConfig, SettlementProposal,
+        DEFAULT_MAX_CASHBACK_BPS,
+    },
+};
+use litesvm::{types::TransactionResult, LiteSVM};
+use litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo};
+use solana_account::Account;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+pub const PROGRAM_SO: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/deploy/capstone_ethanbackhus.so"
+);
+
+pub const MERCHANT_ID: &str = "Amazon";
+pub const DECIMALS: u8 = 6;
+pub const PAYER_BALANCE: u64 = 1_000_000;
+pub const LARGE_SETTLEMENT_THRESHOLD: u64 = 1_000;
+pub const DAILY_LIMIT: u64 = 10_000;
+pub const MONTHLY_LIMIT: u64 = 100_000;
+// native SOL limits, in lamports
+pub const NATIVE_LARGE_SETTLEMENT_THRESHOLD: u64 = 1_000_000_000;
+pub const NATIVE_DAILY_LIMIT: u64 = 10_000_000_000;
+pub const NATIVE_MONTHLY_LIMIT: u64 = 50_000_000_000;
+
+pub fn token_limits() -> EscrowLimits {
+    EscrowLimits {
+        large_settlement_threshold: LARGE_SETTLEMENT_THRESHOLD,
+        daily_limit: DAILY_LIMIT,
+        monthly_limit: MONTHLY_LIMIT,
+    }
+}
+
+pub fn native_limits() -> EscrowLimits {
+    EscrowLimits {
+        large_settlement_threshold: NATIVE_LARGE_SETTLEMENT_THRESHOLD,
+        daily_limit: NATIVE_DAILY_LIMIT,
+        monthly_limit: NATIVE_MONTHLY_LIMIT,
+    }
+}
+
+// Handle on a payment session and the PDAs derived from it
+pub struct Session {
+    pub uuid: [u8; 16],
+    pub payment_session: Pubkey,
+    pub settlement_authority: Pubkey,
+    pub escrow_ata: Pubkey,
+    pub native: bool,               // lamports are escrowed on the settlement authority
+}
+
+pub struct TestContext {
+    pub svm: LiteSVM,
+    pub payer: Keypair,
+    pub mint: Pubkey,
+    pub payer_ata: Pubkey,
+    pub merchant: Keypair,          // authority of the MERCHANT_ID rewards record
+    pub tip_wallet: Pubkey,         // tip wallet registered on that record
+    pub payout_wallet: Pubkey,      // BitPay payout wallet registered on that record
+    next_uuid: u128,
+}
+
+impl TestContext {
+    // Fresh SVM with a funded payer, a mint, the program config initialized
+    // with the payer as the only operator (1 of 1), and a MERCHANT_ID record.
+    pub fn new() -> Self {
+        Self::with_operators(&[], 1)
+    }
+
+    // Same as `new`, with `extra_operators` added to the operator set after the
+    // payer and an M-of-N `threshold`.
+    pub fn with_operators(extra_operators: &[Pubkey], threshold: u8) -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(capstone_ethanbackhus::ID, PROGRAM_SO)
+            .expect("run `anchor build` before the LiteSVM tests");
+
+        let payer = Keypair::new();
+        svm.airdrop(&payer.pubkey(), 100_000_000_000).unwrap();
+
+        let mint = CreateMint::new(&mut svm, &payer)
+            .decimals(DECIMALS)
+            .send()
+            .unwrap();
+
+        let payer_ata = CreateAssociatedTokenAccount::new(&mut svm, &payer, &mint)
+            .send()
+            .unwrap();
+
+        MintTo::new(&mut svm, &payer, &mint, &payer_ata, PAYER_BALANCE)
+            .send()
+            .unwrap();
+
+        let merchant = Keypair::new();
+        let tip_wallet = Keypair::new().pubkey();
+        svm.airdrop(&tip_wallet, 1_000_000_000).unwrap();
+        let payout_wallet = Keypair::new().pubkey();
+        svm.airdrop(&payout_wallet, 1_000_000_000).unwrap();
+
+        let mut ctx = Self { svm, payer, mint, payer_ata, merchant, tip_wallet, payout_wallet, next_uuid: 1 };
+
+        let mut operators = vec![ctx.payer.pubkey()];
+        operators.extend_from_slice(extra_operators);
+        ctx.write_config(operators, threshold);
+
+        let ix = ctx.init_merchant_rewards_ix(&ctx.merchant.pubkey(), 0);
+        ctx.send(&[ix], &[]).unwrap();
+
+        ctx
+    }
+
+    // Signs with the payer plus any extra signers. The blockhash is expired
+    // first so repeating an identical instruction is not deduplicated.
+    #[allow(clippy::result_large_err)] // LiteSVM's own result type
+    pub fn send(&mut self, ixs: &[Instruction], extra_signers: &[&Keypair]) -> TransactionResult {
+        self.svm.expire_blockhash();
+
+        let mut signers: Vec<&Keypair> = vec![&self.payer];
+        signers.extend_from_slice(extra_signers);
+
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&self.payer.pubkey()),
+            &signers,
+            self.svm.latest_blockhash(),
+        );
+
+        self.svm.send_transaction(tx)
+    }
+
+    pub fn new_session(&mut self) -> Session {
+        let uuid = self.next_uuid.to_le_bytes();
+        self.next_uuid += 1;
+        Self::session_for(&self.payer.pubkey(), &self.mint, uuid)
+    }
+
+    pub fn new_native_session(&mut self) -> Session {
+        Session { native: true, ..self.new_session() }
+    }
+
+    pub fn session_for(payer: &Pubkey, mint: &Pubkey, uuid: [u8; 16]) -> Session {
+        let (payment_session, _) = Pubkey::find_program_address(
+            &[b"payment_session", payer.as_ref(), uuid.as_ref()],
+            &capstone_ethanbackhus::ID,
+        );
+        let (settlement_authority, _) = Pubkey::find_program_address(
+            &[b"settlement_authority", payment_session.as_ref(), uuid.as_ref()],
+            &capstone_ethanbackhus::ID,
+        );
+        let escrow_ata = get_associated_token_address(&settlement_authority, mint);
+
+        Session { uuid, payment_session, settlement_authority, escrow_ata, native: false }
+    }
+
+    // Initializes and funds a session, returning its handle
+    pub fn funded_session(&mut self, amount: u64, tip_amount: u64) -> Session {
+        let session = self.new_session();
+        let init = self.init_session_ix(&session, amount);
+        let deposit = self.deposit_ix(&session, tip_amount);
+        self.send(&[init], &[]).unwrap();
+        self.send(&[deposit], &[]).unwrap();
+        session
+    }
+
+    pub fn funded_native_session(&mut self, amount: u64, tip_amount: u64) -> Session {
+        let session = self.new_native_session();
+        let init = self.init_session_ix(&session, amount);
+        let deposit = self.deposit_sol_ix(&session, tip_amount);
+        self.send(&[init], &[]).unwrap();
+        self.send(&[deposit], &[]).unwrap();
+        session
+    }
+
+    pub fn funded_keypair(&mut self) -> Keypair {
+        let keypair = Keypair::new();
+        self.svm.airdrop(&keypair.pubkey(), 10_000_000_000).unwrap();
+        keypair
+    }
+
+    pub fn create_ata(&mut self, owner: &Pubkey) -> Pubkey {
+        let mint = self.mint;
+        self.create_ata_for(&mint, owner)
+    }
+
+    pub fn create_ata_for(&mut self, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+        CreateAssociatedTokenAccount::new(&mut self.svm, &self.payer, mint)
+            .owner(owner)
+            .send()
+            .unwrap()
+    }
+
+    // Mints payment tokens straight into `ata`, the payer is the mint authority
+    pub fn mint_to(&mut self, ata: &Pubkey, amount: u64) {
+        MintTo::new(&mut self.svm, &self.payer, &self.mint, ata, amount)
+            .send()
+            .unwrap();
+    }
+
+    // Token account of the registered tip wallet
+    pub fn tip_ata(&mut self) -> Pubkey {
+        let tip_wallet = self.tip_wallet;
+        self.create_ata(&tip_wallet)
+    }
+
+    // Token account of the registered payout wallet, created on first use
+    pub fn bitpay_ata(&mut self) -> Pubkey {
+        let bitpay_ata = get_associated_token_address(&self.payout_wallet, &self.mint);
+        if self.svm.get_account(&bitpay_ata).is_some() {
+            return bitpay_ata;
+        }
+
+        let payout_wallet = self.payout_wallet;
+        self.create_ata(&payout_wallet)
+    }
+
+    pub fn token_balance(&self, ata: &Pubkey) -> u64 {
+        let account = self.svm.get_account(ata).expect("token account missing");
+        TokenAccount::try_deserialize(&mut account.data.as_slice()).unwrap().amount
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.svm.get_account(key).map_or(0, |account| account.lamports)
+    }
+
+    // Account data for each key, None for missing or closed accounts
+    pub fn fetch_accounts(&self, keys: &[Pubkey]) -> Vec<Option<Vec<u8>>> {
+        keys.iter()
+            .map(|key| self.svm.get_account(key).filter(|account| account.lamports > 0).map(|account| account.data))
+            .collect()
+    }
+
+    pub fn payment_session(&self, session: &Session) -> PaymentSession {
+        let account = self.svm.get_account(&session.payment_session).expect("session missing");
+        bytemuck::pod_read_unaligned(&account.data[PaymentSession::DISCRIMINATOR.len()..])
+    }
+
+    pub fn settlement_proposal(&self, session: &Session) -> SettlementProposal {
+        let account = self.svm
+            .get_account(&settlement_proposal_pda(&session.payment_session))
+            .expect("proposal missing");
+        SettlementProposal::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub fn warp_seconds(&mut self, seconds: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar::<Clock>(&clock);
+    }
+
+    // init_config is gated on the program's upgrade authority, and programs
+    // loaded into LiteSVM have none, so the config is written directly.
+    pub fn write_config(&mut self, operators: Vec<Pubkey>, threshold: u8) {
+        let (config, bump) = Pubkey::find_program_address(&[b"config"], &capstone_ethanbackhus::ID);
+
+        let state = ProgramConfig {
+            admin: self.payer.pubkey(),
+            operators,
+            threshold,
+            token_limits: token_limits(),
+            native_limits: native_limits(),
+            max_cashback_bps: DEFAULT_MAX_CASHBACK_BPS,
+            bump,
+        };
+
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        data.resize(ProgramConfig::DISCRIMINATOR.len() + ProgramConfig::INIT_SPACE, 0);
+
+        self.set_program_account(config, capstone_ethanbackhus::ID, data);
+    }
+
+    // Writes an upgradeable-loader ProgramData account naming `authority` as
+    // the upgrade authority.
+    pub fn write_program_data(&mut self, authority: &Pubkey) {
+        let state = UpgradeableLoaderState::ProgramData {
+            slot: 0,
+            upgrade_authority_address: Some(*authority),
+        };
+
+        self.set_program_account(program_data_address(), bpf_loader_upgradeable::ID, bincode::serialize(&state).unwrap());
+    }
+
+    fn set_program_account(&mut self, key: Pubkey, owner: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+
+        self.svm.set_account(key, account).unwrap();
+    }
+
+    pub fn init_config_ix(&self, operators: Vec<Pubkey>, threshold: u8) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::InitConfig {
+                admin: self.payer.pubkey(),
+                config: config_pda(),
+                program: capstone_ethanbackhus::ID,
+                program_data: program_data_address(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::InitConfig {
+                operators,
+                threshold,
+                token_limits: token_limits(),
+                native_limits: native_limits(),
+            }
+            .data(),
+        }
+    }
+
+    pub fn init_session_ix(&self, session: &Session, amount: u64) -> Instruction {
+        let merchant_index = SessionIndexKey::Merchant(MERCHANT_ID.to_string());
+        let payer_index = SessionIndexKey::Payer(self.payer.pubkey());
+        let merchant_page = next_index_page(&merchant_index, &mut |keys| self.fetch_accounts(keys)).unwrap();
+        let payer_page = next_index_page(&payer_index, &mut |keys| self.fetch_accounts(keys)).unwrap();
+
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::InitPaymentSession {
+                payer: self.payer.pubkey(),
+                token_mint: (!session.native).then_some(self.mint),
+                payer_ata: (!session.native).then_some(self.payer_ata),
+                payment_session: session.payment_session,
+                escrow_ata: (!session.native).then_some(session.escrow_ata),
+                settlement_authority: session.settlement_authority,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                merchant_session_index: merchant_index.page_address(merchant_page.page),
+                previous_merchant_session_index: merchant_page.previous,
+                payer_session_index: payer_index.page_address(payer_page.page),
+                previous_payer_session_index: payer_page.previous,
+                token_program: token::ID,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::InitPaymentSession {
+                uuid: session.uuid,
+                merchant_id: MERCHANT_ID.to_string(),
+                amount,
+                reference_id: "Ref12345".to_string(),
+                fiat_currencty: "USD".to_string(),
+                merchant_bank: "Bank of America".to_string(),
+                merchant_index_page: merchant_page.page,
+                payer_index_page: payer_page.page,
+            }
+            .data(),
+        }
+    }
+
+    pub fn deposit_ix(&self, session: &Session, tip_amount: u64) -> Instruction {
+        self.deposit_with_loyalty_ix(session, tip_amount, 0, None)
+    }
+
+    // Deposit that burns `redeem_amount` loyalty tokens from `payer_loyalty_ata`
+    pub fn redeem_deposit_ix(&self, session: &Session, redeem_amount: u64, payer_loyalty_ata: Pubkey) -> Instruction {
+        self.deposit_with_loyalty_ix(session, 0, redeem_amount, Some(payer_loyalty_ata))
+    }
+
+    fn deposit_with_loyalty_ix(
+        &self,
+        session: &Session,
+        tip_amount: u64,
+        redeem_amount: u64,
+        payer_loyalty_ata: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::DepositStablecoin {
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                payer_ata: self.payer_ata,
+                escrow_ata: session.escrow_ata,
+                settlement_authority: session.settlement_authority,
+                config: config_pda(),
+                payer_profile: payer_profile_pda(&self.payer.pubkey(), &self.mint),
+                loyalty_mint: payer_loyalty_ata.map(|_| loyalty_mint_pda(&self.mint)),
+                payer_loyalty_ata,
+                token_mint: self.mint,
+                token_program: token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::DepositStablecoin { tip_amount, redeem_amount }.data(),
+        }
+    }
+
+    pub fn refund_ix(&self, session: &Session) -> Instruction {
+        self.refund_with_loyalty_ix(session, None)
+    }
+
+    // Refund that restores a redeemed discount to `payer_loyalty_ata`
+    pub fn refund_with_loyalty_ix(&self, session: &Session, payer_loyalty_ata: Option<Pubkey>) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::RefundPayment {
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                payer_ata: self.payer_ata,
+                escrow_ata: session.escrow_ata,
+                settlement_authority: session.settlement_authority,
+                config: payer_loyalty_ata.map(|_| config_pda()),
+                loyalty_mint: payer_loyalty_ata.map(|_| loyalty_mint_pda(&self.mint)),
+                payer_loyalty_ata,
+                token_mint: self.mint,
+                token_program: token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::RefundPayment {}.data(),
+        }
+    }
+
+    pub fn settle_ix(&self, session: &Session, bitpay_ata: Pubkey, tip_ata: Option<Pubkey>) -> Instruction {
+        self.settle_with_loyalty_ix(session, bitpay_ata, tip_ata, None)
+    }
+
+    // Settlement that mints the merchant's cashback to `payer_loyalty_ata`
+    pub fn settle_with_cashback_ix(&self, session: &Session, bitpay_ata: Pubkey, payer_loyalty_ata: Pubkey) -> Instruction {
+        self.settle_with_loyalty_ix(session, bitpay_ata, None, Some(payer_loyalty_ata))
+    }
+
+    fn settle_with_loyalty_ix(
+        &self,
+        session: &Session,
+        bitpay_ata: Pubkey,
+        tip_ata: Option<Pubkey>,
+        payer_loyalty_ata: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::MarkPaymentSettled {
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                config: config_pda(),
+                payer_ata: self.payer_ata,
+                escrow_ata: session.escrow_ata,
+                settlement_authority: session.settlement_authority,
+                bitpay_ata,
+                tip_ata,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                loyalty_mint: payer_loyalty_ata.map(|_| loyalty_mint_pda(&self.mint)),
+                payer_loyalty_ata,
+                token_mint: self.mint,
+                token_program: token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::MarkPaymentSettled {}.data(),
+        }
+    }
+
+    pub fn deposit_sol_ix(&self, session: &Session, tip_amount: u64) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::DepositSol {
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                settlement_authority: session.settlement_authority,
+                config: config_pda(),
+                payer_profile: payer_profile_pda(&self.payer.pubkey(), &Pubkey::default()),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::DepositSol { tip_amount }.data(),
+        }
+    }
+
+    pub fn refund_sol_ix(&self, session: &Session) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::RefundSol {
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                settlement_authority: session.settlement_authority,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::RefundSol {}.data(),
+        }
+    }
+
+    pub fn settle_sol_ix(&self, session: &Session, bitpay_wallet: Pubkey, tip_wallet: Option<Pubkey>) -> Instruction {
+        self.settle_sol_with_loyalty_ix(session, bitpay_wallet, tip_wallet, None)
+    }
+
+    // Native settlement that mints the merchant's wrapped SOL cashback to
+    // `payer_loyalty_ata`
+    pub fn settle_sol_with_cashback_ix(&self, session: &Session, bitpay_wallet: Pubkey, payer_loyalty_ata: Pubkey) -> Instruction {
+        self.settle_sol_with_loyalty_ix(session, bitpay_wallet, None, Some(payer_loyalty_ata))
+    }
+
+    fn settle_sol_with_loyalty_ix(
+        &self,
+        session: &Session,
+        bitpay_wallet: Pubkey,
+        tip_wallet: Option<Pubkey>,
+        payer_loyalty_ata: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::SettleSol {
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                config: config_pda(),
+                settlement_authority: session.settlement_authority,
+                bitpay_wallet,
+                tip_wallet,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                loyalty_mint: payer_loyalty_ata.map(|_| loyalty_mint_pda(&native_mint::ID)),
+                payer_loyalty_ata,
+                token_program: token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::SettleSol {}.data(),
+        }
+    }
+
+    // Schedule co-signed by the payer, who is also the operator
+    pub fn set_milestones_ix(&self, session: &Session, milestones: Vec<MilestoneInput>) -> Instruction {
+        self.set_milestones_with_ix(&self.payer.pubkey(), session, milestones, false)
+    }
+
+    // `with_merchant` passes the MERCHANT_ID record, for schedules the merchant co-signs
+    pub fn set_milestones_with_ix(
+        &self,
+        authority: &Pubkey,
+        session: &Session,
+        milestones: Vec<MilestoneInput>,
+        with_merchant: bool,
+    ) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::SetMilestoneSchedule {
+                payer: self.payer.pubkey(),
+                authority: *authority,
+                payment_session: session.payment_session,
+                config: config_pda(),
+                merchant_rewards: with_merchant.then(|| merchant_rewards_pda(MERCHANT_ID)),
+            }
+            .to_account_metas(None),
+            data: instruction::SetMilestoneSchedule { milestones }.data(),
+        }
+    }
+
+    pub fn release_milestone_ix(
+        &self,
+        authority: &Pubkey,
+        session: &Session,
+        bitpay_ata: Pubkey,
+        tip_ata: Option<Pubkey>,
+    ) -> Instruction {
+        self.release_approved_milestone_ix(authority, session, bitpay_ata, tip_ata, false)
+    }
+
+    // Release that passes the session's settlement proposal when `approved`
+    pub fn release_approved_milestone_ix(
+        &self,
+        authority: &Pubkey,
+        session: &Session,
+        bitpay_ata: Pubkey,
+        tip_ata: Option<Pubkey>,
+        approved: bool,
+    ) -> Instruction {
+        self.release_milestone_with_loyalty_ix(authority, session, bitpay_ata, tip_ata, approved, None)
+    }
+
+    // Release that mints the merchant's cashback to `payer_loyalty_ata` on the final milestone
+    pub fn release_milestone_with_cashback_ix(
+        &self,
+        authority: &Pubkey,
+        session: &Session,
+        bitpay_ata: Pubkey,
+        payer_loyalty_ata: Pubkey,
+    ) -> Instruction {
+        self.release_milestone_with_loyalty_ix(authority, session, bitpay_ata, None, false, Some(payer_loyalty_ata))
+    }
+
+    fn release_milestone_with_loyalty_ix(
+        &self,
+        authority: &Pubkey,
+        session: &Session,
+        bitpay_ata: Pubkey,
+        tip_ata: Option<Pubkey>,
+        approved: bool,
+        payer_loyalty_ata: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::ReleaseMilestone {
+                authority: *authority,
+                payment_session: session.payment_session,
+                config: config_pda(),
+                settlement_proposal: approved.then(|| settlement_proposal_pda(&session.payment_session)),
+                escrow_ata: session.escrow_ata,
+                settlement_authority: session.settlement_authority,
+                bitpay_ata,
+                tip_ata,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                loyalty_mint: payer_loyalty_ata.map(|_| loyalty_mint_pda(&self.mint)),
+                payer_loyalty_ata,
+                token_mint: self.mint,
+                token_program: token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ReleaseMilestone {}.data(),
+        }
+    }
+
+    pub fn propose_ix(&self, operator: &Pubkey, session: &Session, destination: Pubkey) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::ProposeSettlement {
+                operator: *operator,
+                config: config_pda(),
+                payment_session: session.payment_session,
+                settlement_proposal: settlement_proposal_pda(&session.payment_session),
+                destination,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ProposeSettlement {}.data(),
+        }
+    }
+
+    // Cancel signed by `operator` and every key in `co_signers`
+    pub fn cancel_proposal_ix(
+        &self,
+        operator: &Pubkey,
+        proposer: &Pubkey,
+        session: &Session,
+        co_signers: &[Pubkey],
+    ) -> Instruction {
+        let mut accounts = accounts::CancelSettlementProposal {
+            operator: *operator,
+            config: config_pda(),
+            settlement_proposal: settlement_proposal_pda(&session.payment_session),
+            proposer: *proposer,
+        }
+        .to_account_metas(None);
+        accounts.extend(co_signers.iter().map(|co_signer| AccountMeta::new_readonly(*co_signer, true)));
+
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts,
+            data: instruction::CancelSettlementProposal {}.data(),
+        }
+    }
+
+    pub fn approve_ix(&self, operator: &Pubkey, session: &Session) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::ApproveSettlement {
+                operator: *operator,
+                config: config_pda(),
+                settlement_proposal: settlement_proposal_pda(&session.payment_session),
+            }
+            .to_account_metas(None),
+            data: instruction::ApproveSettlement {}.data(),
+        }
+    }
+
+    pub fn revoke_ix(&self, operator: &Pubkey, session: &Session) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::RevokeSettlementApproval {
+                operator: *operator,
+                config: config_pda(),
+                settlement_proposal: settlement_proposal_pda(&session.payment_session),
+            }
+            .to_account_metas(None),
+            data: instruction::RevokeSettlementApproval {}.data(),
+        }
+    }
+
+    pub fn execute_ix(&self, operator: &Pubkey, session: &Session, bitpay_ata: Pubkey) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::ExecuteSettlement {
+                operator: *operator,
+                config: config_pda(),
+                payment_session: session.payment_session,
+                settlement_proposal: settlement_proposal_pda(&session.payment_session),
+                escrow_ata: session.escrow_ata,
+                settlement_authority: session.settlement_authority,
+                bitpay_ata,
+                tip_ata: None,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                loyalty_mint: None,
+                payer_loyalty_ata: None,
+                token_mint: self.mint,
+                token_program: token::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ExecuteSettlement {}.data(),
+        }
+    }
+
+    pub fn execute_sol_ix(
+        &self,
+        operator: &Pubkey,
+        session: &Session,
+        bitpay_wallet: Pubkey,
+        tip_wallet: Option<Pubkey>,
+    ) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::ExecuteSettlementSol {
+                operator: *operator,
+                config: config_pda(),
+                payment_session: session.payment_session,
+                settlement_proposal: settlement_proposal_pda(&session.payment_session),
+                settlement_authority: session.settlement_authority,
+                bitpay_wallet,
+                tip_wallet,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                loyalty_mint: None,
+                payer_loyalty_ata: None,
+                payer: self.payer.pubkey(),
+                token_program: token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::ExecuteSettlementSol {}.data(),
+        }
+    }
+
+    pub fn init_merchant_rewards_ix(&self, authority: &Pubkey, cashback_bps: u16) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::InitMerchantRewards {
+                operator: self.payer.pubkey(),
+                config: config_pda(),
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::InitMerchantRewards {
+                merchant_id: MERCHANT_ID.to_string(),
+                authority: *authority,
+                tip_wallet: self.tip_wallet,
+                payout_wallet: self.payout_wallet,
+                cashback_bps,
+            }
+            .data(),
+        }
+    }
+
+    pub fn init_loyalty_mint_ix(&self, authority: &Pubkey, token_mint: &Pubkey) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::InitLoyaltyMint {
+                authority: *authority,
+                config: config_pda(),
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                token_mint: *token_mint,
+                loyalty_mint: loyalty_mint_pda(token_mint),
+                token_program: token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::InitLoyaltyMint {}.data(),
+        }
+    }
+
+    pub fn set_cashback_rate_ix(&self, authority: &Pubkey, cashback_bps: u16) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::SetCashbackRate {
+                authority: *authority,
+                merchant_rewards: merchant_rewards_pda(MERCHANT_ID),
+                config: config_pda(),
+            }
+            .to_account_metas(None),
+            data: instruction::SetCashbackRate { cashback_bps }.data(),
+        }
+    }
+
+    pub fn set_max_cashback_rate_ix(&self, admin: &Pubkey, max_cashback_bps: u16) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::SetMaxCashbackRate {
+                admin: *admin,
+                config: config_pda(),
+            }
+            .to_account_metas(None),
+            data: instruction::SetMaxCashbackRate { max_cashback_bps }.data(),
+        }
+    }
+
+    // `with_merchant` passes the MERCHANT_ID record, for cancels by the merchant
+    pub fn cancel_ix(&self, authority: &Pubkey, session: &Session, with_merchant: bool) -> Instruction {
+
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::CancelSession {
+                authority: *authority,
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                token_mint: (!session.native).then_some(self.mint),
+                escrow_ata: (!session.native).then_some(session.escrow_ata),
+                payer_ata: (!session.native).then_some(self.payer_ata),
+                settlement_authority: session.settlement_authority,
+                config: config_pda(),
+                merchant_rewards: with_merchant.then(|| merchant_rewards_pda(MERCHANT_ID)),
+                token_program: token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::CancelSession {}.data(),
+        }
+    }
+
+    pub fn migrate_ix(&self, session: &Session) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::MigrateSession {
+                rent_payer: self.payer.pubkey(),
+                payer: self.payer.pubkey(),
+                payment_session: session.payment_session,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::MigrateSession {}.data(),
+        }
+    }
+
+    pub fn update_spending_limits_ix(&self, native: bool, daily_limit: u64, monthly_limit: u64) -> Instruction {
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::UpdateSpendingLimits {
+                admin: self.payer.pubkey(),
+                config: config_pda(),
+            }
+            .to_account_metas(None),
+            data: instruction::UpdateSpendingLimits { native, daily_limit, monthly_limit }.data(),
+        }
+    }
+
+    // Limits for the payer's token profile, or the native SOL one when `native`
+    pub fn set_payer_limits_ix(&self, operator: &Pubkey, native: bool, daily_limit: u64, monthly_limit: u64) -> Instruction {
+        let token_mint = (!native).then_some(self.mint);
+
+        Instruction {
+            program_id: capstone_ethanbackhus::ID,
+            accounts: accounts::SetPayerLimits {
+                operator: *operator,
+                config: config_pda(),
+                payer: self.payer.pubkey(),
+                token_mint,
+                payer_profile: payer_profile_pda(&self.payer.pubkey(), &token_mint.unwrap_or_default()),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::SetPayerLimits { daily_limit, monthly_limit }.data(),
+        }
+    }
+}
+
+impl Default for TestContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &capstone_ethanbackhus::ID).0
+}
+
+pub fn program_data_address() -> Pubkey {
+    bpf_loader_upgradeable::get_program_data_address(&capstone_ethanbackhus::ID)
+}
+
+pub fn payer_profile_pda(payer: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"payer_profile", payer.as_ref(), mint.as_ref()],
+        &capstone_ethanbackhus::ID,
+    )
+    .0
+}
+
+pub fn merchant_rewards_pda(merchant_id: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"merchant_rewards", merchant_id_hash(merchant_id).as_ref()],
+        &capstone_ethanbackhus::ID,
+    )
+    .0
+}
+
+// Loyalty mint of the MERCHANT_ID merchant for `token_mint`
+pub fn loyalty_mint_pda(token_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"loyalty_mint", merchant_id_hash(MERCHANT_ID).as_ref(), token_mint.as_ref()],
+        &capstone_ethanbackhus::ID,
+    )
+    .0
+}
+
+pub fn settlement_proposal_pda(payment_session: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"settlement_proposal", payment_session.as_ref()],
+        &capstone_ethanbackhus::ID,
+    )
+    .0
+}
+
+// Asserts that the transaction failed with the given custom error code
+// (Anchor framework errors or `PaymentError` variants via `u32::from`).
+pub fn assert_error(result: TransactionResult, code: u32) {
+    match result {
+        Ok(_) => panic!("expected error {code}, transaction succeeded"),
+        Err(failed) => match failed.err {
+            TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+                assert_eq!(actual, code, "logs: {:#?}", failed.meta.logs)
+            }
+            other => panic!("expected error {code}, got {other:?}"),
+        },
+    }
+}
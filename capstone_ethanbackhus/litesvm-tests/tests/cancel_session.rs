@@ -0,0 +1,151 @@
+use anchor_lang::{error::ErrorCode, InstructionData};
+use capstone_ethanbackhus::errors::PaymentError;
+use capstone_ethanbackhus_litesvm_tests::*;
+use litesvm_token::CreateMint;
+use solana_signer::Signer;
+
+fn initialized_session(ctx: &mut TestContext) -> Session {
+    let session = ctx.new_session();
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+    session
+}
+
+#[test]
+fn operator_cancel_closes_session_and_escrow() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+    let operator = ctx.payer.pubkey();
+
+    let ix = ctx.cancel_ix(&operator, &session, false);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.lamports(&session.payment_session), 0);
+    assert_eq!(ctx.lamports(&session.escrow_ata), 0);
+}
+
+#[test]
+fn cancel_returns_stray_escrow_tokens_to_payer() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+    let operator = ctx.payer.pubkey();
+
+    // tokens sent to the escrow without going through deposit
+    ctx.mint_to(&session.escrow_ata, 7);
+
+    let ix = ctx.cancel_ix(&operator, &session, false);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE + 7);
+    assert_eq!(ctx.lamports(&session.escrow_ata), 0);
+}
+
+#[test]
+fn cancel_rejects_a_token_account_other_than_the_escrow() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+    let operator = ctx.payer.pubkey();
+
+    // owned by the settlement authority, but not its ATA for the session mint
+    let other_mint = CreateMint::new(&mut ctx.svm, &ctx.payer)
+        .decimals(DECIMALS)
+        .send()
+        .unwrap();
+    let other_ata = ctx.create_ata_for(&other_mint, &session.settlement_authority);
+
+    let mut ix = ctx.cancel_ix(&operator, &session, false);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == session.escrow_ata) {
+        meta.pubkey = other_ata;
+    }
+    assert_error(ctx.send(&[ix], &[]), ErrorCode::ConstraintAssociated.into());
+    assert!(ctx.lamports(&session.payment_session) > 0);
+}
+
+#[test]
+fn merchant_can_cancel_own_session() {
+    let mut ctx = TestContext::new();
+    let merchant = ctx.merchant.insecure_clone();
+
+    let session = initialized_session(&mut ctx);
+    let ix = ctx.cancel_ix(&merchant.pubkey(), &session, true);
+    ctx.send(&[ix], &[&merchant]).unwrap();
+
+    assert_eq!(ctx.lamports(&session.payment_session), 0);
+}
+
+#[test]
+fn stranger_cannot_cancel() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+    let intruder = ctx.funded_keypair();
+
+    let ix = ctx.cancel_ix(&intruder.pubkey(), &session, false);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedCancel.into());
+}
+
+#[test]
+fn non_authority_cannot_cancel_with_merchant_record() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+
+    // passing the merchant record without being its authority does not help
+    let intruder = ctx.funded_keypair();
+    let ix = ctx.cancel_ix(&intruder.pubkey(), &session, true);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedCancel.into());
+}
+
+#[test]
+fn funded_session_cannot_be_cancelled() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let operator = ctx.payer.pubkey();
+
+    let ix = ctx.cancel_ix(&operator, &session, false);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPaymentSessionState.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn deposit_after_cancel_fails() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+    let operator = ctx.payer.pubkey();
+
+    let cancel = ctx.cancel_ix(&operator, &session, false);
+    ctx.send(&[cancel], &[]).unwrap();
+
+    // anyone can recreate the closed escrow ATA, the closed session still refuses funds
+    let mint = ctx.mint;
+    ctx.create_ata_for(&mint, &session.settlement_authority);
+
+    let deposit = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[deposit], &[]), ErrorCode::AccountNotInitialized.into());
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE);
+}
+
+#[test]
+fn another_merchants_record_cannot_cancel() {
+    let mut ctx = TestContext::new();
+    let session = initialized_session(&mut ctx);
+    let intruder = ctx.funded_keypair();
+
+    // a record the intruder controls, registered under a different merchant id
+    let mut register = ctx.init_merchant_rewards_ix(&intruder.pubkey(), 0);
+    register.accounts[2].pubkey = merchant_rewards_pda("other-merchant");
+    register.data = capstone_ethanbackhus::instruction::InitMerchantRewards {
+        merchant_id: "other-merchant".to_string(),
+        authority: intruder.pubkey(),
+        tip_wallet: ctx.tip_wallet,
+        payout_wallet: ctx.payout_wallet,
+        cashback_bps: 0,
+    }
+    .data();
+    ctx.send(&[register], &[]).unwrap();
+
+    let mut ix = ctx.cancel_ix(&intruder.pubkey(), &session, true);
+    let merchant_rewards = merchant_rewards_pda(MERCHANT_ID);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == merchant_rewards) {
+        meta.pubkey = merchant_rewards_pda("other-merchant");
+    }
+    assert_error(ctx.send(&[ix], &[&intruder]), ErrorCode::ConstraintSeeds.into());
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+
+use anchor_lang::{Discriminator, Space};
+use capstone_ethanbackhus::state::{LegacyPaymentSession, PaymentSession};
+use capstone_ethanbackhus_litesvm_tests::*;
+
+// Every session instruction fits in the default per-instruction budget.
+const DEFAULT_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+
+// Compute units per instruction measured on the Borsh PaymentSession, before
+// the move to zero-copy. See the file header for how it is recorded.
+const BASELINE: &str = include_str!("../compute_units.baseline");
+
+fn read_baseline() -> BTreeMap<&'static str, u64> {
+    BASELINE
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, units) = line.split_once(' ').expect("baseline lines are `name units`");
+            (name, units.trim().parse().expect("invalid baseline entry"))
+        })
+        .collect()
+}
+
+fn measure_session_lifecycle() -> BTreeMap<String, u64> {
+    let mut ctx = TestContext::new();
+    let bitpay_ata = ctx.bitpay_ata();
+    let tip_ata = ctx.tip_ata();
+    let mut measured = BTreeMap::new();
+
+    let session = ctx.new_session();
+    let steps = [
+        ("init_payment_session", ctx.init_session_ix(&session, 100)),
+        ("deposit_stablecoin", ctx.deposit_ix(&session, 5)),
+        ("mark_payment_settled", ctx.settle_ix(&session, bitpay_ata, Some(tip_ata))),
+    ];
+    for (name, ix) in steps {
+        let meta = ctx.send(&[ix], &[]).unwrap();
+        measured.insert(name.to_string(), meta.compute_units_consumed);
+    }
+
+    let session = ctx.funded_session(100, 0);
+    let refund = ctx.refund_ix(&session);
+    let meta = ctx.send(&[refund], &[]).unwrap();
+    measured.insert("refund_payment".to_string(), meta.compute_units_consumed);
+
+    measured
+}
+
+// Prints the before/after table, run with `-- --nocapture` to see it. The
+// `after` column is in baseline format, so a new baseline can be pasted from it.
+#[test]
+fn session_lifecycle_compute_units() {
+    let measured = measure_session_lifecycle();
+    let baseline = read_baseline();
+
+    println!("{:<24} {:>8} {:>8} {:>8}", "instruction", "before", "after", "change");
+    for (name, after) in &measured {
+        match baseline.get(name.as_str()) {
+            Some(before) => {
+                let change = *after as i64 - *before as i64;
+                println!("{name:<24} {before:>8} {after:>8} {change:>+8}");
+            }
+            None => println!("{name:<24} {:>8} {after:>8} {:>8}", "-", "-"),
+        }
+    }
+
+    for (name, units) in &measured {
+        assert!(*units < DEFAULT_COMPUTE_UNIT_LIMIT, "{name} used {units} CU");
+    }
+
+    // no instruction may cost more than it did on the Borsh layout
+    assert!(!baseline.is_empty(), "compute_units.baseline has no entries, record them first");
+    for (name, before) in &baseline {
+        let after = measured.get(*name).unwrap_or_else(|| panic!("{name} is in the baseline but was not measured"));
+        assert!(after <= before, "{name} regressed from {before} to {after} CU");
+    }
+}
+
+#[test]
+fn zero_copy_session_needs_less_rent() {
+    let ctx = TestContext::new();
+
+    let current_len = PaymentSession::DISCRIMINATOR.len() + PaymentSession::INIT_SPACE;
+    let current_rent = ctx.svm.minimum_balance_for_rent_exemption(current_len);
+
+    // the Borsh session as deployed
+    let legacy_len = PaymentSession::DISCRIMINATOR.len() + LegacyPaymentSession::INIT_SPACE;
+    let legacy_rent = ctx.svm.minimum_balance_for_rent_exemption(legacy_len);
+
+    assert!(current_len < legacy_len, "{current_len} bytes vs {legacy_len} bytes");
+    assert!(current_rent < legacy_rent, "{current_rent} lamports vs {legacy_rent} lamports");
+}
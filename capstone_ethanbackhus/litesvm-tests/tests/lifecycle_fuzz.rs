@@ -0,0 +1,190 @@
+//! Random sequences of init, deposit, refund, settle and expire steps run
+//! against the program. After every step the outcome and the escrow balances
+//! must match a simple model of the session lifecycle, and no tokens may be
+//! created or destroyed.
+
+use anchor_lang::prelude::{Clock, Pubkey};
+use capstone_ethanbackhus::state::PaymentSessionStatus;
+use capstone_ethanbackhus_litesvm_tests::*;
+use proptest::prelude::*;
+use solana_signer::Signer;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Init { amount: u64 },
+    Deposit { session: usize, tip_amount: u64 },
+    Refund { session: usize },
+    Settle { session: usize },
+    // moves the clock past the expiry of every open session
+    Expire,
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        (1..=LARGE_SETTLEMENT_THRESHOLD / 2).prop_map(|amount| Step::Init { amount }),
+        (any::<usize>(), 0..=50u64)
+            .prop_map(|(session, tip_amount)| Step::Deposit { session, tip_amount }),
+        any::<usize>().prop_map(|session| Step::Refund { session }),
+        any::<usize>().prop_map(|session| Step::Settle { session }),
+        Just(Step::Expire),
+    ]
+}
+
+// Expected state of one session, built only from the steps that succeeded
+struct Tracked {
+    session: Session,
+    amount: u64,
+    tip_amount: u64,
+    status: PaymentSessionStatus,
+    expiry_ts: i64,
+    received: u64,
+    refunded: u64,
+    settled: u64,
+}
+
+impl Tracked {
+    fn escrowed(&self) -> u64 {
+        self.received - self.refunded - self.settled
+    }
+}
+
+struct Harness {
+    ctx: TestContext,
+    bitpay_ata: Pubkey,
+    tip_ata: Pubkey,
+    sessions: Vec<Tracked>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let mut ctx = TestContext::new();
+
+        // keep the velocity limits out of the way of long sequences
+        let operator = ctx.payer.pubkey();
+        let ix = ctx.set_payer_limits_ix(&operator, false, MONTHLY_LIMIT, MONTHLY_LIMIT);
+        ctx.send(&[ix], &[]).unwrap();
+
+        let bitpay_ata = ctx.bitpay_ata();
+        let tip_ata = ctx.tip_ata();
+
+        Self { ctx, bitpay_ata, tip_ata, sessions: Vec::new() }
+    }
+
+    fn now(&self) -> i64 {
+        self.ctx.svm.get_sysvar::<Clock>().unix_timestamp
+    }
+
+    fn run(&mut self, step: &Step) {
+        match *step {
+            Step::Init { amount } => {
+                let session = self.ctx.new_session();
+                let ix = self.ctx.init_session_ix(&session, amount);
+                self.ctx.send(&[ix], &[]).unwrap();
+
+                let expiry_ts = self.ctx.payment_session(&session).expiry_ts;
+                self.sessions.push(Tracked {
+                    session,
+                    amount,
+                    tip_amount: 0,
+                    status: PaymentSessionStatus::Initialized,
+                    expiry_ts,
+                    received: 0,
+                    refunded: 0,
+                    settled: 0,
+                });
+            }
+            Step::Deposit { session, tip_amount } => {
+                let Some(index) = self.pick(session) else { return };
+                let tracked = &self.sessions[index];
+                let allowed = tracked.status == PaymentSessionStatus::Initialized
+                    && self.now() <= tracked.expiry_ts;
+
+                let ix = self.ctx.deposit_ix(&tracked.session, tip_amount);
+                let succeeded = self.ctx.send(&[ix], &[]).is_ok();
+                if self.expect(step, succeeded, allowed) {
+                    let tracked = &mut self.sessions[index];
+                    tracked.tip_amount = tip_amount;
+                    tracked.received += tracked.amount + tip_amount;
+                    tracked.status = PaymentSessionStatus::Funded;
+                }
+            }
+            Step::Refund { session } => {
+                let Some(index) = self.pick(session) else { return };
+                let tracked = &self.sessions[index];
+                let allowed = tracked.status == PaymentSessionStatus::Funded;
+
+                let ix = self.ctx.refund_ix(&tracked.session);
+                let succeeded = self.ctx.send(&[ix], &[]).is_ok();
+                if self.expect(step, succeeded, allowed) {
+                    let tracked = &mut self.sessions[index];
+                    tracked.refunded += tracked.amount + tracked.tip_amount;
+                    tracked.status = PaymentSessionStatus::Refunded;
+                }
+            }
+            Step::Settle { session } => {
+                let Some(index) = self.pick(session) else { return };
+                let tracked = &self.sessions[index];
+                let allowed = tracked.status == PaymentSessionStatus::Funded;
+
+                let ix = self.ctx.settle_ix(&tracked.session, self.bitpay_ata, Some(self.tip_ata));
+                let succeeded = self.ctx.send(&[ix], &[]).is_ok();
+                if self.expect(step, succeeded, allowed) {
+                    let tracked = &mut self.sessions[index];
+                    tracked.settled += tracked.amount + tracked.tip_amount;
+                    tracked.status = PaymentSessionStatus::PendingFiat;
+                }
+            }
+            Step::Expire => self.ctx.warp_seconds(61),
+        }
+    }
+
+    fn pick(&self, session: usize) -> Option<usize> {
+        (!self.sessions.is_empty()).then(|| session % self.sessions.len())
+    }
+
+    // Fails the case when the program accepted a step the model rejects, or
+    // the other way round. Returns whether the step went through.
+    fn expect(&self, step: &Step, succeeded: bool, allowed: bool) -> bool {
+        assert_eq!(succeeded, allowed, "{step:?} succeeded = {succeeded}, expected {allowed}");
+        succeeded
+    }
+
+    fn check_invariants(&self) {
+        let mut received = 0;
+        let mut refunded = 0;
+        let mut settled = 0;
+        let mut escrowed = 0;
+
+        for tracked in &self.sessions {
+            let balance = self.ctx.token_balance(&tracked.session.escrow_ata);
+            assert_eq!(balance, tracked.escrowed(), "escrow balance drifted");
+            assert_eq!(self.ctx.payment_session(&tracked.session).status().unwrap(), tracked.status);
+
+            received += tracked.received;
+            refunded += tracked.refunded;
+            settled += tracked.settled;
+            escrowed += balance;
+        }
+
+        let payer = self.ctx.token_balance(&self.ctx.payer_ata);
+        let paid_out = self.ctx.token_balance(&self.bitpay_ata) + self.ctx.token_balance(&self.tip_ata);
+
+        assert_eq!(payer, PAYER_BALANCE - received + refunded);
+        assert_eq!(paid_out, settled);
+        assert_eq!(payer + paid_out + escrowed, PAYER_BALANCE, "tokens created or destroyed");
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn session_lifecycle_conserves_tokens(steps in prop::collection::vec(step(), 1..48)) {
+        let mut harness = Harness::new();
+
+        for step in &steps {
+            harness.run(step);
+            harness.check_invariants();
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use anchor_lang::{error::ErrorCode, prelude::Pubkey, AccountDeserialize};
+use anchor_spl::token::Mint;
+use capstone_ethanbackhus::{
+    errors::PaymentError,
+    state::{MerchantRewards, DEFAULT_MAX_CASHBACK_BPS},
+};
+use capstone_ethanbackhus_litesvm_tests::*;
+use litesvm_token::CreateMint;
+use solana_signer::Signer;
+
+// Creates the merchant's loyalty mint for the payment mint, sets the cashback
+// rate and returns the payer's loyalty token account.
+fn with_cashback(ctx: &mut TestContext, cashback_bps: u16) -> Pubkey {
+    let merchant = ctx.merchant.insecure_clone();
+    let mint = ctx.mint;
+
+    let init = ctx.init_loyalty_mint_ix(&merchant.pubkey(), &mint);
+    let rate = ctx.set_cashback_rate_ix(&merchant.pubkey(), cashback_bps);
+    ctx.send(&[init, rate], &[&merchant]).unwrap();
+
+    let payer = ctx.payer.pubkey();
+    ctx.create_ata_for(&loyalty_mint_pda(&mint), &payer)
+}
+
+fn merchant_rewards(ctx: &TestContext) -> MerchantRewards {
+    let account = ctx.svm.get_account(&merchant_rewards_pda(MERCHANT_ID)).expect("merchant record missing");
+    MerchantRewards::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+#[test]
+fn loyalty_mint_matches_payment_decimals() {
+    let mut ctx = TestContext::new();
+    with_cashback(&mut ctx, 0);
+
+    let account = ctx.svm.get_account(&loyalty_mint_pda(&ctx.mint)).unwrap();
+    let loyalty_mint = Mint::try_deserialize(&mut account.data.as_slice()).unwrap();
+
+    assert_eq!(loyalty_mint.decimals, DECIMALS);
+    assert_eq!(loyalty_mint.mint_authority, Some(config_pda()).into());
+}
+
+#[test]
+fn operator_can_init_loyalty_mint() {
+    let mut ctx = TestContext::new();
+    let operator = ctx.payer.pubkey();
+
+    let ix = ctx.init_loyalty_mint_ix(&operator, &ctx.mint);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert!(ctx.svm.get_account(&loyalty_mint_pda(&ctx.mint)).is_some());
+}
+
+#[test]
+fn stranger_cannot_init_loyalty_mint() {
+    let mut ctx = TestContext::new();
+    let intruder = ctx.funded_keypair();
+
+    let ix = ctx.init_loyalty_mint_ix(&intruder.pubkey(), &ctx.mint);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedMerchant.into());
+}
+
+#[test]
+fn cashback_rate_is_capped_by_config() {
+    let mut ctx = TestContext::new();
+    let merchant = ctx.merchant.insecure_clone();
+    let admin = ctx.payer.pubkey();
+
+    let ix = ctx.set_cashback_rate_ix(&merchant.pubkey(), DEFAULT_MAX_CASHBACK_BPS + 1);
+    assert_error(ctx.send(&[ix], &[&merchant]), PaymentError::InvalidCashbackRate.into());
+
+    let ix = ctx.set_max_cashback_rate_ix(&admin, DEFAULT_MAX_CASHBACK_BPS + 1);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let ix = ctx.set_cashback_rate_ix(&merchant.pubkey(), DEFAULT_MAX_CASHBACK_BPS + 1);
+    ctx.send(&[ix], &[&merchant]).unwrap();
+
+    assert_eq!(merchant_rewards(&ctx).cashback_bps, DEFAULT_MAX_CASHBACK_BPS + 1);
+}
+
+#[test]
+fn only_merchant_sets_cashback_rate() {
+    let mut ctx = TestContext::new();
+    let intruder = ctx.funded_keypair();
+
+    let ix = ctx.set_cashback_rate_ix(&intruder.pubkey(), 100);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedMerchant.into());
+}
+
+#[test]
+fn only_admin_sets_cashback_cap() {
+    let mut ctx = TestContext::new();
+    let intruder = ctx.funded_keypair();
+
+    let ix = ctx.set_max_cashback_rate_ix(&intruder.pubkey(), 10_000);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedAdmin.into());
+}
+
+#[test]
+fn settlement_mints_cashback() {
+    let mut ctx = TestContext::new();
+    let payer_loyalty_ata = with_cashback(&mut ctx, 500);
+    let session = ctx.funded_session(200, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_with_cashback_ix(&session, bitpay_ata, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.token_balance(&bitpay_ata), 200);
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 10);
+    assert_eq!(merchant_rewards(&ctx).total_cashback_minted, 10);
+}
+
+#[test]
+fn lowered_cap_clamps_cashback() {
+    let mut ctx = TestContext::new();
+    let payer_loyalty_ata = with_cashback(&mut ctx, 500);
+    let admin = ctx.payer.pubkey();
+
+    let ix = ctx.set_max_cashback_rate_ix(&admin, 100);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let session = ctx.funded_session(200, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_with_cashback_ix(&session, bitpay_ata, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 2);
+}
+
+#[test]
+fn cashback_requires_loyalty_accounts() {
+    let mut ctx = TestContext::new();
+    with_cashback(&mut ctx, 500);
+    let session = ctx.funded_session(200, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_ix(&session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::MissingLoyaltyAccounts.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 200);
+}
+
+#[test]
+fn redeemed_loyalty_discounts_deposit() {
+    let mut ctx = TestContext::new();
+    let payer_loyalty_ata = with_cashback(&mut ctx, 500);
+    let settled = ctx.funded_session(200, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_with_cashback_ix(&settled, bitpay_ata, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    let deposit = ctx.redeem_deposit_ix(&session, 10, payer_loyalty_ata);
+    ctx.send(&[init, deposit], &[]).unwrap();
+
+    let state = ctx.payment_session(&session);
+    assert_eq!(state.discount_amount, 10);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 90);
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 0);
+}
+
+#[test]
+fn redemption_is_scoped_to_session_mint() {
+    let mut ctx = TestContext::new();
+    let operator = ctx.payer.pubkey();
+
+    // loyalty tokens the same merchant issued against a different payment token
+    let other_mint = CreateMint::new(&mut ctx.svm, &ctx.payer).decimals(DECIMALS).send().unwrap();
+    let ix = ctx.init_loyalty_mint_ix(&operator, &other_mint);
+    ctx.send(&[ix], &[]).unwrap();
+    let other_loyalty_ata = ctx.create_ata_for(&loyalty_mint_pda(&other_mint), &operator);
+
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    ctx.send(&[init], &[]).unwrap();
+
+    let mut deposit = ctx.redeem_deposit_ix(&session, 10, other_loyalty_ata);
+    deposit.accounts[7].pubkey = loyalty_mint_pda(&other_mint);
+    assert_error(ctx.send(&[deposit], &[]), ErrorCode::ConstraintSeeds.into());
+}
@@ -0,0 +1,119 @@
+use anchor_lang::{AnchorSerialize, Discriminator, Space};
+use capstone_ethanbackhus::{
+    errors::PaymentError,
+    state::{LegacyPaymentSession, PaymentSession, PaymentSessionStatus, PAYMENT_SESSION_VERSION},
+};
+use capstone_ethanbackhus_litesvm_tests::*;
+use solana_signer::Signer;
+
+// Rewrites a session exactly as the deployed program stored it, on the Borsh
+// layout from before PaymentSession moved to zero-copy.
+fn rewrite_as_legacy(ctx: &mut TestContext, session: &Session) {
+    let state = ctx.payment_session(session);
+    let legacy = LegacyPaymentSession {
+        payer: state.payer,
+        merchant_id: state.merchant_id(),
+        amount: state.amount,
+        token_mint: state.token_mint,
+        escrow_ata: session.escrow_ata,
+        payer_ata: ctx.payer_ata,
+        status: state.status().unwrap(),
+        expiry_ts: state.expiry_ts,
+        created_ts: state.created_ts,
+        funded_ts: state.funded_ts(),
+        settled_ts: state.settled_ts(),
+        bump: state.bump,
+        reference_id: state.reference_id(),
+        settlement_authority: state.settlement_authority,
+        settlement_bump: state.settlement_bump,
+        uuid: state.uuid,
+        bitpay_payout_id: None,
+        fiat_currency: "USD".to_string(),
+        merchant_bank: String::new(),
+    };
+
+    let mut data = PaymentSession::DISCRIMINATOR.to_vec();
+    data.extend(legacy.try_to_vec().unwrap());
+    data.resize(PaymentSession::DISCRIMINATOR.len() + LegacyPaymentSession::INIT_SPACE, 0);
+
+    let mut account = ctx.svm.get_account(&session.payment_session).unwrap();
+    account.lamports = ctx.svm.minimum_balance_for_rent_exemption(data.len());
+    account.data = data;
+
+    ctx.svm.set_account(session.payment_session, account).unwrap();
+}
+
+#[test]
+fn new_sessions_use_current_version() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).version, PAYMENT_SESSION_VERSION);
+}
+
+#[test]
+fn unmigrated_session_is_rejected() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+    rewrite_as_legacy(&mut ctx, &session);
+
+    let deposit = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[deposit], &[]), PaymentError::SessionNotMigrated.into());
+}
+
+#[test]
+fn migrated_funded_session_can_be_refunded() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let before = ctx.payment_session(&session);
+    rewrite_as_legacy(&mut ctx, &session);
+
+    let refund = ctx.refund_ix(&session);
+    assert_error(ctx.send(std::slice::from_ref(&refund), &[]), PaymentError::SessionNotMigrated.into());
+
+    let migrate = ctx.migrate_ix(&session);
+    ctx.send(&[migrate], &[]).unwrap();
+
+    let state = ctx.payment_session(&session);
+    assert_eq!(state.version, PAYMENT_SESSION_VERSION);
+    assert_eq!(state.status().unwrap(), PaymentSessionStatus::Funded);
+    assert_eq!(state.tip_amount, 0);
+    assert_eq!(state.discount_amount, 0);
+    assert!(!state.has_milestones());
+    assert_eq!(state.payer, ctx.payer.pubkey());
+    assert_eq!(state.merchant_id(), before.merchant_id());
+    assert_eq!(state.reference_id(), before.reference_id());
+    assert_eq!(state.expiry_ts, before.expiry_ts);
+    assert_eq!(state.funded_ts, before.funded_ts);
+    assert_eq!(state.uuid, before.uuid);
+
+    // the smaller layout releases the surplus rent
+    let current_len = PaymentSession::DISCRIMINATOR.len() + PaymentSession::INIT_SPACE;
+    let account = ctx.svm.get_account(&session.payment_session).unwrap();
+    assert_eq!(account.data.len(), current_len);
+    assert_eq!(account.lamports, ctx.svm.minimum_balance_for_rent_exemption(current_len));
+
+    ctx.send(&[refund], &[]).unwrap();
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE);
+}
+
+#[test]
+fn migrating_current_session_fails() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+
+    let migrate = ctx.migrate_ix(&session);
+    assert_error(ctx.send(&[migrate], &[]), PaymentError::SessionAlreadyMigrated.into());
+}
+
+#[test]
+fn legacy_layout_is_larger_than_current() {
+    // migrate_session tells the deployed layout apart by size alone
+    const { assert!(LegacyPaymentSession::INIT_SPACE > PaymentSession::INIT_SPACE) };
+}
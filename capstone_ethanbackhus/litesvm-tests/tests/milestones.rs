@@ -0,0 +1,264 @@
+use capstone_ethanbackhus::{
+    errors::PaymentError,
+    state::{MilestoneInput, PaymentSessionStatus, MAX_MILESTONE_RELEASE_AFTER},
+};
+use capstone_ethanbackhus_litesvm_tests::*;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+// 30% after 100 seconds, the remaining 70% after 200 seconds
+fn shipment_and_delivery() -> Vec<MilestoneInput> {
+    vec![
+        MilestoneInput { amount_bps: 3_000, release_after: 100 },
+        MilestoneInput { amount_bps: 7_000, release_after: 200 },
+    ]
+}
+
+fn milestone_session(ctx: &mut TestContext, amount: u64, tip_amount: u64) -> Session {
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, amount);
+    let schedule = ctx.set_milestones_ix(&session, shipment_and_delivery());
+    let deposit = ctx.deposit_ix(&session, tip_amount);
+    ctx.send(&[init, schedule, deposit], &[]).unwrap();
+    session
+}
+
+#[test]
+fn payer_confirms_milestones_in_order() {
+    let mut ctx = TestContext::new();
+    let session = milestone_session(&mut ctx, 100, 10);
+    let bitpay_ata = ctx.bitpay_ata();
+    let tip_ata = ctx.tip_ata();
+    let payer = ctx.payer.pubkey();
+
+    let ix = ctx.release_milestone_ix(&payer, &session, bitpay_ata, Some(tip_ata));
+    ctx.send(&[ix], &[]).unwrap();
+
+    let state = ctx.payment_session(&session);
+    assert_eq!(state.status().unwrap(), PaymentSessionStatus::Funded);
+    assert_eq!(state.released_amount, 30);
+    assert_eq!(ctx.token_balance(&bitpay_ata), 30);
+    assert_eq!(ctx.token_balance(&tip_ata), 0);
+
+    let ix = ctx.release_milestone_ix(&payer, &session, bitpay_ata, Some(tip_ata));
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::PendingFiat);
+    assert_eq!(ctx.token_balance(&bitpay_ata), 100);
+    assert_eq!(ctx.token_balance(&tip_ata), 10);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+}
+
+#[test]
+fn anyone_can_release_after_timeout() {
+    let mut ctx = TestContext::new();
+    let session = milestone_session(&mut ctx, 100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let keeper = ctx.funded_keypair();
+
+    let ix = ctx.release_milestone_ix(&keeper.pubkey(), &session, bitpay_ata, None);
+    assert_error(ctx.send(std::slice::from_ref(&ix), &[&keeper]), PaymentError::MilestoneNotDue.into());
+
+    ctx.warp_seconds(101);
+    ctx.send(&[ix], &[&keeper]).unwrap();
+    assert_eq!(ctx.token_balance(&bitpay_ata), 30);
+
+    // the second milestone has its own, later timeout
+    let ix = ctx.release_milestone_ix(&keeper.pubkey(), &session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[&keeper]), PaymentError::MilestoneNotDue.into());
+}
+
+#[test]
+fn refund_returns_unreleased_remainder() {
+    let mut ctx = TestContext::new();
+    let session = milestone_session(&mut ctx, 100, 10);
+    let bitpay_ata = ctx.bitpay_ata();
+    let payer = ctx.payer.pubkey();
+
+    let ix = ctx.release_milestone_ix(&payer, &session, bitpay_ata, None);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let refund = ctx.refund_ix(&session);
+    ctx.send(&[refund], &[]).unwrap();
+
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE - 30);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+}
+
+#[test]
+fn schedule_must_cover_the_full_amount() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    ctx.send(&[init], &[]).unwrap();
+
+    let partial = vec![MilestoneInput { amount_bps: 5_000, release_after: 0 }];
+    let ix = ctx.set_milestones_ix(&session, partial);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidMilestoneSchedule.into());
+}
+
+#[test]
+fn schedule_cannot_wait_longer_than_a_year() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    ctx.send(&[init], &[]).unwrap();
+
+    let too_late = vec![MilestoneInput { amount_bps: 10_000, release_after: MAX_MILESTONE_RELEASE_AFTER + 1 }];
+    let ix = ctx.set_milestones_ix(&session, too_late);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidMilestoneSchedule.into());
+
+    let at_cap = vec![MilestoneInput { amount_bps: 10_000, release_after: MAX_MILESTONE_RELEASE_AFTER }];
+    let ix = ctx.set_milestones_ix(&session, at_cap);
+    ctx.send(&[ix], &[]).unwrap();
+}
+
+#[test]
+fn merchant_can_cosign_schedule() {
+    let mut ctx = TestContext::new();
+    let merchant = ctx.merchant.insecure_clone();
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    ctx.send(&[init], &[]).unwrap();
+
+    let ix = ctx.set_milestones_with_ix(&merchant.pubkey(), &session, shipment_and_delivery(), true);
+    ctx.send(&[ix], &[&merchant]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).milestone_count, 2);
+}
+
+#[test]
+fn payer_cannot_set_schedule_alone() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    ctx.send(&[init], &[]).unwrap();
+
+    // a co-signer that is neither the merchant nor an operator
+    let intruder = ctx.funded_keypair();
+    let ix = ctx.set_milestones_with_ix(&intruder.pubkey(), &session, shipment_and_delivery(), true);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedMerchant.into());
+}
+
+#[test]
+fn final_milestone_mints_cashback() {
+    let mut ctx = TestContext::new();
+    let merchant = ctx.merchant.insecure_clone();
+    let mint = ctx.mint;
+    let payer = ctx.payer.pubkey();
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let init = ctx.init_loyalty_mint_ix(&merchant.pubkey(), &mint);
+    let rate = ctx.set_cashback_rate_ix(&merchant.pubkey(), 500);
+    ctx.send(&[init, rate], &[&merchant]).unwrap();
+    let payer_loyalty_ata = ctx.create_ata_for(&loyalty_mint_pda(&mint), &payer);
+
+    let session = milestone_session(&mut ctx, 200, 0);
+
+    // nothing is minted until the schedule completes
+    let ix = ctx.release_milestone_with_cashback_ix(&payer, &session, bitpay_ata, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 0);
+
+    let ix = ctx.release_milestone_ix(&payer, &session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::MissingLoyaltyAccounts.into());
+
+    // 5% of the whole 200
+    let ix = ctx.release_milestone_with_cashback_ix(&payer, &session, bitpay_ata, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 10);
+}
+
+#[test]
+fn milestone_session_cannot_settle_in_one_step() {
+    let mut ctx = TestContext::new();
+    let session = milestone_session(&mut ctx, 100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_ix(&session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::MilestoneScheduleActive.into());
+}
+
+#[test]
+fn release_rejects_unregistered_payout_destination() {
+    let mut ctx = TestContext::new();
+    let session = milestone_session(&mut ctx, 100, 0);
+    let other_ata = ctx.create_ata(&Keypair::new().pubkey());
+    let payer = ctx.payer.pubkey();
+
+    let ix = ctx.release_milestone_ix(&payer, &session, other_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPayoutDestination.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn large_schedule_releases_against_approved_proposal() {
+    let mut ctx = TestContext::new();
+    let amount = LARGE_SETTLEMENT_THRESHOLD + 1;
+    let session = milestone_session(&mut ctx, amount, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let payer = ctx.payer.pubkey();
+
+    let ix = ctx.release_milestone_ix(&payer, &session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::SettlementRequiresApproval.into());
+
+    // the payer is the only operator, proposing also approves
+    let propose = ctx.propose_ix(&payer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    for _ in 0..2 {
+        let ix = ctx.release_approved_milestone_ix(&payer, &session, bitpay_ata, None, true);
+        ctx.send(&[ix], &[]).unwrap();
+    }
+
+    assert_eq!(ctx.token_balance(&bitpay_ata), amount);
+    assert!(ctx.settlement_proposal(&session).executed);
+}
+
+#[test]
+fn refund_closes_once_the_next_milestone_is_due() {
+    let mut ctx = TestContext::new();
+    let session = milestone_session(&mut ctx, 100, 0);
+
+    ctx.warp_seconds(101);
+
+    let refund = ctx.refund_ix(&session);
+    assert_error(ctx.send(&[refund], &[]), PaymentError::RefundWindowClosed.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn refund_restores_discount_pro_rata() {
+    let mut ctx = TestContext::new();
+    let merchant = ctx.merchant.insecure_clone();
+    let mint = ctx.mint;
+    let payer = ctx.payer.pubkey();
+    let bitpay_ata = ctx.bitpay_ata();
+
+    // 5% cashback on a 400 settlement leaves the payer 20 loyalty tokens
+    let init = ctx.init_loyalty_mint_ix(&merchant.pubkey(), &mint);
+    let rate = ctx.set_cashback_rate_ix(&merchant.pubkey(), 500);
+    ctx.send(&[init, rate], &[&merchant]).unwrap();
+    let payer_loyalty_ata = ctx.create_ata_for(&loyalty_mint_pda(&mint), &payer);
+    let settled = ctx.funded_session(400, 0);
+    let ix = ctx.settle_with_cashback_ix(&settled, bitpay_ata, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+
+    // all 20 redeemed against a 100 schedule, so 80 is escrowed
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 100);
+    let schedule = ctx.set_milestones_ix(&session, shipment_and_delivery());
+    let deposit = ctx.redeem_deposit_ix(&session, 20, payer_loyalty_ata);
+    ctx.send(&[init, schedule, deposit], &[]).unwrap();
+
+    // 30% of the 80 goes to the merchant, 56 comes back
+    let ix = ctx.release_milestone_ix(&payer, &session, bitpay_ata, None);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let refund = ctx.refund_with_loyalty_ix(&session, Some(payer_loyalty_ata));
+    ctx.send(&[refund], &[]).unwrap();
+
+    // only the discount on the refunded 70% is restored
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 14);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+}
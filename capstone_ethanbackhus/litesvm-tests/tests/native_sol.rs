@@ -0,0 +1,227 @@
+use anchor_spl::token::spl_token::native_mint;
+use capstone_ethanbackhus::{errors::PaymentError, state::PaymentSessionStatus};
+use capstone_ethanbackhus_litesvm_tests::*;
+use solana_signer::Signer;
+
+// Lamports the settlement authority keeps to stay rent exempt
+fn rent_reserve(ctx: &TestContext) -> u64 {
+    ctx.svm.minimum_balance_for_rent_exemption(0)
+}
+
+#[test]
+fn deposit_sol_escrows_lamports_on_settlement_authority() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(100, 15);
+
+    let state = ctx.payment_session(&session);
+    assert!(state.is_native());
+    assert_eq!(state.status().unwrap(), PaymentSessionStatus::Funded);
+    assert_eq!(state.tip_amount, 15);
+    assert_eq!(ctx.lamports(&session.settlement_authority), rent_reserve(&ctx) + 115);
+}
+
+#[test]
+fn settle_sol_pays_bitpay_and_tip_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(100, 15);
+    let bitpay_wallet = ctx.payout_wallet;
+    let tip_wallet = ctx.tip_wallet;
+    let bitpay_before = ctx.lamports(&bitpay_wallet);
+    let tip_before = ctx.lamports(&tip_wallet);
+
+    let ix = ctx.settle_sol_ix(&session, bitpay_wallet, Some(tip_wallet));
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::PendingFiat);
+    assert_eq!(ctx.lamports(&bitpay_wallet), bitpay_before + 100);
+    assert_eq!(ctx.lamports(&tip_wallet), tip_before + 15);
+    assert_eq!(ctx.lamports(&session.settlement_authority), 0);
+}
+
+#[test]
+fn settle_sol_mints_wrapped_sol_cashback() {
+    let mut ctx = TestContext::new();
+    let merchant = ctx.merchant.insecure_clone();
+    let payer = ctx.payer.pubkey();
+
+    // 5% cashback on the merchant's loyalty mint for wrapped SOL
+    let init = ctx.init_loyalty_mint_ix(&merchant.pubkey(), &native_mint::ID);
+    let rate = ctx.set_cashback_rate_ix(&merchant.pubkey(), 500);
+    ctx.send(&[init, rate], &[&merchant]).unwrap();
+    let payer_loyalty_ata = ctx.create_ata_for(&loyalty_mint_pda(&native_mint::ID), &payer);
+
+    let session = ctx.funded_native_session(200, 0);
+    let bitpay_wallet = ctx.payout_wallet;
+
+    let ix = ctx.settle_sol_ix(&session, bitpay_wallet, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::MissingLoyaltyAccounts.into());
+
+    let ix = ctx.settle_sol_with_cashback_ix(&session, bitpay_wallet, payer_loyalty_ata);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.token_balance(&payer_loyalty_ata), 10);
+}
+
+#[test]
+fn settle_sol_rejects_unregistered_payout_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(100, 0);
+    let other_wallet = ctx.funded_keypair().pubkey();
+
+    let ix = ctx.settle_sol_ix(&session, other_wallet, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPayoutDestination.into());
+}
+
+#[test]
+fn settle_sol_with_tip_requires_tip_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(100, 15);
+    let bitpay_wallet = ctx.payout_wallet;
+
+    let ix = ctx.settle_sol_ix(&session, bitpay_wallet, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::MissingTipDestination.into());
+}
+
+#[test]
+fn settle_sol_rejects_unregistered_tip_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(100, 15);
+    let bitpay_wallet = ctx.payout_wallet;
+    let tip_wallet = ctx.payer.pubkey();
+
+    let ix = ctx.settle_sol_ix(&session, bitpay_wallet, Some(tip_wallet));
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidTipDestination.into());
+}
+
+#[test]
+fn refund_sol_empties_escrow() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(100, 15);
+
+    let ix = ctx.refund_sol_ix(&session);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::Refunded);
+    assert_eq!(ctx.lamports(&session.settlement_authority), 0);
+
+    let ix = ctx.refund_sol_ix(&session);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPaymentSessionState.into());
+}
+
+#[test]
+fn cancel_native_session_returns_rent_reserve() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_native_session();
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+    assert_eq!(ctx.lamports(&session.settlement_authority), rent_reserve(&ctx));
+
+    let operator = ctx.payer.pubkey();
+    let ix = ctx.cancel_ix(&operator, &session, false);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.lamports(&session.payment_session), 0);
+    assert_eq!(ctx.lamports(&session.settlement_authority), 0);
+}
+
+#[test]
+fn deposit_sol_rejects_token_session() {
+    let mut ctx = TestContext::new();
+
+    let session = ctx.new_session();
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let ix = ctx.deposit_sol_ix(&session, 0);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidEscrowMode.into());
+}
+
+#[test]
+fn native_deposits_use_native_limits() {
+    let mut ctx = TestContext::new();
+
+    // above the token daily limit, well inside the lamport one
+    ctx.funded_native_session(DAILY_LIMIT * 2, 0);
+
+    let ix = ctx.update_spending_limits_ix(true, DAILY_LIMIT * 2 + 50, DAILY_LIMIT * 20);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let session = ctx.new_native_session();
+    let init = ctx.init_session_ix(&session, 51);
+    ctx.send(&[init], &[]).unwrap();
+
+    let deposit = ctx.deposit_sol_ix(&session, 0);
+    assert_error(ctx.send(&[deposit], &[]), PaymentError::DailyLimitExceeded.into());
+
+    // token sessions keep their own limits
+    ctx.funded_session(51, 0);
+}
+
+#[test]
+fn large_native_settlement_goes_through_proposal() {
+    let mut ctx = TestContext::new();
+    let amount = NATIVE_LARGE_SETTLEMENT_THRESHOLD + 1;
+    let session = ctx.funded_native_session(amount, 15);
+    let bitpay_wallet = ctx.payout_wallet;
+    let tip_wallet = ctx.tip_wallet;
+    let operator = ctx.payer.pubkey();
+
+    let ix = ctx.settle_sol_ix(&session, bitpay_wallet, Some(tip_wallet));
+    assert_error(ctx.send(&[ix], &[]), PaymentError::SettlementRequiresApproval.into());
+
+    let propose = ctx.propose_ix(&operator, &session, bitpay_wallet);
+    ctx.send(&[propose], &[]).unwrap();
+
+    let bitpay_before = ctx.lamports(&bitpay_wallet);
+    let tip_before = ctx.lamports(&tip_wallet);
+
+    let execute = ctx.execute_sol_ix(&operator, &session, bitpay_wallet, Some(tip_wallet));
+    ctx.send(&[execute], &[]).unwrap();
+
+    assert!(ctx.settlement_proposal(&session).executed);
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::PendingFiat);
+    assert_eq!(ctx.lamports(&bitpay_wallet), bitpay_before + amount);
+    assert_eq!(ctx.lamports(&tip_wallet), tip_before + 15);
+    assert_eq!(ctx.lamports(&session.settlement_authority), 0);
+}
+
+#[test]
+fn native_execute_pays_only_the_proposed_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(NATIVE_LARGE_SETTLEMENT_THRESHOLD + 1, 0);
+    let bitpay_wallet = ctx.payout_wallet;
+    let other_wallet = ctx.funded_keypair().pubkey();
+    let operator = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&operator, &session, bitpay_wallet);
+    ctx.send(&[propose], &[]).unwrap();
+
+    let execute = ctx.execute_sol_ix(&operator, &session, other_wallet, None);
+    assert_error(ctx.send(&[execute], &[]), PaymentError::ProposalMismatch.into());
+}
+
+#[test]
+fn native_proposal_must_name_the_payout_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_native_session(NATIVE_LARGE_SETTLEMENT_THRESHOLD + 1, 0);
+    let other_wallet = ctx.funded_keypair().pubkey();
+    let operator = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&operator, &session, other_wallet);
+    assert_error(ctx.send(&[propose], &[]), PaymentError::InvalidPayoutDestination.into());
+}
+
+#[test]
+fn native_payer_limits_are_checked_against_native_defaults() {
+    let mut ctx = TestContext::new();
+    let operator = ctx.payer.pubkey();
+
+    // token sized overrides would lower a lamport limit
+    let ix = ctx.set_payer_limits_ix(&operator, true, DAILY_LIMIT * 2, MONTHLY_LIMIT);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidLimits.into());
+
+    let ix = ctx.set_payer_limits_ix(&operator, true, NATIVE_DAILY_LIMIT * 2, NATIVE_MONTHLY_LIMIT);
+    ctx.send(&[ix], &[]).unwrap();
+
+    ctx.funded_native_session(NATIVE_DAILY_LIMIT + 1, 0);
+}
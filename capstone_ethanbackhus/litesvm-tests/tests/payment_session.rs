@@ -0,0 +1,341 @@
+use anchor_lang::{error::ErrorCode, prelude::Pubkey};
+use capstone_ethanbackhus::{errors::PaymentError, state::PaymentSessionStatus};
+use capstone_ethanbackhus_litesvm_tests::*;
+use litesvm_token::CreateMint;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+#[test]
+fn init_payment_session_creates_escrow() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let state = ctx.payment_session(&session);
+    assert_eq!(state.payer, ctx.payer.pubkey());
+    assert_eq!(state.amount, 100);
+    assert_eq!(state.token_mint, ctx.mint);
+    assert_eq!(state.settlement_authority, session.settlement_authority);
+    assert_eq!(state.status().unwrap(), PaymentSessionStatus::Initialized);
+    assert_eq!(state.expiry_ts, state.created_ts + 60);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+}
+
+#[test]
+fn deposit_moves_amount_and_tip_into_escrow() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 15);
+
+    let state = ctx.payment_session(&session);
+    assert_eq!(state.status().unwrap(), PaymentSessionStatus::Funded);
+    assert_eq!(state.tip_amount, 15);
+    assert!(state.funded_ts().is_some());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 115);
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE - 115);
+}
+
+#[test]
+fn settle_pays_bitpay_and_tip_destination() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 15);
+
+    let bitpay_ata = ctx.bitpay_ata();
+    let tip_ata = ctx.tip_ata();
+
+    let ix = ctx.settle_ix(&session, bitpay_ata, Some(tip_ata));
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::PendingFiat);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+    assert_eq!(ctx.token_balance(&bitpay_ata), 100);
+    assert_eq!(ctx.token_balance(&tip_ata), 15);
+}
+
+#[test]
+fn settle_rejects_tip_to_unregistered_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 15);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    // the payer tries to route their own tip back to themselves
+    let ix = ctx.settle_ix(&session, bitpay_ata, Some(ctx.payer_ata));
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidTipDestination.into());
+}
+
+#[test]
+fn settle_rejects_unregistered_payout_destination() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let other_ata = ctx.create_ata(&Keypair::new().pubkey());
+
+    let ix = ctx.settle_ix(&session, other_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPayoutDestination.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn settle_with_tip_requires_tip_destination() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 15);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_ix(&session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::MissingTipDestination.into());
+}
+
+#[test]
+fn refund_returns_amount_and_tip() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 15);
+
+    let ix = ctx.refund_ix(&session);
+    ctx.send(&[ix], &[]).unwrap();
+
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::Refunded);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE);
+}
+
+#[test]
+fn settle_rejects_wrong_seeds() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    // another wallet cannot settle a session it did not create
+    let intruder = ctx.funded_keypair();
+    let mut ix = ctx.settle_ix(&session, bitpay_ata, None);
+    ix.accounts[0].pubkey = intruder.pubkey();
+
+    assert_error(ctx.send(&[ix], &[&intruder]), ErrorCode::ConstraintSeeds.into());
+}
+
+#[test]
+fn settle_rejects_wrong_mint() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let other_mint = CreateMint::new(&mut ctx.svm, &ctx.payer)
+        .decimals(DECIMALS)
+        .send()
+        .unwrap();
+
+    let mut ix = ctx.settle_ix(&session, bitpay_ata, None);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == ctx.mint) {
+        meta.pubkey = other_mint;
+    }
+
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidMint.into());
+}
+
+#[test]
+fn deposit_rejects_wrong_mint() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let other_mint = CreateMint::new(&mut ctx.svm, &ctx.payer)
+        .decimals(DECIMALS)
+        .send()
+        .unwrap();
+
+    let mut ix = ctx.deposit_ix(&session, 0);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == ctx.mint) {
+        meta.pubkey = other_mint;
+    }
+
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidMint.into());
+}
+
+#[test]
+fn settle_rejects_escrow_outside_the_ata() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    // a token account with the right mint and owner, at a non-ATA address
+    let escrow = ctx.svm.get_account(&session.escrow_ata).unwrap();
+    let decoy = Pubkey::new_unique();
+    ctx.svm.set_account(decoy, escrow).unwrap();
+
+    let mut ix = ctx.settle_ix(&session, bitpay_ata, None);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == session.escrow_ata) {
+        meta.pubkey = decoy;
+    }
+
+    assert_error(ctx.send(&[ix], &[]), ErrorCode::ConstraintAssociated.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn double_settle_fails() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_ix(&session, bitpay_ata, None);
+    ctx.send(std::slice::from_ref(&ix), &[]).unwrap();
+
+    // tokens sent to the escrow afterwards must not make a second payout possible
+    ctx.mint_to(&session.escrow_ata, 100);
+
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPaymentSessionState.into());
+    assert_eq!(ctx.token_balance(&bitpay_ata), 100);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn refund_after_settle_fails() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let settle = ctx.settle_ix(&session, bitpay_ata, None);
+    ctx.send(&[settle], &[]).unwrap();
+
+    ctx.mint_to(&session.escrow_ata, 100);
+
+    let refund = ctx.refund_ix(&session);
+    assert_error(ctx.send(&[refund], &[]), PaymentError::InvalidPaymentSessionState.into());
+    assert_eq!(ctx.token_balance(&ctx.payer_ata), PAYER_BALANCE - 100);
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn refund_rejects_other_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+
+    // another wallet cannot pull a session's escrow into its own account
+    let intruder = ctx.funded_keypair();
+    let intruder_ata = ctx.create_ata(&intruder.pubkey());
+    let mut ix = ctx.refund_ix(&session);
+    ix.accounts[0].pubkey = intruder.pubkey();
+    ix.accounts[2].pubkey = intruder_ata;
+
+    assert_error(ctx.send(&[ix], &[&intruder]), ErrorCode::ConstraintSeeds.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn deposit_twice_fails() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(100, 0);
+
+    let ix = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidPaymentSessionState.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 100);
+}
+
+#[test]
+fn deposit_after_expiry_fails() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+
+    let ix = ctx.init_session_ix(&session, 100);
+    ctx.send(&[ix], &[]).unwrap();
+
+    ctx.warp_seconds(61);
+
+    let ix = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::SessionExpired.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), 0);
+}
+
+#[test]
+fn deposit_over_daily_limit_fails() {
+    let mut ctx = TestContext::new();
+    ctx.funded_session(DAILY_LIMIT - 100, 0);
+
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 101);
+    ctx.send(&[init], &[]).unwrap();
+
+    let deposit = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[deposit], &[]), PaymentError::DailyLimitExceeded.into());
+}
+
+#[test]
+fn daily_window_slides_past_old_deposits() {
+    let mut ctx = TestContext::new();
+    ctx.funded_session(DAILY_LIMIT, 0);
+
+    // every hourly bucket of the window has rolled over
+    ctx.warp_seconds(25 * 60 * 60);
+
+    ctx.funded_session(DAILY_LIMIT, 0);
+}
+
+#[test]
+fn daily_limit_holds_across_window_boundary() {
+    let mut ctx = TestContext::new();
+    ctx.funded_session(1, 0);
+
+    ctx.warp_seconds(23 * 60 * 60);
+    ctx.funded_session(DAILY_LIMIT - 1, 0);
+
+    // 24h after the first deposit the second one is only an hour old, so the
+    // window cannot start over with the full limit available
+    ctx.warp_seconds(60 * 60);
+
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, DAILY_LIMIT);
+    ctx.send(&[init], &[]).unwrap();
+
+    let deposit = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[deposit], &[]), PaymentError::DailyLimitExceeded.into());
+}
+
+#[test]
+fn operator_can_raise_payer_limits() {
+    let mut ctx = TestContext::new();
+    let operator = ctx.payer.pubkey();
+
+    let ix = ctx.set_payer_limits_ix(&operator, false, DAILY_LIMIT * 2, MONTHLY_LIMIT);
+    ctx.send(&[ix], &[]).unwrap();
+
+    ctx.funded_session(DAILY_LIMIT + 1, 0);
+}
+
+#[test]
+fn raised_defaults_apply_over_lower_payer_limits() {
+    let mut ctx = TestContext::new();
+    let operator = ctx.payer.pubkey();
+
+    let ix = ctx.set_payer_limits_ix(&operator, false, DAILY_LIMIT * 2, MONTHLY_LIMIT);
+    ctx.send(&[ix], &[]).unwrap();
+
+    // the default now sits above the payer's override
+    let ix = ctx.update_spending_limits_ix(false, DAILY_LIMIT * 3, MONTHLY_LIMIT);
+    ctx.send(&[ix], &[]).unwrap();
+
+    ctx.funded_session(DAILY_LIMIT * 2 + 1, 0);
+}
+
+#[test]
+fn non_operator_cannot_set_payer_limits() {
+    let mut ctx = TestContext::new();
+    let intruder = ctx.funded_keypair();
+
+    let ix = ctx.set_payer_limits_ix(&intruder.pubkey(), false, DAILY_LIMIT * 2, MONTHLY_LIMIT);
+    assert_error(ctx.send(&[ix], &[&intruder]), PaymentError::UnauthorizedOperator.into());
+}
+
+#[test]
+fn admin_can_update_spending_limits() {
+    let mut ctx = TestContext::new();
+
+    let ix = ctx.update_spending_limits_ix(false, 50, 500);
+    ctx.send(&[ix], &[]).unwrap();
+
+    let session = ctx.new_session();
+    let init = ctx.init_session_ix(&session, 51);
+    ctx.send(&[init], &[]).unwrap();
+
+    let deposit = ctx.deposit_ix(&session, 0);
+    assert_error(ctx.send(&[deposit], &[]), PaymentError::DailyLimitExceeded.into());
+}
@@ -0,0 +1,114 @@
+use anchor_lang::prelude::Pubkey;
+use capstone_ethanbackhus::{
+    client::{last_page, recent_sessions, SessionIndexKey},
+    errors::PaymentError,
+    state::SESSIONS_PER_PAGE,
+};
+use capstone_ethanbackhus_litesvm_tests::*;
+use solana_signer::Signer;
+
+fn create_sessions(ctx: &mut TestContext, count: usize) -> Vec<Session> {
+    (0..count)
+        .map(|_| {
+            let session = ctx.new_session();
+            let ix = ctx.init_session_ix(&session, 100);
+            ctx.send(&[ix], &[]).unwrap();
+            session
+        })
+        .collect()
+}
+
+fn newest_first(sessions: &[Session]) -> Vec<Pubkey> {
+    sessions.iter().rev().map(|session| session.payment_session).collect()
+}
+
+fn indexes(ctx: &TestContext) -> [SessionIndexKey; 2] {
+    [
+        SessionIndexKey::Merchant(MERCHANT_ID.to_string()),
+        SessionIndexKey::Payer(ctx.payer.pubkey()),
+    ]
+}
+
+#[test]
+fn sessions_are_listed_newest_first() {
+    let mut ctx = TestContext::new();
+    let created = create_sessions(&mut ctx, 3);
+
+    for index in indexes(&ctx) {
+        let listing = recent_sessions(&index, |keys| ctx.fetch_accounts(keys), None, 10).unwrap();
+        assert_eq!(listing.sessions, newest_first(&created));
+        assert!(listing.next.is_none());
+    }
+}
+
+#[test]
+fn index_rolls_over_to_a_new_page() {
+    let mut ctx = TestContext::new();
+    let created = create_sessions(&mut ctx, SESSIONS_PER_PAGE + 2);
+    let index = SessionIndexKey::Payer(ctx.payer.pubkey());
+
+    let last = last_page(&index, &mut |keys| ctx.fetch_accounts(keys)).unwrap();
+    assert_eq!(last, Some((1, 2)));
+
+    // walk the whole index five sessions at a time
+    let mut listed = Vec::new();
+    let mut cursor = None;
+    loop {
+        let listing = recent_sessions(&index, |keys| ctx.fetch_accounts(keys), cursor, 5).unwrap();
+        listed.extend(listing.sessions);
+        match listing.next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    assert_eq!(listed, newest_first(&created));
+}
+
+#[test]
+fn new_page_requires_full_previous_page() {
+    let mut ctx = TestContext::new();
+    create_sessions(&mut ctx, 1);
+
+    // skip ahead to page 1 while page 0 still has room
+    let index = SessionIndexKey::Payer(ctx.payer.pubkey());
+    let session = ctx.new_session();
+    let mut ix = ctx.init_session_ix(&session, 100);
+
+    let page_0 = index.page_address(0);
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == page_0) {
+        meta.pubkey = index.page_address(1);
+    }
+    let len = ix.data.len();
+    ix.data[len - 4..].copy_from_slice(&1u32.to_le_bytes());
+
+    assert_error(ctx.send(&[ix], &[]), PaymentError::InvalidIndexPage.into());
+}
+
+#[test]
+fn cancelled_sessions_are_skipped_and_keep_their_entry() {
+    let mut ctx = TestContext::new();
+    let created = create_sessions(&mut ctx, 3);
+    let operator = ctx.payer.pubkey();
+
+    let first: Vec<_> = indexes(&ctx)
+        .iter()
+        .map(|index| recent_sessions(index, |keys| ctx.fetch_accounts(keys), None, 1).unwrap())
+        .collect();
+
+    let cancel = ctx.cancel_ix(&operator, &created[1], false);
+    ctx.send(&[cancel], &[]).unwrap();
+
+    for (index, first) in indexes(&ctx).iter().zip(first) {
+        assert_eq!(first.sessions, vec![created[2].payment_session]);
+
+        // the cursor taken before the cancel still continues where it left off
+        let rest = recent_sessions(index, |keys| ctx.fetch_accounts(keys), first.next, 10).unwrap();
+        assert_eq!(rest.sessions, vec![created[0].payment_session]);
+        assert!(rest.next.is_none());
+
+        // the cancelled entry stays on the page
+        let last = last_page(index, &mut |keys| ctx.fetch_accounts(keys)).unwrap();
+        assert_eq!(last, Some((0, 3)));
+    }
+}
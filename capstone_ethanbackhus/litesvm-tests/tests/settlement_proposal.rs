@@ -0,0 +1,238 @@
+use capstone_ethanbackhus::{errors::PaymentError, state::PaymentSessionStatus};
+use capstone_ethanbackhus_litesvm_tests::*;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+const LARGE_AMOUNT: u64 = LARGE_SETTLEMENT_THRESHOLD + 1;
+
+#[test]
+fn large_settlement_requires_proposal() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+
+    let ix = ctx.settle_ix(&session, bitpay_ata, None);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::SettlementRequiresApproval.into());
+}
+
+#[test]
+fn two_of_three_operators_execute_settlement() {
+    let second = Keypair::new();
+    let third = Keypair::new();
+    let mut ctx = TestContext::with_operators(&[second.pubkey(), third.pubkey()], 2);
+
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    // one approval out of two is not enough
+    let execute = ctx.execute_ix(&proposer, &session, bitpay_ata);
+    assert_error(ctx.send(std::slice::from_ref(&execute), &[]), PaymentError::InsufficientApprovals.into());
+
+    let approve = ctx.approve_ix(&second.pubkey(), &session);
+    ctx.send(&[approve], &[&second]).unwrap();
+
+    ctx.send(&[execute], &[]).unwrap();
+
+    let proposal = ctx.settlement_proposal(&session);
+    assert!(proposal.executed);
+    assert_eq!(proposal.approvals, vec![proposer, second.pubkey()]);
+    assert_eq!(ctx.payment_session(&session).status().unwrap(), PaymentSessionStatus::PendingFiat);
+    assert_eq!(ctx.token_balance(&bitpay_ata), LARGE_AMOUNT);
+}
+
+#[test]
+fn execute_pays_only_the_proposed_destination() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let other_ata = ctx.create_ata(&Keypair::new().pubkey());
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    assert_eq!(ctx.settlement_proposal(&session).destination, bitpay_ata);
+    assert_eq!(ctx.settlement_proposal(&session).amount, LARGE_AMOUNT);
+
+    let execute = ctx.execute_ix(&proposer, &session, other_ata);
+    assert_error(ctx.send(&[execute], &[]), PaymentError::ProposalMismatch.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), LARGE_AMOUNT);
+}
+
+#[test]
+fn proposal_cannot_name_an_unregistered_wallet() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let other_ata = ctx.create_ata(&Keypair::new().pubkey());
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, other_ata);
+    assert_error(ctx.send(&[propose], &[]), PaymentError::InvalidPayoutDestination.into());
+
+    // a wallet rather than a token account is refused as well
+    let payout_wallet = ctx.payout_wallet;
+    let propose = ctx.propose_ix(&proposer, &session, payout_wallet);
+    assert_error(ctx.send(&[propose], &[]), PaymentError::InvalidPayoutDestination.into());
+}
+
+#[test]
+fn operators_cancel_a_proposal_and_propose_again() {
+    let second = Keypair::new();
+    let third = Keypair::new();
+    let mut ctx = TestContext::with_operators(&[second.pubkey(), third.pubkey()], 2);
+
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(std::slice::from_ref(&propose), &[]).unwrap();
+
+    // one operator alone is below the threshold
+    let cancel = ctx.cancel_proposal_ix(&third.pubkey(), &proposer, &session, &[]);
+    assert_error(ctx.send(&[cancel], &[&third]), PaymentError::InsufficientApprovals.into());
+
+    let cancel = ctx.cancel_proposal_ix(&third.pubkey(), &proposer, &session, &[second.pubkey()]);
+    ctx.send(&[cancel], &[&third, &second]).unwrap();
+    assert!(ctx.svm.get_account(&settlement_proposal_pda(&session.payment_session)).is_none_or(|account| account.lamports == 0));
+
+    ctx.send(&[propose], &[]).unwrap();
+    assert_eq!(ctx.settlement_proposal(&session).approvals, vec![proposer]);
+}
+
+#[test]
+fn non_operator_cannot_cosign_a_cancel() {
+    let second = Keypair::new();
+    let mut ctx = TestContext::with_operators(&[second.pubkey()], 2);
+
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let proposer = ctx.payer.pubkey();
+    let intruder = ctx.funded_keypair();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    let cancel = ctx.cancel_proposal_ix(&proposer, &proposer, &session, &[intruder.pubkey()]);
+    assert_error(ctx.send(&[cancel], &[&intruder]), PaymentError::UnauthorizedOperator.into());
+}
+
+#[test]
+fn executed_proposal_cannot_be_cancelled() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    let execute = ctx.execute_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+    ctx.send(&[execute], &[]).unwrap();
+
+    let cancel = ctx.cancel_proposal_ix(&proposer, &proposer, &session, &[]);
+    assert_error(ctx.send(&[cancel], &[]), PaymentError::ProposalAlreadyExecuted.into());
+}
+
+#[test]
+fn init_config_requires_upgrade_authority() {
+    let mut ctx = TestContext::new();
+    let operators = vec![ctx.payer.pubkey()];
+
+    // start over without a config, then present a ProgramData account naming
+    // the caller that does not belong to the program
+    ctx.svm.set_account(config_pda(), Default::default()).unwrap();
+    ctx.write_program_data(&ctx.payer.pubkey());
+
+    let ix = ctx.init_config_ix(operators, 1);
+    assert_error(ctx.send(&[ix], &[]), PaymentError::UnauthorizedAdmin.into());
+}
+
+#[test]
+fn revoked_approval_no_longer_counts() {
+    let second = Keypair::new();
+    let mut ctx = TestContext::with_operators(&[second.pubkey()], 2);
+
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    let approve = ctx.approve_ix(&second.pubkey(), &session);
+    let revoke = ctx.revoke_ix(&second.pubkey(), &session);
+    ctx.send(&[propose], &[]).unwrap();
+    ctx.send(&[approve], &[&second]).unwrap();
+    ctx.send(std::slice::from_ref(&revoke), &[&second]).unwrap();
+
+    assert_eq!(ctx.settlement_proposal(&session).approvals, vec![proposer]);
+
+    // revoking twice is rejected
+    assert_error(ctx.send(&[revoke], &[&second]), PaymentError::ApprovalNotFound.into());
+
+    let execute = ctx.execute_ix(&proposer, &session, bitpay_ata);
+    assert_error(ctx.send(&[execute], &[]), PaymentError::InsufficientApprovals.into());
+    assert_eq!(ctx.token_balance(&session.escrow_ata), LARGE_AMOUNT);
+}
+
+#[test]
+fn duplicate_approval_is_rejected() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let proposer = ctx.payer.pubkey();
+
+    let bitpay_ata = ctx.bitpay_ata();
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    let approve = ctx.approve_ix(&proposer, &session);
+    assert_error(ctx.send(&[approve], &[]), PaymentError::AlreadyApproved.into());
+}
+
+#[test]
+fn non_operator_cannot_approve() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let proposer = ctx.payer.pubkey();
+    let intruder = ctx.funded_keypair();
+
+    let bitpay_ata = ctx.bitpay_ata();
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    let approve = ctx.approve_ix(&intruder.pubkey(), &session);
+    assert_error(ctx.send(&[approve], &[&intruder]), PaymentError::UnauthorizedOperator.into());
+}
+
+#[test]
+fn executed_proposal_cannot_run_twice() {
+    let mut ctx = TestContext::new();
+    let session = ctx.funded_session(LARGE_AMOUNT, 0);
+    let bitpay_ata = ctx.bitpay_ata();
+    let proposer = ctx.payer.pubkey();
+
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    ctx.send(&[propose], &[]).unwrap();
+
+    let execute = ctx.execute_ix(&proposer, &session, bitpay_ata);
+    ctx.send(std::slice::from_ref(&execute), &[]).unwrap();
+
+    assert_error(ctx.send(&[execute], &[]), PaymentError::ProposalAlreadyExecuted.into());
+    assert_eq!(ctx.token_balance(&bitpay_ata), LARGE_AMOUNT);
+}
+
+#[test]
+fn unfunded_session_cannot_be_proposed() {
+    let mut ctx = TestContext::new();
+    let session = ctx.new_session();
+    let proposer = ctx.payer.pubkey();
+
+    let init = ctx.init_session_ix(&session, LARGE_AMOUNT);
+    ctx.send(&[init], &[]).unwrap();
+
+    let bitpay_ata = ctx.bitpay_ata();
+    let propose = ctx.propose_ix(&proposer, &session, bitpay_ata);
+    assert_error(ctx.send(&[propose], &[]), PaymentError::InvalidPaymentSessionState.into());
+}
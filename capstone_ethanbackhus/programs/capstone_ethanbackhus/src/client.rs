@@ -0,0 +1,158 @@
+//! Off-chain helpers for reading the merchant and payer session indexes.
+//!
+//! Account fetching is left to the caller, so the helpers work with any RPC
+//! client: `fetch` receives a batch of addresses (one `getMultipleAccounts`
+//! call) and returns the account data for each, or `None` if it does not exist.
+
+use anchor_lang::prelude::*;
+use anchor_lang::AccountDeserialize;
+
+use crate::state::merchant_rewards::merchant_id_hash;
+use crate::state::session_index::{MerchantSessionIndex, PayerSessionIndex, SESSIONS_PER_PAGE};
+
+// pages probed per fetch while looking for the newest page
+const PROBE_BATCH: u32 = 8;
+
+pub enum SessionIndexKey {
+    Merchant(String),
+    Payer(Pubkey),
+}
+
+impl SessionIndexKey {
+    pub fn page_address(&self, page: u32) -> Pubkey {
+        let page = page.to_le_bytes();
+        match self {
+            Self::Merchant(merchant_id) => Pubkey::find_program_address(
+                &[b"merchant_session_index", merchant_id_hash(merchant_id).as_ref(), page.as_ref()],
+                &crate::ID,
+            ),
+            Self::Payer(payer) => Pubkey::find_program_address(
+                &[b"payer_session_index", payer.as_ref(), page.as_ref()],
+                &crate::ID,
+            ),
+        }
+        .0
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<Vec<Pubkey>> {
+        let mut data = data;
+        Ok(match self {
+            Self::Merchant(_) => MerchantSessionIndex::try_deserialize(&mut data)?.sessions,
+            Self::Payer(_) => PayerSessionIndex::try_deserialize(&mut data)?.sessions,
+        })
+    }
+}
+
+// Position in an index, sessions strictly before it are returned next
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionCursor {
+    pub page: u32,
+    pub position: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct SessionPage {
+    pub sessions: Vec<Pubkey>,          // newest first
+    pub next: Option<SessionCursor>,    // None once the oldest session has been returned
+}
+
+// Page that init_payment_session should write the next session into, and the
+// previous page address when that session opens a new page.
+pub struct NextIndexPage {
+    pub page: u32,
+    pub previous: Option<Pubkey>,
+}
+
+// Newest page of the index and the number of sessions in it
+pub fn last_page<F>(index: &SessionIndexKey, fetch: &mut F) -> Result<Option<(u32, usize)>>
+where
+    F: FnMut(&[Pubkey]) -> Vec<Option<Vec<u8>>>,
+{
+    let mut last = None;
+    let mut start = 0;
+
+    loop {
+        let addresses: Vec<Pubkey> = (start..start + PROBE_BATCH)
+            .map(|page| index.page_address(page))
+            .collect();
+
+        for (page, data) in (start..).zip(fetch(&addresses)) {
+            match data {
+                Some(data) => last = Some((page, index.decode(&data)?.len())),
+                None => return Ok(last),
+            }
+        }
+
+        start += PROBE_BATCH;
+    }
+}
+
+pub fn next_index_page<F>(index: &SessionIndexKey, fetch: &mut F) -> Result<NextIndexPage>
+where
+    F: FnMut(&[Pubkey]) -> Vec<Option<Vec<u8>>>,
+{
+    Ok(match last_page(index, fetch)? {
+        None => NextIndexPage { page: 0, previous: None },
+        Some((page, len)) if len >= SESSIONS_PER_PAGE => NextIndexPage {
+            page: page + 1,
+            previous: Some(index.page_address(page)),
+        },
+        Some((page, _)) => NextIndexPage { page, previous: None },
+    })
+}
+
+// Lists up to `limit` sessions, newest first, starting before `before` or at
+// the newest session. Pass the returned `next` cursor to get the next page.
+// Index pages are append-only, so cancelled sessions keep their entry and
+// cursors stay valid. Their accounts are closed, and they are skipped here.
+pub fn recent_sessions<F>(
+    index: &SessionIndexKey,
+    mut fetch: F,
+    before: Option<SessionCursor>,
+    limit: usize,
+) -> Result<SessionPage>
+where
+    F: FnMut(&[Pubkey]) -> Vec<Option<Vec<u8>>>,
+{
+    let mut cursor = match before {
+        Some(cursor) => cursor,
+        None => match last_page(index, &mut fetch)? {
+            Some((page, position)) => SessionCursor { page, position },
+            None => return Ok(SessionPage::default()),
+        },
+    };
+
+    let mut sessions = Vec::with_capacity(limit);
+
+    while sessions.len() < limit {
+        if cursor.position == 0 {
+            if cursor.page == 0 {
+                return Ok(SessionPage { sessions, next: None });
+            }
+            cursor = SessionCursor { page: cursor.page - 1, position: SESSIONS_PER_PAGE };
+        }
+
+        let data = fetch(&[index.page_address(cursor.page)])
+            .pop()
+            .flatten()
+            .ok_or(ErrorCode::AccountNotInitialized)?;
+        let page = index.decode(&data)?;
+
+        let end = cursor.position.min(page.len());
+        let take = (limit - sessions.len()).min(end);
+        let candidates = &page[end - take..end];
+
+        let live = fetch(candidates);
+        sessions.extend(
+            candidates
+                .iter()
+                .zip(live)
+                .rev()
+                .filter_map(|(session, data)| data.map(|_| *session)),
+        );
+        cursor.position = end - take;
+    }
+
+    let next = (cursor.page > 0 || cursor.position > 0).then_some(cursor);
+    Ok(SessionPage { sessions, next })
+}
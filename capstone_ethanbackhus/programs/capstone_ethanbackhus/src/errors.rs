@@ -10,4 +10,76 @@ pub enum PaymentError {
     InvalidPaymentSessionState,
     #[msg("Insufficient funds in the escrow account to complete the settlement.")]
     InsufficientEscrowFunds,
+    #[msg("Arithmetic overflow while computing the payment total.")]
+    Overflow,
+    #[msg("A tip destination account is required to settle a session with a tip.")]
+    MissingTipDestination,
+    #[msg("Tips can only be paid to the merchant's registered tip wallet.")]
+    InvalidTipDestination,
+    #[msg("The signer is not a member of the operator set.")]
+    UnauthorizedOperator,
+    #[msg("Too many operators provided for the operator set.")]
+    TooManyOperators,
+    #[msg("The operator set contains a duplicate operator.")]
+    DuplicateOperator,
+    #[msg("The approval threshold must be between 1 and the number of operators.")]
+    InvalidThreshold,
+    #[msg("This operator has already approved the settlement proposal.")]
+    AlreadyApproved,
+    #[msg("This operator has not approved the settlement proposal.")]
+    ApprovalNotFound,
+    #[msg("The settlement proposal does not have enough approvals.")]
+    InsufficientApprovals,
+    #[msg("The settlement proposal has already been executed.")]
+    ProposalAlreadyExecuted,
+    #[msg("Settlements above the large settlement threshold require an approved proposal.")]
+    SettlementRequiresApproval,
+    #[msg("Only the config admin can perform this operation.")]
+    UnauthorizedAdmin,
+    #[msg("The settlement does not match the destination and amount of the approved proposal.")]
+    ProposalMismatch,
+    #[msg("This deposit would exceed the payer's 24 hour spending limit.")]
+    DailyLimitExceeded,
+    #[msg("This deposit would exceed the payer's 30 day spending limit.")]
+    MonthlyLimitExceeded,
+    #[msg("The monthly limit must be greater than or equal to the daily limit.")]
+    InvalidLimits,
+    #[msg("The cashback rate exceeds the configured maximum.")]
+    InvalidCashbackRate,
+    #[msg("The redeemed loyalty amount cannot exceed the payment amount.")]
+    DiscountExceedsAmount,
+    #[msg("The loyalty mint and payer loyalty token account are required for this operation.")]
+    MissingLoyaltyAccounts,
+    #[msg("The loyalty token account provided does not belong to the payer.")]
+    InvalidLoyaltyAccount,
+    #[msg("Only the merchant rewards authority can perform this operation.")]
+    UnauthorizedMerchant,
+    #[msg("The payment session has expired.")]
+    SessionExpired,
+    #[msg("The payment session must be migrated to the current layout first.")]
+    SessionNotMigrated,
+    #[msg("The payment session is already on the current layout.")]
+    SessionAlreadyMigrated,
+    #[msg("Only the merchant or an operator can cancel this payment session.")]
+    UnauthorizedCancel,
+    #[msg("This session index page is full, use the next page.")]
+    IndexPageFull,
+    #[msg("A new session index page needs the previous page, and it must be full.")]
+    InvalidIndexPage,
+    #[msg("A text field is longer than its fixed-size slot in the payment session.")]
+    FieldTooLong,
+    #[msg("The instruction does not match the session's escrow mode (SPL token or native SOL).")]
+    InvalidEscrowMode,
+    #[msg("Token sessions need the mint, payer ATA and escrow ATA accounts.")]
+    MissingTokenAccounts,
+    #[msg("Milestones need 1 to 4 steps with shares adding up to 10000 bps and non-decreasing timeouts.")]
+    InvalidMilestoneSchedule,
+    #[msg("Sessions with a milestone schedule are released through release_milestone.")]
+    MilestoneScheduleActive,
+    #[msg("The milestone timeout has not passed and the payer has not confirmed it.")]
+    MilestoneNotDue,
+    #[msg("Settlements can only be paid to the merchant's registered payout wallet.")]
+    InvalidPayoutDestination,
+    #[msg("Milestone sessions can only be refunded before the next milestone is due.")]
+    RefundWindowClosed,
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ProgramConfig, SettlementProposal, SettlementApproved};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct ApproveSettlement<'info> {
+
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"settlement_proposal", settlement_proposal.payment_session.as_ref()],
+        bump = settlement_proposal.bump,
+    )]
+    pub settlement_proposal: Account<'info, SettlementProposal>,
+}
+
+impl<'info> ApproveSettlement<'info> {
+    pub fn approve_settlement(
+        &mut self,
+    ) -> Result<()> {
+
+        let operator = self.operator.key();
+
+        require!(self.config.is_operator(&operator), PaymentError::UnauthorizedOperator);
+        require!(!self.settlement_proposal.executed, PaymentError::ProposalAlreadyExecuted);
+        require!(
+            !self.settlement_proposal.approvals.contains(&operator),
+            PaymentError::AlreadyApproved
+        );
+
+        self.settlement_proposal.approvals.push(operator);
+
+        emit!(SettlementApproved {
+            payment_session: self.settlement_proposal.payment_session,
+            settlement_proposal: self.settlement_proposal.key(),
+            operator,
+            approvals: self.settlement_proposal.approvals.len() as u8,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::{
+    token::{CloseAccount, Mint, Token, TokenAccount, TransferChecked, close_account, transfer_checked},
+};
+
+use crate::state::payment_session::{PaymentSession, PaymentSessionCancelled, PaymentSessionStatus};
+use crate::state::{MerchantRewards, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct CancelSession<'info> {
+
+    // merchant authority or an operator
+    pub authority: Signer<'info>,
+
+    // receives the rent of the session and its escrow
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = payer,
+        close = payer,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    // Token accounts, omitted for native SOL sessions
+    #[account(
+        constraint = token_mint.key() == payment_session.load()?.token_mint @ PaymentError::InvalidMint,
+    )]
+    pub token_mint: Option<Account<'info, Mint>>,
+
+    // escrow ATA of the session, for the mint it was opened with
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = settlement_authority,
+    )]
+    pub escrow_ata: Option<Account<'info, TokenAccount>>,
+
+    // receives anything sent to the escrow before the cancel
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = payer,
+    )]
+    pub payer_ata: Option<Account<'info, TokenAccount>>,
+
+    // PDA authority over escrow_ata, or the lamport escrow for native SOL sessions
+    #[account(
+        mut,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    pub settlement_authority: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // Merchant record of the session, only required when the merchant cancels
+    #[account(
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Option<Account<'info, MerchantRewards>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelSession<'info> {
+    pub fn cancel_session(
+        &mut self,
+    ) -> Result<()> {
+
+        let authority = self.authority.key();
+        let is_merchant = self.merchant_rewards
+            .as_ref()
+            .is_some_and(|merchant_rewards| merchant_rewards.authority == authority);
+
+        require!(
+            is_merchant || self.config.is_operator(&authority),
+            PaymentError::UnauthorizedCancel
+        );
+
+        let payment_session = self.payment_session.load()?;
+
+        // only sessions the shopper has not funded yet can be voided
+        payment_session.require_status(PaymentSessionStatus::Initialized)?;
+
+        let payment_key = self.payment_session.key();
+        let uuid = payment_session.uuid;
+        let settlement_bump = payment_session.settlement_bump;
+
+        let seeds = &[
+            b"settlement_authority",
+            payment_key.as_ref(),
+            uuid.as_ref(),
+            &[settlement_bump]
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        if payment_session.is_native() {
+            // return the rent reserve held by the lamport escrow
+            let cpi_accounts = Transfer {
+                from: self.settlement_authority.to_account_info(),
+                to: self.payer.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds
+            );
+
+            transfer(cpi_ctx, self.settlement_authority.lamports())?;
+        } else {
+            let token_mint = self.token_mint.as_ref().ok_or(PaymentError::MissingTokenAccounts)?;
+            let escrow_ata = self.escrow_ata.as_ref().ok_or(PaymentError::MissingTokenAccounts)?;
+
+            // tokens sent straight to the escrow of an unfunded session go
+            // back to the payer, close_account only accepts an empty account
+            if escrow_ata.amount > 0 {
+                let payer_ata = self.payer_ata.as_ref().ok_or(PaymentError::MissingTokenAccounts)?;
+
+                let cpi_accounts = TransferChecked {
+                    from: escrow_ata.to_account_info(),
+                    to: payer_ata.to_account_info(),
+                    authority: self.settlement_authority.to_account_info(),
+                    mint: token_mint.to_account_info(),
+                };
+
+                let cpi_ctx = CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    cpi_accounts,
+                    signer_seeds
+                );
+
+                transfer_checked(cpi_ctx, escrow_ata.amount, token_mint.decimals)?;
+            }
+
+            // close the escrow ATA, returning its rent to the payer
+            let cpi_accounts = CloseAccount {
+                account: escrow_ata.to_account_info(),
+                destination: self.payer.to_account_info(),
+                authority: self.settlement_authority.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                cpi_accounts,
+                signer_seeds
+            );
+
+            close_account(cpi_ctx)?;
+        }
+
+        // the session PDA itself is closed to the payer on exit
+        emit!(PaymentSessionCancelled {
+            payment_session: payment_key,
+            payer: payment_session.payer,
+            merchant_id: payment_session.merchant_id(),
+            reference_id: payment_session.reference_id(),
+            cancelled_by: authority,
+            status: PaymentSessionStatus::Cancelled,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ProgramConfig, SettlementProposal, SettlementProposalCancelled};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct CancelSettlementProposal<'info> {
+
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        has_one = proposer,
+        close = proposer,
+        seeds = [b"settlement_proposal", settlement_proposal.payment_session.as_ref()],
+        bump = settlement_proposal.bump,
+    )]
+    pub settlement_proposal: Account<'info, SettlementProposal>,
+
+    // Operator that opened the proposal, gets its rent back
+    #[account(mut)]
+    pub proposer: SystemAccount<'info>,
+}
+
+impl<'info> CancelSettlementProposal<'info> {
+    // The other operators cancelling with `operator` sign the transaction and
+    // are passed as remaining accounts. Closing the proposal frees its PDA so
+    // the session can be proposed again.
+    pub fn cancel_settlement_proposal(
+        &mut self,
+        co_signers: &[AccountInfo<'info>],
+    ) -> Result<()> {
+
+        require!(!self.settlement_proposal.executed, PaymentError::ProposalAlreadyExecuted);
+
+        let mut cancelled_by = vec![self.operator.key()];
+        for co_signer in co_signers {
+            require!(co_signer.is_signer, PaymentError::UnauthorizedOperator);
+            if !cancelled_by.contains(co_signer.key) {
+                cancelled_by.push(co_signer.key());
+            }
+        }
+
+        require!(
+            cancelled_by.iter().all(|operator| self.config.is_operator(operator)),
+            PaymentError::UnauthorizedOperator
+        );
+        require!(
+            cancelled_by.len() >= self.config.threshold as usize,
+            PaymentError::InsufficientApprovals
+        );
+
+        emit!(SettlementProposalCancelled {
+            payment_session: self.settlement_proposal.payment_session,
+            settlement_proposal: self.settlement_proposal.key(),
+            cancelled_by,
+        });
+
+        Ok(())
+    }
+}
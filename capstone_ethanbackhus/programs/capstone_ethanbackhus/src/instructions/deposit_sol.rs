@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::payment_session::{PaymentSession, PaymentSessionCreated, PaymentSessionStatus};
+use crate::state::{PayerProfile, ProgramConfig};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct DepositSol<'info> {
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    // Holds the escrowed lamports
+    #[account(
+        mut,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    pub settlement_authority: SystemAccount<'info>,
+
+    // Program config holding the default spending limits
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // Rolling 24h / 30d lamport volume, keyed by the unset mint
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PayerProfile::DISCRIMINATOR.len() + PayerProfile::INIT_SPACE,
+        seeds = [b"payer_profile", payer.key().as_ref(), Pubkey::default().as_ref()],
+        bump
+    )]
+    pub payer_profile: Account<'info, PayerProfile>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DepositSol<'info> {
+    pub fn deposit_sol(
+        &mut self,
+        tip_amount: u64,
+        bumps: &DepositSolBumps,
+    ) -> Result<()> {
+
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut payment_session = self.payment_session.load_mut()?;
+
+        require!(payment_session.is_native(), PaymentError::InvalidEscrowMode);
+        payment_session.require_depositable(now)?;
+
+        // loyalty redemption is only offered on token sessions
+        payment_session.tip_amount = tip_amount;
+        let total = payment_session.escrow_total()?;
+
+        if self.payer_profile.payer == Pubkey::default() {
+            self.payer_profile.payer = self.payer.key();
+            self.payer_profile.token_mint = Pubkey::default();
+            self.payer_profile.bump = bumps.payer_profile;
+        }
+
+        self.payer_profile.record_deposit(
+            total,
+            now,
+            self.config.native_limits.daily_limit,
+            self.config.native_limits.monthly_limit,
+        )?;
+
+        let cpi_accounts = Transfer {
+            from: self.payer.to_account_info(),
+            to: self.settlement_authority.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, total)?;
+
+        payment_session.funded_ts = now;
+        payment_session.set_status(PaymentSessionStatus::Funded);
+
+        // the lamports sit on the settlement authority, so it stands in for the escrow ATA
+        emit!(PaymentSessionCreated {
+            payer: self.payer.key(),
+            merchant_id: payment_session.merchant_id(),
+            amount: payment_session.amount,
+            tip_amount,
+            discount_amount: 0,
+            token_mint: Pubkey::default(),
+            escrow_ata: self.settlement_authority.key(),
+            payer_ata: self.payer.key(),
+            status: PaymentSessionStatus::Funded,
+            expiry_ts: payment_session.expiry_ts,
+            created_ts: payment_session.created_ts,
+            funded_ts: payment_session.funded_ts(),
+            settled_ts: payment_session.settled_ts(),
+            reference_id: payment_session.reference_id(),
+            settlement_authority: payment_session.settlement_authority,
+        });
+
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    token::{Mint, Token, TokenAccount, transfer_checked, TransferChecked},
+    token::{Burn, Mint, Token, TokenAccount, burn, transfer_checked, TransferChecked},
 };
 
 use crate::state::payment_session::{PaymentSession, PaymentSessionCreated, PaymentSessionStatus};
+use crate::state::{LoyaltyRedeemed, PayerProfile, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
 
 
 #[derive(Accounts)]
@@ -12,31 +14,113 @@ pub struct DepositStablecoin<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    #[account(mut)]
-    pub payment_session: Account<'info, PaymentSession>,
+    #[account(
+        mut,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
 
     #[account(mut)]
     pub payer_ata: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    // escrow ATA of the session, for the mint it was opened with
+    #[account(
+        mut,
+        constraint = token_mint.key() == payment_session.load()?.token_mint @ PaymentError::InvalidMint,
+        associated_token::mint = token_mint,
+        associated_token::authority = settlement_authority,
+    )]
     pub escrow_ata: Account<'info, TokenAccount>,
 
     #[account(
-        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.uuid.as_ref()],
-        bump = payment_session.settlement_bump,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
     )]
     /// CHECK: This PDA will be used as authority for settling payments
     pub settlement_authority: UncheckedAccount<'info>,
 
+    // Program config holding the default spending limits
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // Rolling 24h / 30d volume for this payer and the session's mint
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PayerProfile::DISCRIMINATOR.len() + PayerProfile::INIT_SPACE,
+        seeds = [b"payer_profile", payer.key().as_ref(), payment_session.load()?.token_mint.as_ref()],
+        bump
+    )]
+    pub payer_profile: Box<Account<'info, PayerProfile>>,
+
+    // Loyalty mint and payer token account, only required when redeeming.
+    // Only the session merchant's tokens for the session mint are accepted.
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), payment_session.load()?.token_mint.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::authority = payer,
+    )]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
     pub token_mint: Account<'info, Mint>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 impl<'info> DepositStablecoin <'info> {
     pub fn deposit_stablecoin(
-        &mut self
+        &mut self,
+        tip_amount: u64,
+        redeem_amount: u64,
+        bumps: &DepositStablecoinBumps,
     ) -> Result<()> {
 
+        // the funded timestamp drives the payer's velocity windows
+        let now = Clock::get()?.unix_timestamp;
+
+        let mut payment_session = self.payment_session.load_mut()?;
+
+        require!(!payment_session.is_native(), PaymentError::InvalidEscrowMode);
+        payment_session.require_depositable(now)?;
+        require!(
+            redeem_amount <= payment_session.amount,
+            PaymentError::DiscountExceedsAmount
+        );
+
+        // redeemed loyalty tokens are burned and discounted from the amount
+        if redeem_amount > 0 {
+            self.redeem_loyalty(redeem_amount, payment_session.merchant_id())?;
+        }
+
+        // the tip is held in escrow alongside the discounted amount
+        payment_session.tip_amount = tip_amount;
+        payment_session.discount_amount = redeem_amount;
+        let total = payment_session.escrow_total()?;
+
+
+        if self.payer_profile.payer == Pubkey::default() {
+            self.payer_profile.payer = self.payer.key();
+            self.payer_profile.token_mint = payment_session.token_mint;
+            self.payer_profile.bump = bumps.payer_profile;
+        }
+
+        self.payer_profile.record_deposit(
+            total,
+            now,
+            self.config.token_limits.daily_limit,
+            self.config.token_limits.monthly_limit,
+        )?;
+
         let cpi_accounts = TransferChecked {
             from: self.payer_ata.to_account_info(),
             to: self.escrow_ata.to_account_info(),
@@ -47,31 +131,65 @@ impl<'info> DepositStablecoin <'info> {
         let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
 
         // execute token transfer from payer_ata to escrow_ata
-        transfer_checked(cpi_ctx, self.payment_session.amount, self.token_mint.decimals)?;
+        transfer_checked(cpi_ctx, total, self.token_mint.decimals)?;
         
         // update PDA session status to funded
-        self.payment_session.status = PaymentSessionStatus::Funded;
+        payment_session.funded_ts = now;
+        payment_session.set_status(PaymentSessionStatus::Funded);
 
         // emit PaymentSession created event
         emit!(PaymentSessionCreated {
             payer: self.payer.key(),
-            merchant_id: self.payment_session.merchant_id.clone(),
-            amount: self.payment_session.amount,
+            merchant_id: payment_session.merchant_id(),
+            amount: payment_session.amount,
+            tip_amount: payment_session.tip_amount,
+            discount_amount: payment_session.discount_amount,
             token_mint: self.token_mint.key(),
             escrow_ata: self.escrow_ata.key(),
             payer_ata: self.payer_ata.key(),
-            status: self.payment_session.status.clone(),
-            expiry_ts: self.payment_session.expiry_ts,
-            created_ts: self.payment_session.created_ts,
-            funded_ts: self.payment_session.funded_ts,
-            settled_ts: self.payment_session.settled_ts,
-            reference_id: self.payment_session.reference_id.clone(),
-            settlement_authority: self.payment_session.settlement_authority,
+            status: PaymentSessionStatus::Funded,
+            expiry_ts: payment_session.expiry_ts,
+            created_ts: payment_session.created_ts,
+            funded_ts: payment_session.funded_ts(),
+            settled_ts: payment_session.settled_ts(),
+            reference_id: payment_session.reference_id(),
+            settlement_authority: payment_session.settlement_authority,
         });
 
         // then webhook will detect event
 
         Ok(())
     }
-}
 
+    pub fn redeem_loyalty(
+        &self,
+        redeem_amount: u64,
+        merchant_id: String,
+    ) -> Result<()> {
+
+        let (loyalty_mint, payer_loyalty_ata) = self.loyalty_mint.as_ref()
+            .zip(self.payer_loyalty_ata.as_ref())
+            .ok_or(PaymentError::MissingLoyaltyAccounts)?;
+
+        require_keys_eq!(payer_loyalty_ata.mint, loyalty_mint.key(), PaymentError::InvalidLoyaltyAccount);
+
+        let cpi_accounts = Burn {
+            mint: loyalty_mint.to_account_info(),
+            from: payer_loyalty_ata.to_account_info(),
+            authority: self.payer.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        burn(cpi_ctx, redeem_amount)?;
+
+        emit!(LoyaltyRedeemed {
+            payer: self.payer.key(),
+            merchant_id,
+            payment_session: self.payment_session.key(),
+            discount_amount: redeem_amount,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, Token, TokenAccount},
+};
+
+use crate::instructions::mark_payment_settled::{mint_cashback, settle_escrow};
+use crate::state::{MerchantRewards, PaymentSession, PaymentSessionStatus, ProgramConfig, SettlementProposal, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct ExecuteSettlement<'info> {
+
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payment_session.load()?.payer.as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    #[account(
+        mut,
+        has_one = payment_session,
+        seeds = [b"settlement_proposal", payment_session.key().as_ref()],
+        bump = settlement_proposal.bump,
+    )]
+    pub settlement_proposal: Account<'info, SettlementProposal>,
+
+    // escrow token account (tokens temporarily held here), the session's ATA
+    // for the mint it was opened with
+    #[account(
+        mut,
+        constraint = token_mint.key() == payment_session.load()?.token_mint @ PaymentError::InvalidMint,
+        associated_token::mint = token_mint,
+        associated_token::authority = settlement_authority,
+    )]
+    pub escrow_ata: Account<'info, TokenAccount>,
+
+    // PDA authority over escrow_ata
+    #[account(
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    /// CHECK: This PDA signs the escrow transfer
+    pub settlement_authority: UncheckedAccount<'info>,
+
+    // Bitpay Deposit ATA, must be the destination the operators approved and
+    // owned by the merchant's registered payout wallet
+    #[account(
+        mut,
+        address = settlement_proposal.destination @ PaymentError::ProposalMismatch,
+        constraint = bitpay_ata.owner == merchant_rewards.payout_wallet @ PaymentError::InvalidPayoutDestination,
+    )]
+    pub bitpay_ata: Account<'info, TokenAccount>,
+
+    // Merchant tip destination, only required when the payer added a tip
+    #[account(
+        mut,
+        token::mint = token_mint,
+        constraint = tip_ata.owner == merchant_rewards.tip_wallet @ PaymentError::InvalidTipDestination,
+    )]
+    pub tip_ata: Option<Account<'info, TokenAccount>>,
+
+    // Merchant record of the session, holds the cashback rate and the payout and tip wallets
+    #[account(
+        mut,
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // The merchant's loyalty mint for the session mint, only required when
+    // cashback is paid
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), payment_session.load()?.token_mint.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    // Payer's loyalty token account, receives the cashback
+    #[account(mut)]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ExecuteSettlement<'info> {
+    pub fn execute_settlement(
+        &mut self,
+    ) -> Result<()> {
+
+        require!(
+            self.config.is_operator(&self.operator.key()),
+            PaymentError::UnauthorizedOperator
+        );
+        require!(!self.settlement_proposal.executed, PaymentError::ProposalAlreadyExecuted);
+
+        let payment_session = self.payment_session.load()?;
+        payment_session.require_status(PaymentSessionStatus::Funded)?;
+        require!(
+            payment_session.escrow_total()? == self.settlement_proposal.amount,
+            PaymentError::ProposalMismatch
+        );
+        drop(payment_session);
+
+        require!(
+            self.settlement_proposal.approval_count(&self.config) >= self.config.threshold as usize,
+            PaymentError::InsufficientApprovals
+        );
+
+        settle_escrow(
+            &self.payment_session,
+            &self.escrow_ata,
+            &self.settlement_authority,
+            &self.bitpay_ata,
+            self.tip_ata.as_ref(),
+            &self.token_mint,
+            &self.token_program,
+        )?;
+
+        mint_cashback(
+            &self.payment_session,
+            &self.config,
+            &mut self.merchant_rewards,
+            self.loyalty_mint.as_ref(),
+            self.payer_loyalty_ata.as_ref(),
+            &self.token_program,
+        )?;
+
+        self.settlement_proposal.executed = true;
+        self.settlement_proposal.executed_ts = Some(Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+}
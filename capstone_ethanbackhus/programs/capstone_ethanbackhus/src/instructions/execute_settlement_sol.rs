@@ -0,0 +1,138 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{spl_token::native_mint, Mint, Token, TokenAccount};
+
+use crate::instructions::mark_payment_settled::mint_cashback;
+use crate::instructions::settle_sol::settle_lamports;
+use crate::state::{MerchantRewards, PaymentSession, PaymentSessionStatus, ProgramConfig, SettlementProposal, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct ExecuteSettlementSol<'info> {
+
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payment_session.load()?.payer.as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    #[account(
+        mut,
+        has_one = payment_session,
+        seeds = [b"settlement_proposal", payment_session.key().as_ref()],
+        bump = settlement_proposal.bump,
+    )]
+    pub settlement_proposal: Account<'info, SettlementProposal>,
+
+    // Holds the escrowed lamports
+    #[account(
+        mut,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    pub settlement_authority: SystemAccount<'info>,
+
+    // Bitpay deposit wallet, must be the destination the operators approved
+    // and the merchant's registered payout wallet
+    #[account(
+        mut,
+        address = settlement_proposal.destination @ PaymentError::ProposalMismatch,
+        constraint = bitpay_wallet.key() == merchant_rewards.payout_wallet @ PaymentError::InvalidPayoutDestination,
+    )]
+    pub bitpay_wallet: SystemAccount<'info>,
+
+    // Merchant tip destination, only required when the payer added a tip
+    #[account(
+        mut,
+        constraint = tip_wallet.key() == merchant_rewards.tip_wallet @ PaymentError::InvalidTipDestination,
+    )]
+    pub tip_wallet: Option<SystemAccount<'info>>,
+
+    // Merchant record of the session, holds the cashback rate and the payout and tip wallets
+    #[account(
+        mut,
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // The merchant's loyalty mint for wrapped SOL, only required when
+    // cashback is paid
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), native_mint::ID.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    // Payer's loyalty token account, receives the cashback
+    #[account(mut)]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
+    // Session payer, receives the escrow's rent reserve
+    #[account(
+        mut,
+        address = payment_session.load()?.payer @ ErrorCode::ConstraintHasOne,
+    )]
+    pub payer: SystemAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ExecuteSettlementSol<'info> {
+    pub fn execute_settlement_sol(
+        &mut self,
+    ) -> Result<()> {
+
+        require!(
+            self.config.is_operator(&self.operator.key()),
+            PaymentError::UnauthorizedOperator
+        );
+        require!(!self.settlement_proposal.executed, PaymentError::ProposalAlreadyExecuted);
+
+        let payment_session = self.payment_session.load()?;
+        payment_session.require_status(PaymentSessionStatus::Funded)?;
+        require!(
+            payment_session.escrow_total()? == self.settlement_proposal.amount,
+            PaymentError::ProposalMismatch
+        );
+        drop(payment_session);
+
+        require!(
+            self.settlement_proposal.approval_count(&self.config) >= self.config.threshold as usize,
+            PaymentError::InsufficientApprovals
+        );
+
+        settle_lamports(
+            &self.payment_session,
+            &self.settlement_authority,
+            &self.bitpay_wallet,
+            self.tip_wallet.as_ref(),
+            &self.payer.to_account_info(),
+            &self.system_program,
+        )?;
+
+        mint_cashback(
+            &self.payment_session,
+            &self.config,
+            &mut self.merchant_rewards,
+            self.loyalty_mint.as_ref(),
+            self.payer_loyalty_ata.as_ref(),
+            &self.token_program,
+        )?;
+
+        self.settlement_proposal.executed = true;
+        self.settlement_proposal.executed_ts = Some(Clock::get()?.unix_timestamp);
+
+        Ok(())
+    }
+}
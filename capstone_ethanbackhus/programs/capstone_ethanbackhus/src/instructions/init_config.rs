@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::program::CapstoneEthanbackhus;
+use crate::state::program_config::{EscrowLimits, ProgramConfig, ProgramConfigInitialized, DEFAULT_MAX_CASHBACK_BPS, MAX_OPERATORS};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ProgramConfig::DISCRIMINATOR.len() + ProgramConfig::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // only the program's upgrade authority can create the config
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()) @ PaymentError::UnauthorizedAdmin,
+    )]
+    pub program: Program<'info, CapstoneEthanbackhus>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key()) @ PaymentError::UnauthorizedAdmin,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> InitConfig<'info> {
+    pub fn init_config(
+        &mut self,
+        operators: Vec<Pubkey>,
+        threshold: u8,
+        token_limits: EscrowLimits,
+        native_limits: EscrowLimits,
+        bumps: &InitConfigBumps,
+    ) -> Result<()> {
+
+        require!(operators.len() <= MAX_OPERATORS, PaymentError::TooManyOperators);
+        for (i, operator) in operators.iter().enumerate() {
+            require!(!operators[..i].contains(operator), PaymentError::DuplicateOperator);
+        }
+        require!(
+            threshold > 0 && threshold as usize <= operators.len(),
+            PaymentError::InvalidThreshold
+        );
+        for limits in [&token_limits, &native_limits] {
+            require!(limits.daily_limit <= limits.monthly_limit, PaymentError::InvalidLimits);
+        }
+
+        self.config.set_inner(ProgramConfig {
+            admin: self.admin.key(),
+            operators: operators.clone(),
+            threshold,
+            token_limits,
+            native_limits,
+            max_cashback_bps: DEFAULT_MAX_CASHBACK_BPS,
+            bump: bumps.config,
+        });
+
+        emit!(ProgramConfigInitialized {
+            admin: self.admin.key(),
+            operators,
+            threshold,
+            token_limits,
+            native_limits,
+        });
+
+        Ok(())
+    }
+}
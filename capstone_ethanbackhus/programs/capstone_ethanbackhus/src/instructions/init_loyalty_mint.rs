@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, Token},
+};
+
+use crate::state::{LoyaltyMintInitialized, MerchantRewards, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct InitLoyaltyMint<'info> {
+
+    // The merchant or an operator
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        seeds = [b"merchant_rewards", merchant_id_hash(&merchant_rewards.merchant_id).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // Payment token the loyalty tokens are redeemed against
+    pub token_mint: Account<'info, Mint>,
+
+    // Loyalty mint for this merchant and payment token, the config PDA is the
+    // only mint authority. Matching the payment token's decimals keeps one
+    // loyalty base unit worth one payment base unit.
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"loyalty_mint", merchant_id_hash(&merchant_rewards.merchant_id).as_ref(), token_mint.key().as_ref()],
+        bump,
+        mint::decimals = token_mint.decimals,
+        mint::authority = config,
+    )]
+    pub loyalty_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> InitLoyaltyMint<'info> {
+    pub fn init_loyalty_mint(
+        &mut self,
+    ) -> Result<()> {
+
+        let authority = self.authority.key();
+        require!(
+            authority == self.merchant_rewards.authority || self.config.is_operator(&authority),
+            PaymentError::UnauthorizedMerchant
+        );
+
+        emit!(LoyaltyMintInitialized {
+            merchant_id: self.merchant_rewards.merchant_id.clone(),
+            token_mint: self.token_mint.key(),
+            loyalty_mint: self.loyalty_mint.key(),
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{MerchantRewards, CashbackRateUpdated, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+#[instruction(merchant_id: String)]
+pub struct InitMerchantRewards<'info> {
+
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // one record per merchant id, sessions find it from their merchant_id
+    #[account(
+        init,
+        payer = operator,
+        space = MerchantRewards::DISCRIMINATOR.len() + MerchantRewards::INIT_SPACE,
+        seeds = [b"merchant_rewards", merchant_id_hash(&merchant_id).as_ref()],
+        bump
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> InitMerchantRewards<'info> {
+    pub fn init_merchant_rewards(
+        &mut self,
+        merchant_id: String,
+        authority: Pubkey,
+        tip_wallet: Pubkey,
+        payout_wallet: Pubkey,
+        cashback_bps: u16,
+        bumps: &InitMerchantRewardsBumps,
+    ) -> Result<()> {
+
+        require!(
+            self.config.is_operator(&self.operator.key()),
+            PaymentError::UnauthorizedOperator
+        );
+        require!(cashback_bps <= self.config.max_cashback_bps, PaymentError::InvalidCashbackRate);
+
+        self.merchant_rewards.set_inner(MerchantRewards {
+            merchant_id: merchant_id.clone(),
+            authority,
+            tip_wallet,
+            payout_wallet,
+            cashback_bps,
+            total_cashback_minted: 0,
+            bump: bumps.merchant_rewards,
+        });
+
+        emit!(CashbackRateUpdated {
+            merchant_id,
+            authority,
+            cashback_bps,
+        });
+
+        Ok(())
+    }
+}
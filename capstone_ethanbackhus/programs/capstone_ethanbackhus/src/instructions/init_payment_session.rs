@@ -1,26 +1,39 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::{
-    associated_token::{AssociatedToken, create},
+    associated_token::{AssociatedToken, create, get_associated_token_address},
     token::{Mint, Token, TokenAccount},
 };
 
-use crate::state::payment_session::{PaymentSession, PaymentSessionStatus};
+use crate::state::payment_session::{PaymentSession, PaymentSessionStatus, PAYMENT_SESSION_VERSION, to_fixed};
+use crate::state::merchant_rewards::{MerchantRewards, merchant_id_hash};
+use crate::state::session_index::{MerchantSessionIndex, PayerSessionIndex};
+use crate::errors::PaymentError;
 
 #[derive(Accounts)]
-#[instruction(uuid: [u8; 16])]
+#[instruction(
+    uuid: [u8; 16],
+    merchant_id: String,
+    amount: u64,
+    reference_id: String,
+    fiat_currency: String,
+    merchant_bank: String,
+    merchant_index_page: u32,
+    payer_index_page: u32,
+)]
 pub struct InitPaymentSession<'info> {
 
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    pub token_mint: Account<'info, Mint>,
+    // Token accounts, omitted for native SOL sessions
+    pub token_mint: Option<Account<'info, Mint>>,
 
     #[account(
         mut,
-        associated_token::mint = token_mint,
-        associated_token::authority = payer,
+        token::authority = payer,
     )]
-    pub payer_ata: Account<'info, TokenAccount>,
+    pub payer_ata: Option<Account<'info, TokenAccount>>,
 
     #[account(
         init,
@@ -29,18 +42,58 @@ pub struct InitPaymentSession<'info> {
         seeds = [b"payment_session", payer.key().as_ref(), uuid.as_ref()],
         bump
     )]
-    pub payment_session: Account<'info, PaymentSession>,
+    pub payment_session: AccountLoader<'info, PaymentSession>,
 
     #[account(mut)]
     /// CHECK: will be created via CPI
-    pub escrow_ata: UncheckedAccount<'info>,
+    pub escrow_ata: Option<UncheckedAccount<'info>>,
 
+    // Owns the escrow ATA, or holds the lamports itself for native SOL sessions
     #[account(
+        mut,
         seeds = [b"settlement_authority", payment_session.key().as_ref(), uuid.as_ref()],
         bump
     )]
-    /// CHECK: This PDA will be used as authority for settling payments
-    pub settlement_authority: UncheckedAccount<'info>,
+    pub settlement_authority: SystemAccount<'info>,
+
+    // Sessions can only be opened for registered merchants, settlement pays
+    // out through this record
+    #[account(
+        seeds = [b"merchant_rewards", merchant_id_hash(&merchant_id).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+    // Paged session lists for merchant and payer dashboards. The previous
+    // page is only needed when a session opens a new page.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = MerchantSessionIndex::DISCRIMINATOR.len() + MerchantSessionIndex::INIT_SPACE,
+        seeds = [b"merchant_session_index", merchant_id_hash(&merchant_id).as_ref(), merchant_index_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub merchant_session_index: Box<Account<'info, MerchantSessionIndex>>,
+
+    #[account(
+        seeds = [b"merchant_session_index", merchant_id_hash(&merchant_id).as_ref(), merchant_index_page.saturating_sub(1).to_le_bytes().as_ref()],
+        bump = previous_merchant_session_index.bump,
+    )]
+    pub previous_merchant_session_index: Option<Box<Account<'info, MerchantSessionIndex>>>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PayerSessionIndex::DISCRIMINATOR.len() + PayerSessionIndex::INIT_SPACE,
+        seeds = [b"payer_session_index", payer.key().as_ref(), payer_index_page.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub payer_session_index: Box<Account<'info, PayerSessionIndex>>,
+
+    #[account(
+        seeds = [b"payer_session_index", payer.key().as_ref(), payer_index_page.saturating_sub(1).to_le_bytes().as_ref()],
+        bump = previous_payer_session_index.bump,
+    )]
+    pub previous_payer_session_index: Option<Box<Account<'info, PayerSessionIndex>>>,
 
     pub token_program: Program<'info, Token>,
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -48,6 +101,7 @@ pub struct InitPaymentSession<'info> {
 }
 
 impl<'info> InitPaymentSession<'info> {
+        #[allow(clippy::too_many_arguments)]
         pub fn initialize(
         &mut self,
         uuid: [u8; 16],
@@ -56,6 +110,8 @@ impl<'info> InitPaymentSession<'info> {
         reference_id: String,
         fiat_currency: String,
         merchant_bank: String,
+        merchant_index_page: u32,
+        payer_index_page: u32,
         bumps: &InitPaymentSessionBumps,
     ) -> Result<()> {
         let now = Clock::get()?.unix_timestamp;
@@ -63,46 +119,105 @@ impl<'info> InitPaymentSession<'info> {
         // it will expire in 60 seconds for testing purposes
         let expiry_ts = now + 60;
         
-        // Create escrow ATA via CPI (owned by payment_session PDA)
+        let native = self.token_mint.is_none();
+
+        let token_mint = match &self.token_mint {
+            Some(token_mint) => {
+                self.create_escrow_ata(token_mint)?;
+                token_mint.key()
+            }
+            None => {
+                self.fund_native_escrow()?;
+                Pubkey::default()
+            }
+        };
+
+        let settlement_pda = self.settlement_authority.key();
+
+        // Initialize PaymentSession in place, the payer and escrow ATAs are derived
+        let mut payment_session = self.payment_session.load_init()?;
+
+        payment_session.payer = self.payer.key();
+        payment_session.merchant_id = to_fixed(&merchant_id)?;
+        payment_session.amount = amount;
+        payment_session.tip_amount = 0; // chosen by the payer at deposit time
+        payment_session.discount_amount = 0; // loyalty tokens redeemed at deposit time
+        payment_session.token_mint = token_mint; // unset for native SOL sessions
+        payment_session.settlement_authority = settlement_pda;
+        payment_session.settlement_bump = bumps.settlement_authority;
+        payment_session.set_status(PaymentSessionStatus::Initialized);
+        payment_session.expiry_ts = expiry_ts;
+        payment_session.created_ts = now;
+        payment_session.bump = bumps.payment_session;
+        payment_session.reference_id = to_fixed(&reference_id)?;
+        payment_session.uuid = uuid;
+        payment_session.fiat_currency = to_fixed(&fiat_currency)?;
+        payment_session.merchant_bank = to_fixed(&merchant_bank)?;
+        payment_session.version = PAYMENT_SESSION_VERSION;
+        payment_session.native = native as u8;
+        // bitpay_payout_id stays zeroed until payout creation
+
+        drop(payment_session);
+
+        let session_key = self.payment_session.key();
+
+        self.merchant_session_index.merchant_id_hash = merchant_id_hash(&merchant_id);
+        self.merchant_session_index.page = merchant_index_page;
+        self.merchant_session_index.bump = bumps.merchant_session_index;
+        self.merchant_session_index.append(
+            self.previous_merchant_session_index.as_deref().map(|previous| &**previous),
+            session_key,
+        )?;
+
+        self.payer_session_index.payer = self.payer.key();
+        self.payer_session_index.page = payer_index_page;
+        self.payer_session_index.bump = bumps.payer_session_index;
+        self.payer_session_index.append(
+            self.previous_payer_session_index.as_deref().map(|previous| &**previous),
+            session_key,
+        )?;
+
+        Ok(())
+    }
+
+    fn create_escrow_ata(&self, token_mint: &Account<'info, Mint>) -> Result<()> {
+        let payer_ata = self.payer_ata.as_ref().ok_or(PaymentError::MissingTokenAccounts)?;
+        let escrow_ata = self.escrow_ata.as_ref().ok_or(PaymentError::MissingTokenAccounts)?;
+
+        require_keys_eq!(
+            payer_ata.key(),
+            get_associated_token_address(&self.payer.key(), &token_mint.key()),
+            PaymentError::InvalidMint
+        );
+
+        // Create escrow ATA via CPI (owned by the settlement authority PDA)
         create(
             CpiContext::new(
                 self.associated_token_program.to_account_info(),
                 anchor_spl::associated_token::Create {
                     payer: self.payer.to_account_info(),
-                    associated_token: self.escrow_ata.to_account_info(),
+                    associated_token: escrow_ata.to_account_info(),
                     authority: self.settlement_authority.to_account_info(),
-                    mint: self.token_mint.to_account_info(),
+                    mint: token_mint.to_account_info(),
                     system_program: self.system_program.to_account_info(),
                     token_program: self.token_program.to_account_info(),
                 },
             ),
-        )?;
+        )
+    }
 
-        let settlement_pda = self.settlement_authority.key();
+    // The settlement authority holds native SOL escrows directly, so it is
+    // funded to rent exemption up front (same as the week2 vault).
+    fn fund_native_escrow(&self) -> Result<()> {
+        let rent_exempt = Rent::get()?.minimum_balance(self.settlement_authority.to_account_info().data_len());
 
-        // Initialize PaymentSession struct
-        self.payment_session.set_inner(PaymentSession {
-            payer: self.payer.key(),
-            merchant_id,
-            amount,
-            token_mint: self.token_mint.key(),
-            payer_ata: self.payer_ata.key(),
-            escrow_ata: self.escrow_ata.key(),
-            settlement_authority: settlement_pda,
-            settlement_bump: bumps.settlement_authority,
-            status: PaymentSessionStatus::Initialized,
-            expiry_ts,
-            created_ts: now,
-            funded_ts: None,
-            settled_ts: None,
-            bump: bumps.payment_session,
-            reference_id,
-            uuid,
-            fiat_currency: fiat_currency,
-            merchant_bank: merchant_bank,
-            bitpay_payout_id: None, // this wil be set later after payout creation
-        });
+        let cpi_accounts = Transfer {
+            from: self.payer.to_account_info(),
+            to: self.settlement_authority.to_account_info(),
+        };
 
-        Ok(())
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, rent_exempt)
     }
 }
\ No newline at end of file
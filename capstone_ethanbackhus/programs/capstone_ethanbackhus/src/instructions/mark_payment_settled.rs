@@ -1,9 +1,9 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    token::{Mint, Token, TokenAccount, TransferChecked, transfer_checked},
+    token::{Mint, MintTo, Token, TokenAccount, TransferChecked, mint_to, transfer_checked},
 };
 
-use crate::state::{PaymentSessionSettled, payment_session::{PaymentSession, PaymentSessionStatus}};
+use crate::state::{CashbackMinted, MerchantRewards, PaymentSessionSettled, ProgramConfig, merchant_id_hash, payment_session::{PaymentSession, PaymentSessionStatus}};
 use crate::{errors::PaymentError};
 
 #[derive(Accounts)]
@@ -14,31 +14,76 @@ pub struct MarkPaymentSettled<'info> {
 
     #[account(
         mut,
-        seeds = [b"payment_session", payer.key().as_ref(), payment_session.uuid.as_ref()],
-        bump = payment_session.bump,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
     )]
-    pub payment_session: Account<'info, PaymentSession>,
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    // Operator config, used to route large settlements through a proposal
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
 
     // Payer's token account (escrow source)
     #[account(mut)]
     pub payer_ata: Account<'info, TokenAccount>,
 
-    // escrow token account (tokens temporarily held here)
-    #[account(mut)]
+    // escrow token account (tokens temporarily held here), the session's ATA
+    // for the mint it was opened with
+    #[account(
+        mut,
+        constraint = token_mint.key() == payment_session.load()?.token_mint @ PaymentError::InvalidMint,
+        associated_token::mint = token_mint,
+        associated_token::authority = settlement_authority,
+    )]
     pub escrow_ata: Account<'info, TokenAccount>,
 
     // PDA authority over escrow_ata
     #[account(
-        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.uuid.as_ref()],
-        bump = payment_session.settlement_bump,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
     )]
     /// CHECK: This PDA signs the escrow transfer
     pub settlement_authority: UncheckedAccount<'info>,
 
-    // Bitpay Deposit ATA (controlled by off-chain integration)
-    #[account(mut)]
+    // Bitpay Deposit ATA, owned by the merchant's registered payout wallet
+    #[account(
+        mut,
+        constraint = bitpay_ata.owner == merchant_rewards.payout_wallet @ PaymentError::InvalidPayoutDestination,
+    )]
     pub bitpay_ata: Account<'info, TokenAccount>,
 
+    // Merchant tip destination, only required when the payer added a tip
+    #[account(
+        mut,
+        token::mint = token_mint,
+        constraint = tip_ata.owner == merchant_rewards.tip_wallet @ PaymentError::InvalidTipDestination,
+    )]
+    pub tip_ata: Option<Account<'info, TokenAccount>>,
+
+    // Merchant record of the session, holds the cashback rate and the payout and tip wallets
+    #[account(
+        mut,
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // The merchant's loyalty mint for the session mint, only required when
+    // cashback is paid
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), payment_session.load()?.token_mint.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    // Payer's loyalty token account, receives the cashback
+    #[account(mut)]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
     pub token_mint: Account<'info, Mint>,
 
     pub token_program: Program<'info, Token>,
@@ -49,57 +94,216 @@ impl<'info> MarkPaymentSettled <'info> {
         &mut self
     ) -> Result<()> {
 
-        // ensure token mint matches
-        require_keys_eq!(
-            self.escrow_ata.mint,
-            self.token_mint.key(),
-            PaymentError::InvalidMint
+        let payment_session = self.payment_session.load()?;
+
+        payment_session.require_status(PaymentSessionStatus::Funded)?;
+
+        // large settlements must go through an approved SettlementProposal
+        let total = payment_session.escrow_total()?;
+        drop(payment_session);
+
+        require!(
+            !self.config.requires_approval(total, false),
+            PaymentError::SettlementRequiresApproval
         );
-    
-        let payment_session_key = self.payment_session.key();
 
-        let settlement_seeds: &[&[u8]] = &[
-            b"settlement_authority",
-            payment_session_key.as_ref(),
-            self.payment_session.uuid.as_ref(),
-            &[self.payment_session.settlement_bump]
-        ];
+        settle_escrow(
+            &self.payment_session,
+            &self.escrow_ata,
+            &self.settlement_authority,
+            &self.bitpay_ata,
+            self.tip_ata.as_ref(),
+            &self.token_mint,
+            &self.token_program,
+        )?;
+
+        mint_cashback(
+            &self.payment_session,
+            &self.config,
+            &mut self.merchant_rewards,
+            self.loyalty_mint.as_ref(),
+            self.payer_loyalty_ata.as_ref(),
+            &self.token_program,
+        )
+    }
+}
 
-        let signer_seeds = &[settlement_seeds];
+// Moves the escrowed payment (and tip) out of the session escrow. Shared by
+// mark_payment_settled and execute_settlement.
+pub fn settle_escrow<'info>(
+    payment_session: &AccountLoader<'info, PaymentSession>,
+    escrow_ata: &Account<'info, TokenAccount>,
+    settlement_authority: &UncheckedAccount<'info>,
+    bitpay_ata: &Account<'info, TokenAccount>,
+    tip_ata: Option<&Account<'info, TokenAccount>>,
+    token_mint: &Account<'info, Mint>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+
+    // ensure token mint matches
+    require_keys_eq!(
+        escrow_ata.mint,
+        token_mint.key(),
+        PaymentError::InvalidMint
+    );
+
+    let payment_session_key = payment_session.key();
+    let mut session = payment_session.load_mut()?;
+
+    require!(!session.is_native(), PaymentError::InvalidEscrowMode);
+    require!(!session.has_milestones(), PaymentError::MilestoneScheduleActive);
+    let uuid = session.uuid;
+    let settlement_bump = session.settlement_bump;
+
+    let settlement_seeds: &[&[u8]] = &[
+        b"settlement_authority",
+        payment_session_key.as_ref(),
+        uuid.as_ref(),
+        &[settlement_bump]
+    ];
+
+    let signer_seeds = &[settlement_seeds];
+
+    // send payment to merchant from escrow_ata
+    let cpi_accounts = TransferChecked {
+        from: escrow_ata.to_account_info(),
+        to: bitpay_ata.to_account_info(),
+        authority: settlement_authority.to_account_info(),
+        mint: token_mint.to_account_info()
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(), 
+        cpi_accounts, 
+        signer_seeds
+    );
+
+    transfer_checked(cpi_ctx, session.net_amount(), token_mint.decimals)?;
+
+    // route the tip in full to the merchant's tip destination
+    let tip_amount = session.tip_amount;
+    let mut tip_destination = None;
+
+    if tip_amount > 0 {
+        let tip_ata = tip_ata.ok_or(PaymentError::MissingTipDestination)?;
 
-        // send payment to merchant from escrow_ata
         let cpi_accounts = TransferChecked {
-            from: self.escrow_ata.to_account_info(),
-            to: self.bitpay_ata.to_account_info(),
-            authority: self.settlement_authority.to_account_info(),
-            mint: self.token_mint.to_account_info()
+            from: escrow_ata.to_account_info(),
+            to: tip_ata.to_account_info(),
+            authority: settlement_authority.to_account_info(),
+            mint: token_mint.to_account_info()
         };
 
         let cpi_ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(), 
+            token_program.to_account_info(), 
             cpi_accounts, 
             signer_seeds
         );
 
-        transfer_checked(cpi_ctx, self.payment_session.amount, self.token_mint.decimals)?;
-        
-        // set paymentsession status to indicate off-chain payout pending
-        self.payment_session.status = PaymentSessionStatus::PendingFiat;
-
-        // emit PaymentSettled event
-        emit!(PaymentSessionSettled{
-            payer: self.payment_session.payer,
-            merchant_id: self.payment_session.merchant_id.clone(),
-            amount: self.payment_session.amount,
-            token_mint: self.payment_session.token_mint,
-            escrow_ata: self.payment_session.escrow_ata,
-            payer_ata: self.payment_session.payer_ata,
-            status: self.payment_session.status.clone(),
-            reference_id: self.payment_session.reference_id.clone(),
-            expiry_ts: self.payment_session.expiry_ts,
-            settlement_authority: self.payment_session.settlement_authority,
-        });
-
-        Ok(())
+        transfer_checked(cpi_ctx, tip_amount, token_mint.decimals)?;
+        tip_destination = Some(tip_ata.key());
     }
+    
+    // set paymentsession status to indicate off-chain payout pending
+    session.set_status(PaymentSessionStatus::PendingFiat);
+
+    // emit PaymentSettled event
+    emit!(PaymentSessionSettled{
+        payer: session.payer,
+        merchant_id: session.merchant_id(),
+        amount: session.amount,
+        tip_amount,
+        discount_amount: session.discount_amount,
+        token_mint: session.token_mint,
+        escrow_ata: escrow_ata.key(),
+        status: PaymentSessionStatus::PendingFiat,
+        reference_id: session.reference_id(),
+        expiry_ts: session.expiry_ts,
+        settlement_authority: session.settlement_authority,
+        tip_destination,
+    });
+
+    Ok(())
+}
+
+// Mints cashback on the settled (post discount) amount to the payer at the
+// merchant's configured rate. Shared by both settlement paths.
+pub fn mint_cashback<'info>(
+    payment_session: &AccountLoader<'info, PaymentSession>,
+    config: &Account<'info, ProgramConfig>,
+    merchant_rewards: &mut Account<'info, MerchantRewards>,
+    loyalty_mint: Option<&Account<'info, Mint>>,
+    payer_loyalty_ata: Option<&Account<'info, TokenAccount>>,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+
+    let session = payment_session.load()?;
+    let payer = session.payer;
+    let merchant_id = session.merchant_id();
+    let settled_amount = session.net_amount();
+    drop(session);
+
+    let cashback_amount = merchant_rewards.cashback_for(settled_amount, config.max_cashback_bps)?;
+
+    if cashback_amount == 0 {
+        return Ok(());
+    }
+
+    let (loyalty_mint, payer_loyalty_ata) = loyalty_mint
+        .zip(payer_loyalty_ata)
+        .ok_or(PaymentError::MissingLoyaltyAccounts)?;
+
+    mint_loyalty(
+        payment_session,
+        config,
+        loyalty_mint,
+        payer_loyalty_ata,
+        cashback_amount,
+        token_program,
+    )?;
+
+    merchant_rewards.total_cashback_minted = merchant_rewards.total_cashback_minted
+        .checked_add(cashback_amount)
+        .ok_or(PaymentError::Overflow)?;
+
+    emit!(CashbackMinted {
+        payer,
+        merchant_id,
+        payment_session: payment_session.key(),
+        settled_amount,
+        cashback_amount,
+    });
+
+    Ok(())
+}
+
+// Mints loyalty tokens to the session payer, signed by the config PDA.
+pub fn mint_loyalty<'info>(
+    payment_session: &AccountLoader<'info, PaymentSession>,
+    config: &Account<'info, ProgramConfig>,
+    loyalty_mint: &Account<'info, Mint>,
+    payer_loyalty_ata: &Account<'info, TokenAccount>,
+    amount: u64,
+    token_program: &Program<'info, Token>,
+) -> Result<()> {
+
+    require_keys_eq!(payer_loyalty_ata.mint, loyalty_mint.key(), PaymentError::InvalidLoyaltyAccount);
+    require_keys_eq!(payer_loyalty_ata.owner, payment_session.load()?.payer, PaymentError::InvalidLoyaltyAccount);
+
+    let config_seeds: &[&[u8]] = &[b"config", &[config.bump]];
+    let signer_seeds = &[config_seeds];
+
+    let cpi_accounts = MintTo {
+        mint: loyalty_mint.to_account_info(),
+        to: payer_loyalty_ata.to_account_info(),
+        authority: config.to_account_info(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds
+    );
+
+    mint_to(cpi_ctx, amount)
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::payment_session::{PaymentSession, PaymentSessionMigrated, PAYMENT_SESSION_VERSION};
+use crate::state::LegacyPaymentSession;
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct MigrateSession<'info> {
+
+    // Anyone can migrate a session, they only cover any extra rent
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    // Session payer, receives any rent freed by a smaller layout
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+
+    // Still on an older layout, so AccountLoader cannot read it yet
+    #[account(
+        mut,
+        owner = crate::ID,
+    )]
+    /// CHECK: discriminator, seeds and payer are verified in migrate_session
+    pub payment_session: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrateSession<'info> {
+    pub fn migrate_session(
+        &mut self,
+    ) -> Result<()> {
+
+        let session_info = self.payment_session.to_account_info();
+        let current_len = PaymentSession::DISCRIMINATOR.len() + PaymentSession::INIT_SPACE;
+
+        let legacy = {
+            let data = session_info.try_borrow_data()?;
+
+            require!(
+                data.starts_with(PaymentSession::DISCRIMINATOR),
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            require!(data.len() != current_len, PaymentError::SessionAlreadyMigrated);
+
+            LegacyPaymentSession::from_account_data(&data[PaymentSession::DISCRIMINATOR.len()..])?
+        };
+
+        let expected = Pubkey::create_program_address(
+            &[b"payment_session", legacy.payer.as_ref(), legacy.uuid.as_ref(), &[legacy.bump]],
+            &crate::ID,
+        ).map_err(|_| ErrorCode::ConstraintSeeds)?;
+
+        require_keys_eq!(expected, self.payment_session.key(), ErrorCode::ConstraintSeeds);
+        require_keys_eq!(legacy.payer, self.payer.key(), ErrorCode::ConstraintHasOne);
+
+        session_info.resize(current_len)?;
+
+        {
+            let mut data = session_info.try_borrow_mut_data()?;
+            let session: &mut PaymentSession = bytemuck::from_bytes_mut(&mut data[PaymentSession::DISCRIMINATOR.len()..]);
+            *session = legacy.into_current();
+        }
+
+        // settle the rent difference for the new account size
+        let rent_exempt = Rent::get()?.minimum_balance(current_len);
+        let lamports = session_info.lamports();
+
+        if lamports > rent_exempt {
+            let surplus = lamports - rent_exempt;
+            session_info.sub_lamports(surplus)?;
+            self.payer.add_lamports(surplus)?;
+        } else if lamports < rent_exempt {
+            let cpi_accounts = Transfer {
+                from: self.rent_payer.to_account_info(),
+                to: session_info.clone(),
+            };
+
+            let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+            transfer(cpi_ctx, rent_exempt - lamports)?;
+        }
+
+        emit!(PaymentSessionMigrated {
+            payment_session: self.payment_session.key(),
+            from_version: 0,
+            to_version: PAYMENT_SESSION_VERSION,
+        });
+
+        Ok(())
+    }
+}
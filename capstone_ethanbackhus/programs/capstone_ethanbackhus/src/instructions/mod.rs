@@ -2,9 +2,49 @@ pub mod init_payment_session;
 pub mod deposit_stablecoin;
 pub mod refund_payment;
 pub mod mark_payment_settled;
+pub mod init_config;
+pub mod propose_settlement;
+pub mod approve_settlement;
+pub mod revoke_settlement_approval;
+pub mod cancel_settlement_proposal;
+pub mod execute_settlement;
+pub mod execute_settlement_sol;
+pub mod update_spending_limits;
+pub mod set_payer_limits;
+pub mod init_loyalty_mint;
+pub mod init_merchant_rewards;
+pub mod set_cashback_rate;
+pub mod set_max_cashback_rate;
+pub mod migrate_session;
+pub mod cancel_session;
+pub mod deposit_sol;
+pub mod refund_sol;
+pub mod settle_sol;
+pub mod set_milestone_schedule;
+pub mod release_milestone;
 
 
 pub use init_payment_session::*;
 pub use deposit_stablecoin::*;
 pub use refund_payment::*;
-pub use mark_payment_settled::*;
\ No newline at end of file
+pub use mark_payment_settled::*;
+pub use init_config::*;
+pub use propose_settlement::*;
+pub use approve_settlement::*;
+pub use revoke_settlement_approval::*;
+pub use cancel_settlement_proposal::*;
+pub use execute_settlement::*;
+pub use execute_settlement_sol::*;
+pub use update_spending_limits::*;
+pub use set_payer_limits::*;
+pub use init_loyalty_mint::*;
+pub use init_merchant_rewards::*;
+pub use set_cashback_rate::*;
+pub use set_max_cashback_rate::*;
+pub use migrate_session::*;
+pub use cancel_session::*;
+pub use deposit_sol::*;
+pub use refund_sol::*;
+pub use settle_sol::*;
+pub use set_milestone_schedule::*;
+pub use release_milestone::*;
\ No newline at end of file
@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{self, TokenAccount},
+};
+
+use crate::state::{MerchantRewards, PaymentSession, PaymentSessionStatus, ProgramConfig, SettlementProposal, SettlementProposed, SettlementApproved, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct ProposeSettlement<'info> {
+
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        seeds = [b"payment_session", payment_session.load()?.payer.as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = SettlementProposal::DISCRIMINATOR.len() + SettlementProposal::INIT_SPACE,
+        seeds = [b"settlement_proposal", payment_session.key().as_ref()],
+        bump
+    )]
+    pub settlement_proposal: Account<'info, SettlementProposal>,
+
+    /// CHECK: the merchant's payout wallet for native SOL sessions, otherwise
+    /// a token account it owns for the session mint. Checked in the handler,
+    /// execute_settlement then pays out to exactly this account.
+    pub destination: UncheckedAccount<'info>,
+
+    // Merchant record of the session, holds the payout wallet
+    #[account(
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> ProposeSettlement<'info> {
+    pub fn propose_settlement(
+        &mut self,
+        bumps: &ProposeSettlementBumps,
+    ) -> Result<()> {
+
+        require!(
+            self.config.is_operator(&self.operator.key()),
+            PaymentError::UnauthorizedOperator
+        );
+
+        let payment_session = self.payment_session.load()?;
+
+        // only funded sessions can be proposed for settlement
+        payment_session.require_status(PaymentSessionStatus::Funded)?;
+
+        // approvals cover this exact payout, execute cannot redirect or resize it
+        let destination = self.destination.key();
+        let amount = payment_session.escrow_total()?;
+
+        // a proposal that execute would refuse only blocks the session, so the
+        // destination is checked against the payout wallet up front
+        let payout_wallet = self.merchant_rewards.payout_wallet;
+        if payment_session.is_native() {
+            require_keys_eq!(destination, payout_wallet, PaymentError::InvalidPayoutDestination);
+        } else {
+            require_keys_eq!(*self.destination.owner, token::ID, PaymentError::InvalidPayoutDestination);
+            let destination_ata = TokenAccount::try_deserialize(&mut &self.destination.try_borrow_data()?[..])?;
+            require_keys_eq!(destination_ata.mint, payment_session.token_mint, PaymentError::InvalidMint);
+            require_keys_eq!(destination_ata.owner, payout_wallet, PaymentError::InvalidPayoutDestination);
+        }
+
+        // the proposer's approval is recorded straight away
+        self.settlement_proposal.set_inner(SettlementProposal {
+            payment_session: self.payment_session.key(),
+            proposer: self.operator.key(),
+            destination,
+            amount,
+            approvals: vec![self.operator.key()],
+            executed: false,
+            created_ts: Clock::get()?.unix_timestamp,
+            executed_ts: None,
+            bump: bumps.settlement_proposal,
+        });
+
+        emit!(SettlementProposed {
+            payment_session: self.payment_session.key(),
+            settlement_proposal: self.settlement_proposal.key(),
+            proposer: self.operator.key(),
+            destination,
+            amount: payment_session.amount,
+            tip_amount: payment_session.tip_amount,
+        });
+
+        emit!(SettlementApproved {
+            payment_session: self.payment_session.key(),
+            settlement_proposal: self.settlement_proposal.key(),
+            operator: self.operator.key(),
+            approvals: 1,
+        });
+
+        Ok(())
+    }
+}
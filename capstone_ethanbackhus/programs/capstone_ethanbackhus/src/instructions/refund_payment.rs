@@ -1,10 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::{AssociatedToken},
     token::{Mint, Token, TokenAccount, transfer_checked, TransferChecked},
 };
 
+use crate::instructions::mark_payment_settled::mint_loyalty;
 use crate::state::payment_session::{PaymentSession, PaymentSessionStatus, PaymentSessionRefunded};
+use crate::state::{ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
 
 #[derive(Accounts)]
 pub struct RefundPayment<'info> {
@@ -12,23 +14,50 @@ pub struct RefundPayment<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    #[account(mut)]
-    pub payment_session: Account<'info, PaymentSession>,
+    #[account(
+        mut,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
 
     #[account(mut)]
     pub payer_ata: Account<'info, TokenAccount>,
 
-    #[account(mut)]
+    // escrow ATA of the session, for the mint it was opened with
+    #[account(
+        mut,
+        constraint = token_mint.key() == payment_session.load()?.token_mint @ PaymentError::InvalidMint,
+        associated_token::mint = token_mint,
+        associated_token::authority = settlement_authority,
+    )]
     pub escrow_ata: Account<'info, TokenAccount>,
 
     // PDA authority over escrow_ata
     #[account(
-        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.uuid.as_ref()],
-        bump = payment_session.settlement_bump,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
     )]
     /// CHECK: This PDA signs the escrow transfer
     pub settlement_authority: UncheckedAccount<'info>,
 
+    // Loyalty accounts, only required to restore a redeemed discount
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), payment_session.load()?.token_mint.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    #[account(mut)]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
     pub token_mint: Account<'info, Mint>,
 
     pub token_program: Program<'info, Token>,
@@ -39,13 +68,21 @@ impl<'info> RefundPayment <'info> {
         &mut self,
     ) -> Result<()> {
 
+        let payment_session = self.payment_session.load()?;
+
+        // only escrowed sessions can be refunded
+        require!(!payment_session.is_native(), PaymentError::InvalidEscrowMode);
+        payment_session.require_refundable(Clock::get()?.unix_timestamp)?;
+
         let payment_key = self.payment_session.key();
+        let uuid = payment_session.uuid;
+        let settlement_bump = payment_session.settlement_bump;
 
         let seeds = &[
             b"settlement_authority",
             payment_key.as_ref(),
-            self.payment_session.uuid.as_ref(),
-            &[self.payment_session.settlement_bump]
+            uuid.as_ref(),
+            &[settlement_bump]
         ];
 
         let signer_seeds = &[&seeds[..]];
@@ -64,23 +101,50 @@ impl<'info> RefundPayment <'info> {
             signer_seeds
         );
 
-        transfer_checked(cpi_ctx, self.payment_session.amount, self.token_mint.decimals)?;
+        // refund the tip together with the discounted payment amount, less
+        // any milestones already released to the merchant
+        let total = payment_session.remaining_escrow()?;
+
+        transfer_checked(cpi_ctx, total, self.token_mint.decimals)?;
+
+        // restore the loyalty tokens redeemed at deposit time, less the share
+        // covering milestones the merchant already received
+        let discount_amount = payment_session.refundable_discount()?;
+        drop(payment_session);
+
+        if discount_amount > 0 {
+            let config = self.config.as_ref().ok_or(PaymentError::MissingLoyaltyAccounts)?;
+            let loyalty_mint = self.loyalty_mint.as_ref().ok_or(PaymentError::MissingLoyaltyAccounts)?;
+            let payer_loyalty_ata = self.payer_loyalty_ata.as_ref().ok_or(PaymentError::MissingLoyaltyAccounts)?;
+
+            mint_loyalty(
+                &self.payment_session,
+                config,
+                loyalty_mint,
+                payer_loyalty_ata,
+                discount_amount,
+                &self.token_program,
+            )?;
+        }
         
         // set paymentsession status to refunded
-        self.payment_session.status = PaymentSessionStatus::Refunded;
+        let mut payment_session = self.payment_session.load_mut()?;
+        payment_session.set_status(PaymentSessionStatus::Refunded);
 
         // emit PaymentRefunded event
         emit!(PaymentSessionRefunded {
-            payer: self.payment_session.payer,
-            merchant_id: self.payment_session.merchant_id.clone(),
-            amount: self.payment_session.amount,
-            token_mint: self.payment_session.token_mint,
-            escrow_ata: self.payment_session.escrow_ata,
-            payer_ata: self.payment_session.payer_ata,
-            status: self.payment_session.status.clone(),
-            reference_id: self.payment_session.reference_id.clone(),
-            expiry_ts: self.payment_session.expiry_ts,
-            settlement_authority: self.payment_session.settlement_authority,
+            payer: payment_session.payer,
+            merchant_id: payment_session.merchant_id(),
+            amount: payment_session.amount,
+            tip_amount: payment_session.tip_amount,
+            discount_amount,
+            token_mint: payment_session.token_mint,
+            escrow_ata: self.escrow_ata.key(),
+            payer_ata: self.payer_ata.key(),
+            status: PaymentSessionStatus::Refunded,
+            reference_id: payment_session.reference_id(),
+            expiry_ts: payment_session.expiry_ts,
+            settlement_authority: payment_session.settlement_authority,
         });
 
         Ok(())
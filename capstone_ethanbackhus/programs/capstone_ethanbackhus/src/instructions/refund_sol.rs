@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::payment_session::{PaymentSession, PaymentSessionRefunded, PaymentSessionStatus};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct RefundSol<'info> {
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    // Holds the escrowed lamports
+    #[account(
+        mut,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    pub settlement_authority: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> RefundSol<'info> {
+    pub fn refund_sol(
+        &mut self,
+    ) -> Result<()> {
+
+        let mut payment_session = self.payment_session.load_mut()?;
+
+        require!(payment_session.is_native(), PaymentError::InvalidEscrowMode);
+        payment_session.require_refundable(Clock::get()?.unix_timestamp)?;
+
+        let payment_key = self.payment_session.key();
+        let seeds = &[
+            b"settlement_authority",
+            payment_key.as_ref(),
+            payment_session.uuid.as_ref(),
+            &[payment_session.settlement_bump]
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        // return the payment, the tip and the rent reserve in one transfer
+        let cpi_accounts = Transfer {
+            from: self.settlement_authority.to_account_info(),
+            to: self.payer.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        transfer(cpi_ctx, self.settlement_authority.lamports())?;
+
+        payment_session.set_status(PaymentSessionStatus::Refunded);
+
+        emit!(PaymentSessionRefunded {
+            payer: payment_session.payer,
+            merchant_id: payment_session.merchant_id(),
+            amount: payment_session.amount,
+            tip_amount: payment_session.tip_amount,
+            discount_amount: 0,
+            token_mint: Pubkey::default(),
+            escrow_ata: self.settlement_authority.key(),
+            payer_ata: self.payer.key(),
+            status: PaymentSessionStatus::Refunded,
+            reference_id: payment_session.reference_id(),
+            expiry_ts: payment_session.expiry_ts,
+            settlement_authority: payment_session.settlement_authority,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Mint, Token, TokenAccount, TransferChecked, transfer_checked},
+};
+
+use crate::instructions::mark_payment_settled::mint_cashback;
+use crate::state::payment_session::{PaymentSession, PaymentSessionSettled, PaymentSessionStatus};
+use crate::state::{MerchantRewards, MilestoneReleased, ProgramConfig, SettlementProposal, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct ReleaseMilestone<'info> {
+
+    // The payer confirms a milestone, anyone can release it after its timeout
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payment_session.load()?.payer.as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    // Operator config, large sessions must settle through a proposal
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // Proposal approved for the whole schedule, only required for large sessions
+    #[account(
+        mut,
+        has_one = payment_session,
+        seeds = [b"settlement_proposal", payment_session.key().as_ref()],
+        bump = settlement_proposal.bump,
+    )]
+    pub settlement_proposal: Option<Account<'info, SettlementProposal>>,
+
+    // escrow ATA of the session, for the mint it was opened with
+    #[account(
+        mut,
+        constraint = token_mint.key() == payment_session.load()?.token_mint @ PaymentError::InvalidMint,
+        associated_token::mint = token_mint,
+        associated_token::authority = settlement_authority,
+    )]
+    pub escrow_ata: Account<'info, TokenAccount>,
+
+    // PDA authority over escrow_ata
+    #[account(
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    /// CHECK: This PDA signs the escrow transfer
+    pub settlement_authority: UncheckedAccount<'info>,
+
+    // Bitpay Deposit ATA, owned by the merchant's registered payout wallet
+    #[account(
+        mut,
+        constraint = bitpay_ata.owner == merchant_rewards.payout_wallet @ PaymentError::InvalidPayoutDestination,
+    )]
+    pub bitpay_ata: Account<'info, TokenAccount>,
+
+    // Merchant tip destination, only required for the final milestone of a tipped session
+    #[account(
+        mut,
+        token::mint = token_mint,
+        constraint = tip_ata.owner == merchant_rewards.tip_wallet @ PaymentError::InvalidTipDestination,
+    )]
+    pub tip_ata: Option<Account<'info, TokenAccount>>,
+
+    // Merchant record of the session, holds the cashback rate and the payout and tip wallets
+    #[account(
+        mut,
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // The merchant's loyalty mint for the session mint, only required when
+    // the final milestone pays cashback
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), payment_session.load()?.token_mint.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    // Payer's loyalty token account, receives the cashback
+    #[account(mut)]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> ReleaseMilestone<'info> {
+    pub fn release_milestone(
+        &mut self,
+    ) -> Result<()> {
+
+        let now = Clock::get()?.unix_timestamp;
+        let payment_key = self.payment_session.key();
+        let mut payment_session = self.payment_session.load_mut()?;
+
+        require!(!payment_session.is_native(), PaymentError::InvalidEscrowMode);
+        require!(payment_session.has_milestones(), PaymentError::InvalidMilestoneSchedule);
+        payment_session.require_status(PaymentSessionStatus::Funded)?;
+
+        // large sessions release against a proposal the operators approved for
+        // the whole escrow, each milestone going to its destination
+        let escrow_total = payment_session.escrow_total()?;
+        if self.config.requires_approval(escrow_total, false) {
+            let proposal = self.settlement_proposal.as_ref().ok_or(PaymentError::SettlementRequiresApproval)?;
+
+            require!(!proposal.executed, PaymentError::ProposalAlreadyExecuted);
+            require!(
+                proposal.destination == self.bitpay_ata.key() && proposal.amount == escrow_total,
+                PaymentError::ProposalMismatch
+            );
+            require!(
+                proposal.approval_count(&self.config) >= self.config.threshold as usize,
+                PaymentError::InsufficientApprovals
+            );
+        }
+        require_keys_eq!(self.escrow_ata.mint, self.token_mint.key(), PaymentError::InvalidMint);
+
+        let index = payment_session.milestones_released as usize;
+        let released_by = self.authority.key();
+        let confirmed_by_payer = released_by == payment_session.payer;
+
+        // without the payer's confirmation the milestone unlocks after its timeout
+        if !confirmed_by_payer {
+            require!(now >= payment_session.next_milestone_due()?, PaymentError::MilestoneNotDue);
+        }
+
+        let amount = payment_session.next_milestone_amount()?;
+        let last = index + 1 == payment_session.milestone_count as usize;
+
+        // the tip goes out with the final milestone
+        let tip_amount = if last { payment_session.tip_amount } else { 0 };
+
+        let uuid = payment_session.uuid;
+        let settlement_bump = payment_session.settlement_bump;
+
+        let settlement_seeds: &[&[u8]] = &[
+            b"settlement_authority",
+            payment_key.as_ref(),
+            uuid.as_ref(),
+            &[settlement_bump]
+        ];
+
+        let signer_seeds = &[settlement_seeds];
+
+        self.transfer_from_escrow(&self.bitpay_ata, amount, signer_seeds)?;
+
+        let mut tip_destination = None;
+
+        if tip_amount > 0 {
+            let tip_ata = self.tip_ata.as_ref().ok_or(PaymentError::MissingTipDestination)?;
+
+            self.transfer_from_escrow(tip_ata, tip_amount, signer_seeds)?;
+            tip_destination = Some(tip_ata.key());
+        }
+
+        payment_session.released_amount = payment_session.released_amount
+            .checked_add(amount)
+            .ok_or(PaymentError::Overflow)?;
+        payment_session.milestones_released += 1;
+
+        emit!(MilestoneReleased {
+            payment_session: payment_key,
+            index: index as u8,
+            amount,
+            tip_amount,
+            released_by,
+            confirmed_by_payer,
+            remaining: payment_session.net_amount().saturating_sub(payment_session.released_amount),
+        });
+
+        // the last release completes the session like a single settlement
+        if last {
+            payment_session.set_status(PaymentSessionStatus::PendingFiat);
+
+            if let Some(proposal) = self.settlement_proposal.as_mut() {
+                proposal.executed = true;
+                proposal.executed_ts = Some(now);
+            }
+
+            emit!(PaymentSessionSettled {
+                payer: payment_session.payer,
+                merchant_id: payment_session.merchant_id(),
+                amount: payment_session.amount,
+                tip_amount,
+                discount_amount: payment_session.discount_amount,
+                token_mint: payment_session.token_mint,
+                escrow_ata: self.escrow_ata.key(),
+                status: PaymentSessionStatus::PendingFiat,
+                reference_id: payment_session.reference_id(),
+                expiry_ts: payment_session.expiry_ts,
+                settlement_authority: payment_session.settlement_authority,
+                tip_destination,
+            });
+        }
+
+        drop(payment_session);
+
+        // cashback is paid once, on the whole net amount, when the schedule completes
+        if last {
+            mint_cashback(
+                &self.payment_session,
+                &self.config,
+                &mut self.merchant_rewards,
+                self.loyalty_mint.as_ref(),
+                self.payer_loyalty_ata.as_ref(),
+                &self.token_program,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn transfer_from_escrow(
+        &self,
+        to: &Account<'info, TokenAccount>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+
+        let cpi_accounts = TransferChecked {
+            from: self.escrow_ata.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.settlement_authority.to_account_info(),
+            mint: self.token_mint.to_account_info()
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        transfer_checked(cpi_ctx, amount, self.token_mint.decimals)
+    }
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ProgramConfig, SettlementProposal, SettlementApprovalRevoked};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct RevokeSettlementApproval<'info> {
+
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"settlement_proposal", settlement_proposal.payment_session.as_ref()],
+        bump = settlement_proposal.bump,
+    )]
+    pub settlement_proposal: Account<'info, SettlementProposal>,
+}
+
+impl<'info> RevokeSettlementApproval<'info> {
+    pub fn revoke_settlement_approval(
+        &mut self,
+    ) -> Result<()> {
+
+        let operator = self.operator.key();
+
+        require!(self.config.is_operator(&operator), PaymentError::UnauthorizedOperator);
+        require!(!self.settlement_proposal.executed, PaymentError::ProposalAlreadyExecuted);
+
+        let position = self.settlement_proposal.approvals
+            .iter()
+            .position(|approval| *approval == operator)
+            .ok_or(PaymentError::ApprovalNotFound)?;
+
+        self.settlement_proposal.approvals.remove(position);
+
+        emit!(SettlementApprovalRevoked {
+            payment_session: self.settlement_proposal.payment_session,
+            settlement_proposal: self.settlement_proposal.key(),
+            operator,
+            approvals: self.settlement_proposal.approvals.len() as u8,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{MerchantRewards, CashbackRateUpdated, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct SetCashbackRate<'info> {
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ PaymentError::UnauthorizedMerchant,
+        seeds = [b"merchant_rewards", merchant_id_hash(&merchant_rewards.merchant_id).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // Holds the admin-controlled cashback cap
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+impl<'info> SetCashbackRate<'info> {
+    pub fn set_cashback_rate(
+        &mut self,
+        cashback_bps: u16,
+    ) -> Result<()> {
+
+        require!(cashback_bps <= self.config.max_cashback_bps, PaymentError::InvalidCashbackRate);
+
+        self.merchant_rewards.cashback_bps = cashback_bps;
+
+        emit!(CashbackRateUpdated {
+            merchant_id: self.merchant_rewards.merchant_id.clone(),
+            authority: self.authority.key(),
+            cashback_bps,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{MaxCashbackRateUpdated, ProgramConfig, MAX_CASHBACK_BPS};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct SetMaxCashbackRate<'info> {
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ PaymentError::UnauthorizedAdmin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+impl<'info> SetMaxCashbackRate<'info> {
+    pub fn set_max_cashback_rate(
+        &mut self,
+        max_cashback_bps: u16,
+    ) -> Result<()> {
+
+        require!(max_cashback_bps <= MAX_CASHBACK_BPS, PaymentError::InvalidCashbackRate);
+
+        self.config.max_cashback_bps = max_cashback_bps;
+
+        emit!(MaxCashbackRateUpdated {
+            admin: self.admin.key(),
+            max_cashback_bps,
+        });
+
+        Ok(())
+    }
+}
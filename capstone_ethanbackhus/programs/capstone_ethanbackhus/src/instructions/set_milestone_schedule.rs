@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+use crate::state::payment_session::{PaymentSession, PaymentSessionStatus};
+use crate::state::milestone::{validate_milestones, MilestoneInput, MilestoneScheduleSet};
+use crate::state::{MerchantRewards, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct SetMilestoneSchedule<'info> {
+
+    pub payer: Signer<'info>,
+
+    // the merchant or an operator agrees to the schedule with the payer
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // Merchant record of the session, only required when the merchant co-signs
+    #[account(
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Option<Account<'info, MerchantRewards>>,
+}
+
+impl<'info> SetMilestoneSchedule<'info> {
+    pub fn set_milestone_schedule(
+        &mut self,
+        milestones: Vec<MilestoneInput>,
+    ) -> Result<()> {
+
+        let authority = self.authority.key();
+        let is_merchant = self.merchant_rewards
+            .as_ref()
+            .is_some_and(|merchant_rewards| merchant_rewards.authority == authority);
+
+        require!(
+            is_merchant || self.config.is_operator(&authority),
+            PaymentError::UnauthorizedMerchant
+        );
+
+        let mut payment_session = self.payment_session.load_mut()?;
+
+        // the schedule is fixed before the payer funds the escrow
+        payment_session.require_status(PaymentSessionStatus::Initialized)?;
+        require!(!payment_session.is_native(), PaymentError::InvalidEscrowMode);
+
+        validate_milestones(&milestones)?;
+
+        for (slot, milestone) in payment_session.milestones.iter_mut().zip(&milestones) {
+            slot.amount_bps = milestone.amount_bps;
+            slot.release_after = milestone.release_after;
+        }
+        payment_session.milestone_count = milestones.len() as u8;
+
+        emit!(MilestoneScheduleSet {
+            payment_session: self.payment_session.key(),
+            milestones,
+        });
+
+        Ok(())
+    }
+}
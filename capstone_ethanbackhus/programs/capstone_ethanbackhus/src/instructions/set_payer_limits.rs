@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Mint,
+};
+
+use crate::state::{PayerProfile, PayerLimitsUpdated, ProgramConfig};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct SetPayerLimits<'info> {
+
+    #[account(mut)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    /// CHECK: only used to derive the payer profile PDA
+    pub payer: UncheckedAccount<'info>,
+
+    // Omitted for the payer's native SOL profile
+    pub token_mint: Option<Account<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = PayerProfile::DISCRIMINATOR.len() + PayerProfile::INIT_SPACE,
+        seeds = [b"payer_profile", payer.key().as_ref(), profile_mint(&token_mint).as_ref()],
+        bump
+    )]
+    pub payer_profile: Box<Account<'info, PayerProfile>>,
+
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> SetPayerLimits<'info> {
+    pub fn set_payer_limits(
+        &mut self,
+        daily_limit: u64,
+        monthly_limit: u64,
+        bumps: &SetPayerLimitsBumps,
+    ) -> Result<()> {
+
+        require!(
+            self.config.is_operator(&self.operator.key()),
+            PaymentError::UnauthorizedOperator
+        );
+        require!(daily_limit <= monthly_limit, PaymentError::InvalidLimits);
+
+        // overrides can only raise a payer above the defaults for the mode
+        let defaults = self.config.limits(self.token_mint.is_none());
+        require!(
+            daily_limit >= defaults.daily_limit && monthly_limit >= defaults.monthly_limit,
+            PaymentError::InvalidLimits
+        );
+
+        let token_mint = profile_mint(&self.token_mint);
+
+        let profile = &mut self.payer_profile;
+        profile.payer = self.payer.key();
+        profile.token_mint = token_mint;
+        profile.verified = true;
+        profile.daily_limit = Some(daily_limit);
+        profile.monthly_limit = Some(monthly_limit);
+        profile.bump = bumps.payer_profile;
+
+        emit!(PayerLimitsUpdated {
+            payer: self.payer.key(),
+            token_mint,
+            operator: self.operator.key(),
+            daily_limit,
+            monthly_limit,
+        });
+
+        Ok(())
+    }
+}
+
+// Native SOL sessions store the default pubkey as their mint, so their
+// payer profile is keyed by it as well.
+fn profile_mint(token_mint: &Option<Account<Mint>>) -> Pubkey {
+    token_mint.as_ref().map(|token_mint| token_mint.key()).unwrap_or_default()
+}
@@ -0,0 +1,199 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token::{spl_token::native_mint, Mint, Token, TokenAccount};
+
+use crate::instructions::mark_payment_settled::mint_cashback;
+use crate::state::payment_session::{PaymentSession, PaymentSessionSettled, PaymentSessionStatus};
+use crate::state::{MerchantRewards, ProgramConfig, merchant_id_hash};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct SettleSol<'info> {
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_session", payer.key().as_ref(), PaymentSession::load_current(&payment_session)?.uuid.as_ref()],
+        bump = payment_session.load()?.bump,
+    )]
+    pub payment_session: AccountLoader<'info, PaymentSession>,
+
+    // Operator config, large settlements must use a proposal
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    // Holds the escrowed lamports
+    #[account(
+        mut,
+        seeds = [b"settlement_authority", payment_session.key().as_ref(), payment_session.load()?.uuid.as_ref()],
+        bump = payment_session.load()?.settlement_bump,
+    )]
+    pub settlement_authority: SystemAccount<'info>,
+
+    // Bitpay deposit wallet, the merchant's registered payout wallet
+    #[account(
+        mut,
+        address = merchant_rewards.payout_wallet @ PaymentError::InvalidPayoutDestination,
+    )]
+    pub bitpay_wallet: SystemAccount<'info>,
+
+    // Merchant tip destination, only required when the payer added a tip
+    #[account(
+        mut,
+        constraint = tip_wallet.key() == merchant_rewards.tip_wallet @ PaymentError::InvalidTipDestination,
+    )]
+    pub tip_wallet: Option<SystemAccount<'info>>,
+
+    // Merchant record of the session, holds the cashback rate and the payout and tip wallets
+    #[account(
+        mut,
+        seeds = [b"merchant_rewards", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref()],
+        bump = merchant_rewards.bump,
+    )]
+    pub merchant_rewards: Account<'info, MerchantRewards>,
+
+    // The merchant's loyalty mint for wrapped SOL, only required when
+    // cashback is paid
+    #[account(
+        mut,
+        seeds = [b"loyalty_mint", merchant_id_hash(&payment_session.load()?.merchant_id()).as_ref(), native_mint::ID.as_ref()],
+        bump,
+    )]
+    pub loyalty_mint: Option<Account<'info, Mint>>,
+
+    // Payer's loyalty token account, receives the cashback
+    #[account(mut)]
+    pub payer_loyalty_ata: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SettleSol<'info> {
+    pub fn settle_sol(
+        &mut self,
+    ) -> Result<()> {
+
+        let payment_session = self.payment_session.load()?;
+        payment_session.require_status(PaymentSessionStatus::Funded)?;
+
+        let total = payment_session.escrow_total()?;
+        drop(payment_session);
+
+        require!(
+            !self.config.requires_approval(total, true),
+            PaymentError::SettlementRequiresApproval
+        );
+
+        settle_lamports(
+            &self.payment_session,
+            &self.settlement_authority,
+            &self.bitpay_wallet,
+            self.tip_wallet.as_ref(),
+            &self.payer.to_account_info(),
+            &self.system_program,
+        )?;
+
+        mint_cashback(
+            &self.payment_session,
+            &self.config,
+            &mut self.merchant_rewards,
+            self.loyalty_mint.as_ref(),
+            self.payer_loyalty_ata.as_ref(),
+            &self.token_program,
+        )
+    }
+}
+
+// Pays the escrowed lamports (and tip) out of the settlement authority and
+// hands its rent reserve back to the payer. Shared by settle_sol and
+// execute_settlement_sol. Cashback for native sessions is minted on the
+// merchant's loyalty mint for wrapped SOL.
+pub fn settle_lamports<'info>(
+    payment_session: &AccountLoader<'info, PaymentSession>,
+    settlement_authority: &SystemAccount<'info>,
+    bitpay_wallet: &SystemAccount<'info>,
+    tip_wallet: Option<&SystemAccount<'info>>,
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+
+    let payment_key = payment_session.key();
+    let mut session = payment_session.load_mut()?;
+
+    require!(session.is_native(), PaymentError::InvalidEscrowMode);
+    require!(!session.has_milestones(), PaymentError::MilestoneScheduleActive);
+
+    let uuid = session.uuid;
+    let settlement_bump = session.settlement_bump;
+
+    let seeds: &[&[u8]] = &[
+        b"settlement_authority",
+        payment_key.as_ref(),
+        uuid.as_ref(),
+        &[settlement_bump]
+    ];
+
+    let signer_seeds = &[seeds];
+
+    pay_out(settlement_authority, &bitpay_wallet.to_account_info(), session.net_amount(), system_program, signer_seeds)?;
+
+    let tip_amount = session.tip_amount;
+    let mut tip_destination = None;
+
+    if tip_amount > 0 {
+        let tip_wallet = tip_wallet.ok_or(PaymentError::MissingTipDestination)?;
+
+        pay_out(settlement_authority, &tip_wallet.to_account_info(), tip_amount, system_program, signer_seeds)?;
+        tip_destination = Some(tip_wallet.key());
+    }
+
+    // hand the rent reserve back to the payer, emptying the escrow
+    pay_out(settlement_authority, payer, settlement_authority.lamports(), system_program, signer_seeds)?;
+
+    session.set_status(PaymentSessionStatus::PendingFiat);
+
+    emit!(PaymentSessionSettled {
+        payer: session.payer,
+        merchant_id: session.merchant_id(),
+        amount: session.amount,
+        tip_amount,
+        discount_amount: 0,
+        token_mint: Pubkey::default(),
+        escrow_ata: settlement_authority.key(),
+        status: PaymentSessionStatus::PendingFiat,
+        reference_id: session.reference_id(),
+        expiry_ts: session.expiry_ts,
+        settlement_authority: session.settlement_authority,
+        tip_destination,
+    });
+
+    Ok(())
+}
+
+fn pay_out<'info>(
+    settlement_authority: &SystemAccount<'info>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+    system_program: &Program<'info, System>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+
+    let cpi_accounts = Transfer {
+        from: settlement_authority.to_account_info(),
+        to: to.clone(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds
+    );
+
+    transfer(cpi_ctx, amount)
+}
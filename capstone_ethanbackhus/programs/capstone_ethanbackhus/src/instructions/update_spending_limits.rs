@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ProgramConfig, SpendingLimitsUpdated};
+use crate::errors::PaymentError;
+
+#[derive(Accounts)]
+pub struct UpdateSpendingLimits<'info> {
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = admin @ PaymentError::UnauthorizedAdmin,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+impl<'info> UpdateSpendingLimits<'info> {
+    pub fn update_spending_limits(
+        &mut self,
+        native: bool,
+        daily_limit: u64,
+        monthly_limit: u64,
+    ) -> Result<()> {
+
+        require!(daily_limit <= monthly_limit, PaymentError::InvalidLimits);
+
+        let limits = if native { &mut self.config.native_limits } else { &mut self.config.token_limits };
+        limits.daily_limit = daily_limit;
+        limits.monthly_limit = monthly_limit;
+
+        emit!(SpendingLimitsUpdated {
+            admin: self.admin.key(),
+            native,
+            daily_limit,
+            monthly_limit,
+        });
+
+        Ok(())
+    }
+}
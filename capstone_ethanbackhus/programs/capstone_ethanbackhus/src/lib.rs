@@ -1,10 +1,13 @@
 use anchor_lang::prelude::*;
 
 mod instructions;
-mod state;
-mod errors;
+pub mod state;
+pub mod errors;
+#[cfg(not(target_os = "solana"))]
+pub mod client;
 
 use instructions::*;
+use state::{EscrowLimits, MilestoneInput};
 
 declare_id!("DDR17KNMbiT9pFnncgeyLeLz6UXSnbBrwvwxzUDwLrV6");
 
@@ -13,6 +16,7 @@ pub mod capstone_ethanbackhus {
 
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn init_payment_session(
         ctx: Context<InitPaymentSession>,
         uuid: [u8; 16],
@@ -21,15 +25,19 @@ pub mod capstone_ethanbackhus {
         reference_id: String,
         fiat_currencty: String,
         merchant_bank: String,
+        merchant_index_page: u32,
+        payer_index_page: u32,
     ) -> Result<()> {
-        ctx.accounts.initialize(uuid, merchant_id, amount, reference_id, fiat_currencty, merchant_bank, &ctx.bumps)?;
+        ctx.accounts.initialize(uuid, merchant_id, amount, reference_id, fiat_currencty, merchant_bank, merchant_index_page, payer_index_page, &ctx.bumps)?;
         Ok(())
     }
 
     pub fn deposit_stablecoin(
-        ctx: Context<DepositStablecoin>
+        ctx: Context<DepositStablecoin>,
+        tip_amount: u64,
+        redeem_amount: u64,
     ) -> Result<()> {
-        ctx.accounts.deposit_stablecoin()?;
+        ctx.accounts.deposit_stablecoin(tip_amount, redeem_amount, &ctx.bumps)?;
         Ok(())
     }
 
@@ -46,4 +54,162 @@ pub mod capstone_ethanbackhus {
         ctx.accounts.mark_payment_settled()?;
         Ok(())
     }
-}
+
+    pub fn init_config(
+        ctx: Context<InitConfig>,
+        operators: Vec<Pubkey>,
+        threshold: u8,
+        token_limits: EscrowLimits,
+        native_limits: EscrowLimits,
+    ) -> Result<()> {
+        ctx.accounts.init_config(operators, threshold, token_limits, native_limits, &ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn propose_settlement(
+        ctx: Context<ProposeSettlement>,
+    ) -> Result<()> {
+        ctx.accounts.propose_settlement(&ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn approve_settlement(
+        ctx: Context<ApproveSettlement>,
+    ) -> Result<()> {
+        ctx.accounts.approve_settlement()?;
+        Ok(())
+    }
+
+    pub fn revoke_settlement_approval(
+        ctx: Context<RevokeSettlementApproval>,
+    ) -> Result<()> {
+        ctx.accounts.revoke_settlement_approval()?;
+        Ok(())
+    }
+
+    pub fn cancel_settlement_proposal<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelSettlementProposal<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.cancel_settlement_proposal(ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    pub fn execute_settlement(
+        ctx: Context<ExecuteSettlement>,
+    ) -> Result<()> {
+        ctx.accounts.execute_settlement()?;
+        Ok(())
+    }
+
+    pub fn execute_settlement_sol(
+        ctx: Context<ExecuteSettlementSol>,
+    ) -> Result<()> {
+        ctx.accounts.execute_settlement_sol()?;
+        Ok(())
+    }
+
+    pub fn update_spending_limits(
+        ctx: Context<UpdateSpendingLimits>,
+        native: bool,
+        daily_limit: u64,
+        monthly_limit: u64,
+    ) -> Result<()> {
+        ctx.accounts.update_spending_limits(native, daily_limit, monthly_limit)?;
+        Ok(())
+    }
+
+    pub fn set_payer_limits(
+        ctx: Context<SetPayerLimits>,
+        daily_limit: u64,
+        monthly_limit: u64,
+    ) -> Result<()> {
+        ctx.accounts.set_payer_limits(daily_limit, monthly_limit, &ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn init_loyalty_mint(
+        ctx: Context<InitLoyaltyMint>,
+    ) -> Result<()> {
+        ctx.accounts.init_loyalty_mint()?;
+        Ok(())
+    }
+
+    pub fn init_merchant_rewards(
+        ctx: Context<InitMerchantRewards>,
+        merchant_id: String,
+        authority: Pubkey,
+        tip_wallet: Pubkey,
+        payout_wallet: Pubkey,
+        cashback_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.init_merchant_rewards(merchant_id, authority, tip_wallet, payout_wallet, cashback_bps, &ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn set_cashback_rate(
+        ctx: Context<SetCashbackRate>,
+        cashback_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.set_cashback_rate(cashback_bps)?;
+        Ok(())
+    }
+
+    pub fn set_max_cashback_rate(
+        ctx: Context<SetMaxCashbackRate>,
+        max_cashback_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.set_max_cashback_rate(max_cashback_bps)?;
+        Ok(())
+    }
+
+    pub fn migrate_session(
+        ctx: Context<MigrateSession>,
+    ) -> Result<()> {
+        ctx.accounts.migrate_session()?;
+        Ok(())
+    }
+
+    pub fn cancel_session(
+        ctx: Context<CancelSession>,
+    ) -> Result<()> {
+        ctx.accounts.cancel_session()?;
+        Ok(())
+    }
+
+    pub fn deposit_sol(
+        ctx: Context<DepositSol>,
+        tip_amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.deposit_sol(tip_amount, &ctx.bumps)?;
+        Ok(())
+    }
+
+    pub fn refund_sol(
+        ctx: Context<RefundSol>,
+    ) -> Result<()> {
+        ctx.accounts.refund_sol()?;
+        Ok(())
+    }
+
+    pub fn settle_sol(
+        ctx: Context<SettleSol>,
+    ) -> Result<()> {
+        ctx.accounts.settle_sol()?;
+        Ok(())
+    }
+
+    pub fn set_milestone_schedule(
+        ctx: Context<SetMilestoneSchedule>,
+        milestones: Vec<MilestoneInput>,
+    ) -> Result<()> {
+        ctx.accounts.set_milestone_schedule(milestones)?;
+        Ok(())
+    }
+
+    pub fn release_milestone(
+        ctx: Context<ReleaseMilestone>,
+    ) -> Result<()> {
+        ctx.accounts.release_milestone()?;
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+
+use crate::state::payment_session::{PaymentSession, PaymentSessionStatus, PAYMENT_SESSION_VERSION};
+
+// Borsh layout PaymentSession was deployed with, before the version byte and
+// the zero-copy layout. Kept so that migrate_session can read live escrows
+// written by it.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct LegacyPaymentSession {
+    pub payer: Pubkey,
+    #[max_len(50)]
+    pub merchant_id: String,
+    pub amount: u64,
+    pub token_mint: Pubkey,
+    pub escrow_ata: Pubkey,
+    pub payer_ata: Pubkey,
+    pub status: PaymentSessionStatus,
+    pub expiry_ts: i64,
+    pub created_ts: i64,
+    pub funded_ts: Option<i64>,
+    pub settled_ts: Option<i64>,
+    pub bump: u8,
+    #[max_len(50)]
+    pub reference_id: String,
+    pub settlement_authority: Pubkey,
+    pub settlement_bump: u8,
+    pub uuid: [u8; 16],
+    #[max_len(200)]
+    pub bitpay_payout_id: Option<String>,
+    #[max_len(10)]
+    pub fiat_currency: String,
+    #[max_len(100)]
+    pub merchant_bank: String,
+}
+
+impl LegacyPaymentSession {
+    // Reads a session on the deployed layout, which has a size of its own.
+    // Expects the data past the discriminator.
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() == LegacyPaymentSession::INIT_SPACE,
+            ErrorCode::AccountDidNotDeserialize
+        );
+
+        let mut data = data;
+        Ok(LegacyPaymentSession::deserialize(&mut data)?)
+    }
+
+    // Tips, loyalty discounts, native escrows and milestones did not exist
+    // yet, so their fields start zeroed.
+    pub fn into_current(self) -> PaymentSession {
+        let mut session = PaymentSession {
+            amount: self.amount,
+            tip_amount: 0,
+            discount_amount: 0,
+            expiry_ts: self.expiry_ts,
+            created_ts: self.created_ts,
+            funded_ts: self.funded_ts.unwrap_or_default(),
+            settled_ts: self.settled_ts.unwrap_or_default(),
+            payer: self.payer,
+            token_mint: self.token_mint,
+            settlement_authority: self.settlement_authority,
+            uuid: self.uuid,
+            merchant_id: truncate_to_fixed(&self.merchant_id),
+            reference_id: truncate_to_fixed(&self.reference_id),
+            merchant_bank: truncate_to_fixed(&self.merchant_bank),
+            bitpay_payout_id: truncate_to_fixed(self.bitpay_payout_id.as_deref().unwrap_or_default()),
+            fiat_currency: truncate_to_fixed(&self.fiat_currency),
+            status: 0,
+            bump: self.bump,
+            settlement_bump: self.settlement_bump,
+            version: PAYMENT_SESSION_VERSION,
+            native: 0,
+            reserved: [0; 3],
+            released_amount: 0,
+            milestone_count: 0,
+            milestones_released: 0,
+            milestone_reserved: [0; 6],
+            milestones: bytemuck::Zeroable::zeroed(),
+        };
+        session.set_status(self.status);
+
+        session
+    }
+}
+
+// Legacy strings could be longer than the new fixed-size slots. Migration
+// keeps the leading bytes rather than leaving the escrow stuck.
+fn truncate_to_fixed<const N: usize>(value: &str) -> [u8; N] {
+    let len = value.len().min(N);
+    let mut field = [0u8; N];
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+    field
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::PaymentError;
+
+pub const MAX_CASHBACK_BPS: u16 = 10_000;
+
+// Merchant ids are variable length strings that can exceed the 32 byte seed
+// limit, so merchant PDAs are keyed by their sha256 hash.
+pub fn merchant_id_hash(merchant_id: &str) -> [u8; 32] {
+    solana_sha256_hasher::hash(merchant_id.as_bytes()).to_bytes()
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantRewards {
+    #[max_len(50)]
+    pub merchant_id: String,                // merchant identifier, the PDA is keyed by its hash
+    pub authority: Pubkey,                  // merchant wallet allowed to change the cashback rate
+    pub tip_wallet: Pubkey,                 // wallet that owns the merchant's tip destination
+    pub payout_wallet: Pubkey,              // wallet that owns the merchant's BitPay deposit destination
+    pub cashback_bps: u16,                  // cashback paid to payers in basis points of the settled amount
+    pub total_cashback_minted: u64,         // loyalty tokens minted for this merchant's sessions
+    pub bump: u8,                           // bump for PDA
+}
+
+impl MerchantRewards {
+    // The rate is capped by the config at payout time as well, so lowering the
+    // cap applies to merchants that were registered under a higher one.
+    pub fn cashback_for(&self, settled_amount: u64, max_cashback_bps: u16) -> Result<u64> {
+        let cashback_bps = self.cashback_bps.min(max_cashback_bps);
+
+        let cashback = (settled_amount as u128)
+            .checked_mul(cashback_bps as u128)
+            .ok_or(PaymentError::Overflow)?
+            .checked_div(MAX_CASHBACK_BPS as u128)
+            .ok_or(PaymentError::Overflow)?;
+
+        Ok(cashback as u64)
+    }
+}
+
+#[event]
+pub struct CashbackRateUpdated {
+    pub merchant_id: String,
+    pub authority: Pubkey,
+    pub cashback_bps: u16,
+}
+
+#[event]
+pub struct LoyaltyMintInitialized {
+    pub merchant_id: String,
+    pub token_mint: Pubkey,
+    pub loyalty_mint: Pubkey,
+}
+
+#[event]
+pub struct CashbackMinted {
+    pub payer: Pubkey,
+    pub merchant_id: String,
+    pub payment_session: Pubkey,
+    pub settled_amount: u64,
+    pub cashback_amount: u64,
+}
+
+#[event]
+pub struct LoyaltyRedeemed {
+    pub payer: Pubkey,
+    pub merchant_id: String,
+    pub payment_session: Pubkey,
+    pub discount_amount: u64,
+}
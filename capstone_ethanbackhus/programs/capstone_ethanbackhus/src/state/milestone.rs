@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::PaymentError;
+
+pub const MAX_MILESTONES: usize = 4;
+pub const MILESTONE_BPS_TOTAL: u16 = 10_000;
+
+// Longest a milestone can wait after funding, one year
+pub const MAX_MILESTONE_RELEASE_AFTER: i64 = 365 * 24 * 60 * 60;
+
+// One step of a session's release schedule, stored inline in PaymentSession
+#[zero_copy]
+#[derive(InitSpace)]
+pub struct Milestone {
+    pub release_after: i64,             // seconds after funding when anyone can release it
+    pub amount_bps: u16,                // share of the net amount, in basis points
+    pub reserved: [u8; 6],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct MilestoneInput {
+    pub amount_bps: u16,
+    pub release_after: i64,
+}
+
+// Checks a requested schedule: 1 to MAX_MILESTONES steps whose shares add up
+// to the whole amount and whose timeouts never go backwards or past
+// MAX_MILESTONE_RELEASE_AFTER.
+pub fn validate_milestones(milestones: &[MilestoneInput]) -> Result<()> {
+    require!(
+        !milestones.is_empty() && milestones.len() <= MAX_MILESTONES,
+        PaymentError::InvalidMilestoneSchedule
+    );
+
+    let mut total_bps: u16 = 0;
+    let mut previous_release_after = 0;
+
+    for milestone in milestones {
+        require!(
+            milestone.amount_bps > 0
+                && milestone.release_after >= previous_release_after
+                && milestone.release_after <= MAX_MILESTONE_RELEASE_AFTER,
+            PaymentError::InvalidMilestoneSchedule
+        );
+
+        total_bps = total_bps
+            .checked_add(milestone.amount_bps)
+            .ok_or(PaymentError::InvalidMilestoneSchedule)?;
+        previous_release_after = milestone.release_after;
+    }
+
+    require!(total_bps == MILESTONE_BPS_TOTAL, PaymentError::InvalidMilestoneSchedule);
+
+    Ok(())
+}
+
+#[event]
+pub struct MilestoneScheduleSet {
+    pub payment_session: Pubkey,
+    pub milestones: Vec<MilestoneInput>,
+}
+
+#[event]
+pub struct MilestoneReleased {
+    pub payment_session: Pubkey,
+    pub index: u8,
+    pub amount: u64,
+    pub tip_amount: u64,
+    pub released_by: Pubkey,
+    pub confirmed_by_payer: bool,       // false when released after the timeout
+    pub remaining: u64,
+}
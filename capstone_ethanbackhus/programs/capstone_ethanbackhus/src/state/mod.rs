@@ -1,3 +1,17 @@
 pub mod payment_session;
+pub mod program_config;
+pub mod settlement_proposal;
+pub mod payer_profile;
+pub mod merchant_rewards;
+pub mod session_index;
+pub mod legacy_payment_session;
+pub mod milestone;
 
-pub use payment_session::*;
\ No newline at end of file
+pub use payment_session::*;
+pub use program_config::*;
+pub use settlement_proposal::*;
+pub use payer_profile::*;
+pub use merchant_rewards::*;
+pub use session_index::*;
+pub use legacy_payment_session::*;
+pub use milestone::*;
\ No newline at end of file
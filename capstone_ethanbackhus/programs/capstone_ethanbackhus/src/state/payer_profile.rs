@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::PaymentError;
+
+// Limits are enforced over sliding windows made of fixed-size buckets. A
+// deposit stays counted while its bucket is one of the last `*_BUCKETS`, so
+// the window always covers at least 24h / 30d and a fresh window can never be
+// stacked on a full one across a boundary.
+pub const DAILY_BUCKET_SECONDS: i64 = 60 * 60;
+pub const DAILY_BUCKETS: usize = 25;
+pub const MONTHLY_BUCKET_SECONDS: i64 = 24 * DAILY_BUCKET_SECONDS;
+pub const MONTHLY_BUCKETS: usize = 31;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct VolumeBucket {
+    pub epoch: i64,                         // now / bucket length when the bucket was last written
+    pub volume: u64,                        // volume deposited during that epoch
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PayerProfile {
+    pub payer: Pubkey,                      // payer this profile tracks
+    pub token_mint: Pubkey,                 // volume is tracked per payer and mint
+    pub daily_buckets: [VolumeBucket; DAILY_BUCKETS],       // hourly volume for the 24h window
+    pub monthly_buckets: [VolumeBucket; MONTHLY_BUCKETS],   // daily volume for the 30d window
+    pub verified: bool,                     // set once an operator has reviewed the payer
+    pub daily_limit: Option<u64>,           // operator override of the config daily limit
+    pub monthly_limit: Option<u64>,         // operator override of the config monthly limit
+    pub bump: u8,                           // bump for PDA
+}
+
+impl PayerProfile {
+    // Adds a deposit to the 24h and 30d sliding windows and rejects it if
+    // either limit would be exceeded.
+    pub fn record_deposit(
+        &mut self,
+        amount: u64,
+        now: i64,
+        daily_limit: u64,
+        monthly_limit: u64,
+    ) -> Result<()> {
+        let daily_epoch = now.div_euclid(DAILY_BUCKET_SECONDS);
+        let monthly_epoch = now.div_euclid(MONTHLY_BUCKET_SECONDS);
+
+        let daily_volume = window_volume(&self.daily_buckets, daily_epoch)?
+            .checked_add(amount)
+            .ok_or(PaymentError::Overflow)?;
+        let monthly_volume = window_volume(&self.monthly_buckets, monthly_epoch)?
+            .checked_add(amount)
+            .ok_or(PaymentError::Overflow)?;
+
+        require!(
+            daily_volume <= effective_limit(self.daily_limit, daily_limit),
+            PaymentError::DailyLimitExceeded
+        );
+        require!(
+            monthly_volume <= effective_limit(self.monthly_limit, monthly_limit),
+            PaymentError::MonthlyLimitExceeded
+        );
+
+        add_to_bucket(&mut self.daily_buckets, daily_epoch, amount)?;
+        add_to_bucket(&mut self.monthly_buckets, monthly_epoch, amount)
+    }
+}
+
+// Overrides only ever raise a payer's limit. The default can be raised past
+// an override later, and a verified payer then gets the new default.
+fn effective_limit(limit_override: Option<u64>, default: u64) -> u64 {
+    limit_override.map_or(default, |limit| limit.max(default))
+}
+
+// Sum of the buckets still inside the window ending at `epoch`.
+fn window_volume(buckets: &[VolumeBucket], epoch: i64) -> Result<u64> {
+    buckets
+        .iter()
+        .filter(|bucket| bucket.volume > 0 && epoch - bucket.epoch < buckets.len() as i64)
+        .try_fold(0u64, |total, bucket| total.checked_add(bucket.volume))
+        .ok_or(PaymentError::Overflow.into())
+}
+
+// Each epoch maps to a fixed slot, a slot still holding an older epoch has
+// left the window and is reset first.
+fn add_to_bucket(buckets: &mut [VolumeBucket], epoch: i64, amount: u64) -> Result<()> {
+    let slot = epoch.rem_euclid(buckets.len() as i64) as usize;
+    let bucket = &mut buckets[slot];
+
+    if bucket.epoch != epoch {
+        *bucket = VolumeBucket { epoch, volume: 0 };
+    }
+
+    bucket.volume = bucket.volume.checked_add(amount).ok_or(PaymentError::Overflow)?;
+
+    Ok(())
+}
+
+#[event]
+pub struct PayerLimitsUpdated {
+    pub payer: Pubkey,
+    pub token_mint: Pubkey,
+    pub operator: Pubkey,
+    pub daily_limit: u64,
+    pub monthly_limit: u64,
+}
@@ -1,32 +1,205 @@
 use anchor_lang::prelude::*;
 
-#[account]
+use crate::errors::PaymentError;
+use crate::state::milestone::{Milestone, MAX_MILESTONES, MILESTONE_BPS_TOTAL};
+
+// Bumped whenever the PaymentSession layout changes. Accounts written by an
+// older layout must go through migrate_session before they can be used.
+//   0:    deployed Borsh layout with String fields and stored ATAs, which
+//         has no version byte (see legacy_payment_session)
+//   1:    fixed-size zero-copy layout. Replaces the Borsh layout with a
+//         trailing version byte, which was never deployed
+pub const PAYMENT_SESSION_VERSION: u8 = 1;
+
+pub const MERCHANT_ID_LEN: usize = 64;
+pub const REFERENCE_ID_LEN: usize = 64;
+pub const MERCHANT_BANK_LEN: usize = 64;
+pub const BITPAY_PAYOUT_ID_LEN: usize = 64;
+pub const FIAT_CURRENCY_LEN: usize = 8;
+
+// Fixed-size layout read in place through AccountLoader. Text fields are zero
+// padded byte arrays, and the payer and escrow ATAs are derived rather than
+// stored. Fields are ordered by alignment so the struct has no implicit padding.
+#[account(zero_copy)]
 #[derive(InitSpace)]
 pub struct PaymentSession {
-    pub payer: Pubkey,                      // person initializing the payment session
-    #[max_len(50)]
-    pub merchant_id: String,                // merchant identifier
-    pub amount: u64,                        // amount to be paid in smallest unit of the token
-    pub token_mint: Pubkey,                 // mint of the token being used for payment
-    pub escrow_ata: Pubkey,                 // associated token account holding the funds
-    pub payer_ata: Pubkey,                  // associated token account of the payer's funds
-    pub status: PaymentSessionStatus,       // current status of the payment session
-    pub expiry_ts: i64,                     // expiration timestamp
-    pub created_ts: i64,                    // created timestamp (to make monitoring and debugging much easier)
-    pub funded_ts: Option<i64>,             // funded timestamp
-    pub settled_ts: Option<i64>,            // settled timestamp
-    pub bump: u8,                           // bump for PDA
-    #[max_len(50)]
-    pub reference_id: String,               // reference identifier for tracking
-    pub settlement_authority: Pubkey,       // pubkey to where we are sending the funds? do we know yet or will the backend api tell us?
-    pub settlement_bump: u8,                // bump for settlement authority PDA
-    pub uuid: [u8; 16],                     // unique identifier for the payment session
-    #[max_len(200)]
-    pub bitpay_payout_id: Option<String>,    // Only created once payout is requested
-    #[max_len(10)]
-    pub fiat_currency: String,
-    #[max_len(100)]
-    pub merchant_bank: String,
+    pub amount: u64,                                    // amount to be paid in smallest unit of the token
+    pub tip_amount: u64,                                // optional gratuity chosen by the payer at deposit time
+    pub discount_amount: u64,                           // loyalty tokens redeemed against the amount at deposit time
+    pub expiry_ts: i64,                                 // expiration timestamp
+    pub created_ts: i64,                                // created timestamp (to make monitoring and debugging much easier)
+    pub funded_ts: i64,                                 // funded timestamp, 0 until funded
+    pub settled_ts: i64,                                // settled timestamp, 0 until settled
+    pub payer: Pubkey,                                  // person initializing the payment session
+    pub token_mint: Pubkey,                             // mint of the token being used for payment
+    pub settlement_authority: Pubkey,                   // PDA that owns the escrow ATA
+    pub uuid: [u8; 16],                                 // unique identifier for the payment session
+    pub merchant_id: [u8; MERCHANT_ID_LEN],             // merchant identifier
+    pub reference_id: [u8; REFERENCE_ID_LEN],           // reference identifier for tracking
+    pub merchant_bank: [u8; MERCHANT_BANK_LEN],
+    pub bitpay_payout_id: [u8; BITPAY_PAYOUT_ID_LEN],   // only set once payout is requested
+    pub fiat_currency: [u8; FIAT_CURRENCY_LEN],
+    pub status: u8,                                     // PaymentSessionStatus, read through status()
+    pub bump: u8,                                       // bump for PDA
+    pub settlement_bump: u8,                            // bump for settlement authority PDA
+    pub version: u8,                                    // layout version
+    pub native: u8,                                     // 1 when the settlement authority escrows lamports instead of an ATA
+    pub reserved: [u8; 3],                              // keeps the size a multiple of 8
+    pub released_amount: u64,                           // net amount already paid out through milestones
+    pub milestone_count: u8,                            // 0 settles in one step
+    pub milestones_released: u8,                        // milestones release in order, this is the next index
+    pub milestone_reserved: [u8; 6],
+    pub milestones: [Milestone; MAX_MILESTONES],
+}
+
+impl PaymentSession {
+    // Loads the session, refusing accounts still on a legacy Borsh layout.
+    // Those accounts have a different size, so the length alone tells them apart.
+    pub fn load_current<'a>(
+        loader: &'a AccountLoader<'_, PaymentSession>,
+    ) -> Result<std::cell::Ref<'a, PaymentSession>> {
+        require!(
+            loader.as_ref().data_len() == PaymentSession::DISCRIMINATOR.len() + PaymentSession::INIT_SPACE,
+            PaymentError::SessionNotMigrated
+        );
+
+        let session = loader.load()?;
+        require!(session.version == PAYMENT_SESSION_VERSION, PaymentError::SessionNotMigrated);
+
+        Ok(session)
+    }
+
+    // amount owed to the merchant once the loyalty discount is applied
+    pub fn net_amount(&self) -> u64 {
+        self.amount.saturating_sub(self.discount_amount)
+    }
+
+    // total held in escrow once the session is funded
+    pub fn escrow_total(&self) -> Result<u64> {
+        Ok(self.net_amount()
+            .checked_add(self.tip_amount)
+            .ok_or(PaymentError::Overflow)?)
+    }
+
+    pub fn has_milestones(&self) -> bool {
+        self.milestone_count > 0
+    }
+
+    // Net amount paid out by the next milestone. The last milestone takes
+    // whatever is left so rounding never strands funds in escrow.
+    pub fn next_milestone_amount(&self) -> Result<u64> {
+        let index = self.milestones_released as usize;
+        require!(index < self.milestone_count as usize, PaymentError::InvalidMilestoneSchedule);
+
+        let remaining = self.net_amount().saturating_sub(self.released_amount);
+        if index + 1 == self.milestone_count as usize {
+            return Ok(remaining);
+        }
+
+        let share = (self.net_amount() as u128)
+            .checked_mul(self.milestones[index].amount_bps as u128)
+            .ok_or(PaymentError::Overflow)?
+            / MILESTONE_BPS_TOTAL as u128;
+
+        Ok((share as u64).min(remaining))
+    }
+
+    // when the next milestone unlocks without the payer's confirmation
+    pub fn next_milestone_due(&self) -> Result<i64> {
+        let index = self.milestones_released as usize;
+        require!(index < self.milestone_count as usize, PaymentError::InvalidMilestoneSchedule);
+
+        Ok(self.funded_ts
+            .checked_add(self.milestones[index].release_after)
+            .ok_or(PaymentError::Overflow)?)
+    }
+
+    // Loyalty discount restored on refund, in proportion to the share of the
+    // net amount that goes back to the payer rather than to the merchant.
+    pub fn refundable_discount(&self) -> Result<u64> {
+        let net_amount = self.net_amount();
+        if net_amount == 0 {
+            return Ok(self.discount_amount);
+        }
+
+        let refunded = net_amount.saturating_sub(self.released_amount);
+        let discount = (self.discount_amount as u128)
+            .checked_mul(refunded as u128)
+            .ok_or(PaymentError::Overflow)?
+            / net_amount as u128;
+
+        Ok(discount as u64)
+    }
+
+    // what is still held in escrow, less any milestones already released
+    pub fn remaining_escrow(&self) -> Result<u64> {
+        Ok(self.escrow_total()?.saturating_sub(self.released_amount))
+    }
+
+    pub fn is_native(&self) -> bool {
+        self.native != 0
+    }
+
+    pub fn require_status(&self, status: PaymentSessionStatus) -> Result<()> {
+        require!(self.status()? == status, PaymentError::InvalidPaymentSessionState);
+        Ok(())
+    }
+
+    // Checks shared by the token and native deposit paths
+    pub fn require_depositable(&self, now: i64) -> Result<()> {
+        self.require_status(PaymentSessionStatus::Initialized)?;
+        require!(now <= self.expiry_ts, PaymentError::SessionExpired);
+        Ok(())
+    }
+
+    // Checks shared by the token and native refund paths. Once a schedule is
+    // running the payer can only back out before the next milestone unlocks
+    // on its own.
+    pub fn require_refundable(&self, now: i64) -> Result<()> {
+        self.require_status(PaymentSessionStatus::Funded)?;
+        if self.has_milestones() {
+            require!(now < self.next_milestone_due()?, PaymentError::RefundWindowClosed);
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> Result<PaymentSessionStatus> {
+        PaymentSessionStatus::try_from(self.status)
+    }
+
+    pub fn set_status(&mut self, status: PaymentSessionStatus) {
+        self.status = status as u8;
+    }
+
+    pub fn funded_ts(&self) -> Option<i64> {
+        (self.funded_ts != 0).then_some(self.funded_ts)
+    }
+
+    pub fn settled_ts(&self) -> Option<i64> {
+        (self.settled_ts != 0).then_some(self.settled_ts)
+    }
+
+    pub fn merchant_id(&self) -> String {
+        from_fixed(&self.merchant_id)
+    }
+
+    pub fn reference_id(&self) -> String {
+        from_fixed(&self.reference_id)
+    }
+}
+
+// Copies `value` into a zero padded fixed-size field
+pub fn to_fixed<const N: usize>(value: &str) -> Result<[u8; N]> {
+    require!(value.len() <= N, PaymentError::FieldTooLong);
+
+    let mut field = [0u8; N];
+    field[..value.len()].copy_from_slice(value.as_bytes());
+    Ok(field)
+}
+
+pub fn from_fixed(field: &[u8]) -> String {
+    let len = field.iter().position(|byte| *byte == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).into_owned()
 }
 
 #[event]
@@ -34,6 +207,8 @@ pub struct PaymentSessionCreated {
     pub payer: Pubkey,
     pub merchant_id: String,
     pub amount: u64,
+    pub tip_amount: u64,
+    pub discount_amount: u64,
     pub token_mint: Pubkey,
     pub escrow_ata: Pubkey,
     pub payer_ata: Pubkey,
@@ -51,6 +226,8 @@ pub struct PaymentSessionRefunded {
     pub payer: Pubkey,
     pub merchant_id: String,
     pub amount: u64,
+    pub tip_amount: u64,
+    pub discount_amount: u64,
     pub token_mint: Pubkey,
     pub escrow_ata: Pubkey,
     pub payer_ata: Pubkey,
@@ -65,13 +242,32 @@ pub struct PaymentSessionSettled {
     pub payer: Pubkey,
     pub merchant_id: String,
     pub amount: u64,
+    pub tip_amount: u64,
+    pub discount_amount: u64,
     pub token_mint: Pubkey,
     pub escrow_ata: Pubkey,
-    pub payer_ata: Pubkey,
     pub status: PaymentSessionStatus,
     pub expiry_ts: i64,
     pub reference_id: String,
     pub settlement_authority: Pubkey,
+    pub tip_destination: Option<Pubkey>,
+}
+
+#[event]
+pub struct PaymentSessionCancelled {
+    pub payment_session: Pubkey,
+    pub payer: Pubkey,
+    pub merchant_id: String,
+    pub reference_id: String,
+    pub cancelled_by: Pubkey,
+    pub status: PaymentSessionStatus,
+}
+
+#[event]
+pub struct PaymentSessionMigrated {
+    pub payment_session: Pubkey,
+    pub from_version: u8,
+    pub to_version: u8,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
@@ -81,7 +277,8 @@ pub enum PaymentSessionStatus {
     Funded,
     Refunded,
     PendingFiat,
-    Settled
+    Settled,
+    Cancelled
 }
 
 impl Default for PaymentSessionStatus {
@@ -90,6 +287,22 @@ impl Default for PaymentSessionStatus {
     }
 }
 
+impl TryFrom<u8> for PaymentSessionStatus {
+    type Error = Error;
+
+    fn try_from(status: u8) -> Result<Self> {
+        Ok(match status {
+            0 => Self::Initialized,
+            1 => Self::Funded,
+            2 => Self::Refunded,
+            3 => Self::PendingFiat,
+            4 => Self::Settled,
+            5 => Self::Cancelled,
+            _ => return err!(PaymentError::InvalidPaymentSessionState),
+        })
+    }
+}
+
 impl Space for PaymentSessionStatus {
     const INIT_SPACE: usize = 1; // u8 representation
 }
\ No newline at end of file
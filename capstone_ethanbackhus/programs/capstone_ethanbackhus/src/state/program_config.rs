@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+pub const MAX_OPERATORS: usize = 10;
+
+// Cashback cap applied until the admin sets one, in basis points.
+pub const DEFAULT_MAX_CASHBACK_BPS: u16 = 500;
+
+// Limits for one escrow mode. Token sessions count in the mint's base units,
+// native SOL sessions in lamports, so each mode gets its own values.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct EscrowLimits {
+    pub large_settlement_threshold: u64,    // settlements above this total need a SettlementProposal
+    pub daily_limit: u64,                   // default 24h deposit limit per payer and mint
+    pub monthly_limit: u64,                 // default 30d deposit limit per payer and mint
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    pub admin: Pubkey,                      // wallet that initialized the config
+    #[max_len(MAX_OPERATORS)]
+    pub operators: Vec<Pubkey>,             // operator set allowed to approve settlements
+    pub threshold: u8,                      // approvals required (M of N) for large settlements
+    pub token_limits: EscrowLimits,         // limits for SPL token sessions
+    pub native_limits: EscrowLimits,        // limits for native SOL sessions
+    pub max_cashback_bps: u16,              // highest cashback rate a merchant can set
+    pub bump: u8,                           // bump for PDA
+}
+
+impl ProgramConfig {
+    pub fn is_operator(&self, key: &Pubkey) -> bool {
+        self.operators.contains(key)
+    }
+
+    pub fn limits(&self, native: bool) -> &EscrowLimits {
+        if native { &self.native_limits } else { &self.token_limits }
+    }
+
+    pub fn requires_approval(&self, total: u64, native: bool) -> bool {
+        total > self.limits(native).large_settlement_threshold
+    }
+}
+
+#[event]
+pub struct ProgramConfigInitialized {
+    pub admin: Pubkey,
+    pub operators: Vec<Pubkey>,
+    pub threshold: u8,
+    pub token_limits: EscrowLimits,
+    pub native_limits: EscrowLimits,
+}
+
+#[event]
+pub struct SpendingLimitsUpdated {
+    pub admin: Pubkey,
+    pub native: bool,
+    pub daily_limit: u64,
+    pub monthly_limit: u64,
+}
+
+#[event]
+pub struct MaxCashbackRateUpdated {
+    pub admin: Pubkey,
+    pub max_cashback_bps: u16,
+}
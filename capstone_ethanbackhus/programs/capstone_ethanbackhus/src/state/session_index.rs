@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::PaymentError;
+
+pub const SESSIONS_PER_PAGE: usize = 32;
+
+#[account]
+#[derive(InitSpace)]
+pub struct MerchantSessionIndex {
+    pub merchant_id_hash: [u8; 32],         // sha256 of the merchant id this page lists
+    pub page: u32,                          // page number, pages fill up in order from 0
+    #[max_len(SESSIONS_PER_PAGE)]
+    pub sessions: Vec<Pubkey>,              // payment sessions, oldest first
+    pub bump: u8,                           // bump for PDA
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PayerSessionIndex {
+    pub payer: Pubkey,                      // payer this page lists
+    pub page: u32,                          // page number, pages fill up in order from 0
+    #[max_len(SESSIONS_PER_PAGE)]
+    pub sessions: Vec<Pubkey>,              // payment sessions, oldest first
+    pub bump: u8,                           // bump for PDA
+}
+
+// Appends a session to a page. A new page can only be opened once the page
+// before it is full, so clients can walk pages from 0 without gaps.
+fn append_session(
+    sessions: &mut Vec<Pubkey>,
+    page: u32,
+    previous_page_len: Option<usize>,
+    session: Pubkey,
+) -> Result<()> {
+    require!(sessions.len() < SESSIONS_PER_PAGE, PaymentError::IndexPageFull);
+
+    if sessions.is_empty() && page > 0 {
+        require!(
+            previous_page_len == Some(SESSIONS_PER_PAGE),
+            PaymentError::InvalidIndexPage
+        );
+    }
+
+    sessions.push(session);
+    Ok(())
+}
+
+impl MerchantSessionIndex {
+    pub fn append(
+        &mut self,
+        previous: Option<&MerchantSessionIndex>,
+        session: Pubkey,
+    ) -> Result<()> {
+        append_session(
+            &mut self.sessions,
+            self.page,
+            previous.map(|previous| previous.sessions.len()),
+            session,
+        )
+    }
+}
+
+impl PayerSessionIndex {
+    pub fn append(
+        &mut self,
+        previous: Option<&PayerSessionIndex>,
+        session: Pubkey,
+    ) -> Result<()> {
+        append_session(
+            &mut self.sessions,
+            self.page,
+            previous.map(|previous| previous.sessions.len()),
+            session,
+        )
+    }
+}
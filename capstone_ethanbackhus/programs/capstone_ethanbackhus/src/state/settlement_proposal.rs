@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::state::program_config::{ProgramConfig, MAX_OPERATORS};
+
+#[account]
+#[derive(InitSpace)]
+pub struct SettlementProposal {
+    pub payment_session: Pubkey,            // session this proposal settles
+    pub proposer: Pubkey,                   // operator that opened the proposal
+    pub destination: Pubkey,                // account the settlement must pay out to
+    pub amount: u64,                        // escrow total (amount + tip) approved for payout
+    #[max_len(MAX_OPERATORS)]
+    pub approvals: Vec<Pubkey>,             // operators that have approved so far
+    pub executed: bool,                     // set once the settlement has been executed
+    pub created_ts: i64,                    // created timestamp
+    pub executed_ts: Option<i64>,           // executed timestamp
+    pub bump: u8,                           // bump for PDA
+}
+
+impl SettlementProposal {
+    // only approvals from current operators count towards the threshold
+    pub fn approval_count(&self, config: &ProgramConfig) -> usize {
+        self.approvals
+            .iter()
+            .filter(|approval| config.is_operator(approval))
+            .count()
+    }
+}
+
+#[event]
+pub struct SettlementProposed {
+    pub payment_session: Pubkey,
+    pub settlement_proposal: Pubkey,
+    pub proposer: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub tip_amount: u64,
+}
+
+#[event]
+pub struct SettlementApproved {
+    pub payment_session: Pubkey,
+    pub settlement_proposal: Pubkey,
+    pub operator: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct SettlementApprovalRevoked {
+    pub payment_session: Pubkey,
+    pub settlement_proposal: Pubkey,
+    pub operator: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct SettlementProposalCancelled {
+    pub payment_session: Pubkey,
+    pub settlement_proposal: Pubkey,
+    pub cancelled_by: Vec<Pubkey>,
+}
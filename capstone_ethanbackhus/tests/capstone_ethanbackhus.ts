@@ -4,7 +4,7 @@ import { CapstoneEthanbackhus } from "../target/types/capstone_ethanbackhus";
 import { publicKey, token } from "@coral-xyz/anchor/dist/cjs/utils";
 import { assert } from "chai";
 import { Keypair, PublicKey } from "@solana/web3.js";
-import { randomBytes } from "crypto";
+import { createHash, randomBytes } from "crypto";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
@@ -17,7 +17,7 @@ import {
   getOrCreateAssociatedTokenAccount,
   Account,
 } from "@solana/spl-token";
-import { formatDuration } from "./helpers";
+import { decodeFixed, formatDuration, SessionStatus } from "./helpers";
 import { isAccountsGeneric } from "@coral-xyz/anchor/dist/cjs/program/accounts-resolver";
 import { flattenPartialAccounts } from "@coral-xyz/anchor/dist/cjs/program/namespace/methods";
 import { get } from "http";
@@ -68,7 +68,140 @@ describe("capstone_ethanbackhus", () => {
     program.programId
   );
 
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+
+  // settlements above this total must go through a SettlementProposal
+  const largeSettlementThreshold = new anchor.BN(1_000);
+
+  // default per payer and mint spending limits
+  const dailyLimit = new anchor.BN(10_000);
+  const monthlyLimit = new anchor.BN(100_000);
+
+  // native SOL sessions have their own limits, in lamports
+  const nativeLimits = {
+    largeSettlementThreshold: new anchor.BN(1_000_000_000),
+    dailyLimit: new anchor.BN(10_000_000_000),
+    monthlyLimit: new anchor.BN(50_000_000_000),
+  };
+
+  // init_config is gated on the upgrade authority stored in the program's ProgramData
+  const [programDataPda] = PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+  );
+
+  // merchant record for merchantId, holds the registered payout and tip wallets
+  const [merchantRewardsPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("merchant_rewards"), createHash("sha256").update(merchantId).digest()],
+    program.programId
+  );
+  let tipWallet: PublicKey;
+  let payoutWallet: PublicKey;
+
+  const payerProfilePda = (mint: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("payer_profile"), payer.toBuffer(), mint.toBuffer()],
+      program.programId
+    )[0];
+
+  // sessions per MerchantSessionIndex / PayerSessionIndex page
+  const sessionsPerPage = 32;
+
+  const merchantIndexPda = (page: number): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("merchant_session_index"),
+        createHash("sha256").update(merchantId).digest(),
+        new anchor.BN(page).toArrayLike(Buffer, "le", 4),
+      ],
+      program.programId
+    )[0];
+
+  const payerIndexPda = (page: number): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("payer_session_index"),
+        payer.toBuffer(),
+        new anchor.BN(page).toArrayLike(Buffer, "le", 4),
+      ],
+      program.programId
+    )[0];
+
+  // page the next session is written to, plus the previous page when it opens a new one
+  const nextIndexPage = async (
+    account: "merchantSessionIndex" | "payerSessionIndex",
+    pageAddress: (page: number) => PublicKey
+  ) => {
+    for (let page = 0; ; page++) {
+      const index = await program.account[account].fetchNullable(pageAddress(page));
+      if (!index) {
+        return { page, address: pageAddress(page), previous: page > 0 ? pageAddress(page - 1) : null };
+      }
+      // cancelled sessions leave the page but still count towards filling it
+      if (index.appended < sessionsPerPage) {
+        return { page, address: pageAddress(page), previous: null };
+      }
+    }
+  };
+
+  const sessionIndexes = async () => {
+    const merchant = await nextIndexPage("merchantSessionIndex", merchantIndexPda);
+    const payerPage = await nextIndexPage("payerSessionIndex", payerIndexPda);
+    return {
+      merchantPage: merchant.page,
+      payerPage: payerPage.page,
+      accounts: {
+        merchantSessionIndex: merchant.address,
+        previousMerchantSessionIndex: merchant.previous,
+        payerSessionIndex: payerPage.address,
+        previousPayerSessionIndex: payerPage.previous,
+      },
+    };
+  };
+
   before(async () => {
+    // the operator config is a singleton, only initialize it once per validator
+    const configInfo = await connection.getAccountInfo(configPda);
+    if (!configInfo) {
+      await program.methods
+      .initConfig(
+        [wallet.publicKey],
+        1,
+        { largeSettlementThreshold, dailyLimit, monthlyLimit },
+        nativeLimits
+      )
+      .accountsStrict({
+        admin: wallet.publicKey,
+        config: configPda,
+        program: program.programId,
+        programData: programDataPda,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    }
+
+    // the merchant record is a singleton too, reuse its wallets on later runs
+    const merchantRewards = await program.account.merchantRewards.fetchNullable(merchantRewardsPda);
+    if (merchantRewards) {
+      tipWallet = merchantRewards.tipWallet;
+      payoutWallet = merchantRewards.payoutWallet;
+    } else {
+      tipWallet = Keypair.generate().publicKey;
+      payoutWallet = Keypair.generate().publicKey;
+      await program.methods
+      .initMerchantRewards(merchantId, wallet.publicKey, tipWallet, payoutWallet, 0)
+      .accountsStrict({
+        operator: wallet.publicKey,
+        config: configPda,
+        merchantRewards: merchantRewardsPda,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    }
+
     // create token Mint for testing
     tokenMint = await createMint(
       connection,
@@ -120,6 +253,7 @@ describe("capstone_ethanbackhus", () => {
   it("Successfully settles a payment", async () => {
 
     // execute initialize payment session instruction
+    const indexes = await sessionIndexes();
     const tx = await program.methods
     .initPaymentSession(
       Array.from(uuid),
@@ -127,7 +261,9 @@ describe("capstone_ethanbackhus", () => {
       amount,
       referenceId,
       fiatCurrency,
-      merchantBank
+      merchantBank,
+      indexes.merchantPage,
+      indexes.payerPage
     )
     .accountsStrict({
       payer: payer,
@@ -136,6 +272,8 @@ describe("capstone_ethanbackhus", () => {
       paymentSession: paymentSession,
       settlementAuthority: settlementAuthorityPda,
       escrowAta: escrowAta,
+      merchantRewards: merchantRewardsPda,
+      ...indexes.accounts,
       tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -161,8 +299,8 @@ describe("capstone_ethanbackhus", () => {
     // Fetch payment session and assert
     sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
-    const escrowAtaSessionAccount = await getAccount(connection, sessionAccount.escrowAta);
-    const payerAtaSessionAccount = await getAccount(connection, sessionAccount.payerAta);
+    const escrowAtaSessionAccount = await getAccount(connection, escrowAta);
+    const payerAtaSessionAccount = await getAccount(connection, payerAta.address);
 
     // get readable timestamps
     const createdTs = Number(sessionAccount.createdTs);
@@ -171,11 +309,11 @@ describe("capstone_ethanbackhus", () => {
 
     console.log("\n📊 PaymentSession PDA:")
     console.log("  Payer:", sessionAccount.payer.toBase58());
-    console.log("  Merchant ID:", sessionAccount.merchantId.toString());
+    console.log("  Merchant ID:", decodeFixed(sessionAccount.merchantId));
     console.log("  Amount:", sessionAccount.amount.toString());
     console.log("  Token Mint:", sessionAccount.tokenMint.toBase58());
-    console.log("  Escrow ATA:", sessionAccount.escrowAta.toBase58());
-    console.log("  Payer ATA:", sessionAccount.payerAta.toBase58());
+    console.log("  Escrow ATA:", escrowAta.toBase58());
+    console.log("  Payer ATA:", payerAta.address.toBase58());
     console.log("  Payer ATA amount:", sessionAccount.amount);
     console.log("  Status:", sessionAccount.status);
     console.log("  Created Timestamp:", formatDuration(createdTs));
@@ -189,26 +327,29 @@ describe("capstone_ethanbackhus", () => {
 
     assert.equal(tokenMint.toBase58(), sessionAccount.tokenMint.toBase58());                // make sure token mint is equal to sessionAccount mint
     assert.equal(payerBalanceBefore.amount, paymentAmount);                                 // DO WE NEED THIS? Need to make sure the mint amounts are equal
-    assert.equal(sessionAccount.payerAta.toBase58(), payerAta.address.toBase58());   // make sure payer ata is equal to sessionAccount payer ata
-    assert.equal(sessionAccount.escrowAta.toBase58(), escrowAta);                           // make sure escrow ata is equal to sessionAccount escrow ata
     assert.equal(escrowAtaSessionAccount.amount, escrowBalanceBefore.amount);               // make sure escrow ata amount is equal to sessionAccount escrow ata amount
     assert.equal(sessionAccount.payer.toBase58(), payer.toBase58());                        // make sure payer is equal to sessionAccount payer
-    assert.equal(sessionAccount.merchantId, "Amazon");                                      // make sure merchant id is equal to sessionAccount merchant id
+    assert.equal(decodeFixed(sessionAccount.merchantId), "Amazon");                                      // make sure merchant id is equal to sessionAccount merchant id
     assert.equal(sessionAccount.amount.toNumber(), paymentAmount);                          // make sure amount is equal to sessionAccount amount
-    assert.ok("initialized" in sessionAccount.status);                                      // make sure status is initialized     
+    assert.equal(sessionAccount.status, SessionStatus.initialized);                                    // make sure status is initialized     
     assert.ok(sessionAccount.expiryTs > bnZero);                                            // make sure expiry timestamp is greater than zero
 
 
     const depositTx = await program.methods
-    .depositStablecoin()
+    .depositStablecoin(bnZero, bnZero)
     .accountsStrict({
       payer: payer,
       paymentSession: paymentSession,
       payerAta: payerAta.address,
       settlementAuthority: settlementAuthorityPda,
       escrowAta: escrowAta,
+      config: configPda,
+      payerProfile: payerProfilePda(tokenMint),
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
       tokenMint: tokenMint,
       tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
     })
     .rpc();
 
@@ -219,8 +360,8 @@ describe("capstone_ethanbackhus", () => {
     sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
     // get payer and escrow balances after transaction
-    payerBalanceAfter = await getAccount(connection, sessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, sessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAta.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
 
     console.log("\n💰 After Deposit:");
     console.log("Payer balance after:", payerBalanceAfter.amount.toString());
@@ -230,13 +371,14 @@ describe("capstone_ethanbackhus", () => {
     assert.equal(payerBalanceAfter.amount, BigInt(0));                // if full amount transferred
     assert.equal(escrowBalanceAfter.amount, BigInt(paymentAmount));   // should equal the payment amount
 
-    const bitpayWallet = Keypair.generate();
+    // settlements can only pay out to the merchant's registered payout wallet
     const bitpayAtaAccount = await getOrCreateAssociatedTokenAccount(
       connection,
       wallet.payer,              // payer for account creation
       tokenMint,
-      bitpayWallet.publicKey
+      payoutWallet
     );
+    const bitPayBalanceBefore = bitpayAtaAccount.amount;
 
     // execute mark payment settled instruction
     const markPaymentTx = await program.methods
@@ -244,10 +386,15 @@ describe("capstone_ethanbackhus", () => {
     .accountsStrict({
       payer: payer,
       paymentSession: paymentSession,
+      config: configPda,
       payerAta: payerAta.address,
       escrowAta: escrowAta,
       settlementAuthority: settlementAuthorityPda,
       bitpayAta: bitpayAtaAccount.address,
+      tipAta: null,
+      merchantRewards: merchantRewardsPda,
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
       tokenMint: tokenMint,
       tokenProgram: TOKEN_PROGRAM_ID
     })
@@ -260,8 +407,8 @@ describe("capstone_ethanbackhus", () => {
     console.log("Transaction signature:", markPaymentTx);
 
     // get payer and escrow balances after settlement
-    payerBalanceAfter = await getAccount(connection, sessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, sessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAta.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
     const bitPayBalanceAfter = await getAccount(connection, bitpayAtaAccount.address);
 
     console.log("\n✅ Balances After Transaction");
@@ -274,7 +421,7 @@ describe("capstone_ethanbackhus", () => {
     // assert that the coins were successfully transferred from payer to escrow
     assert.equal(payerBalanceAfter.amount, BigInt(0));                        // payer balance should be zero
     assert.equal(escrowBalanceAfter.amount, BigInt(0));                       // escrow balance should be zero
-    assert.equal(bitPayBalanceAfter.amount, BigInt(paymentAmount));         // merchant balance should equal payment amount
+    assert.equal(bitPayBalanceAfter.amount - bitPayBalanceBefore, BigInt(paymentAmount)); // merchant receives the payment amount
   });
 
   it("Payment failed, refunding payment", async () => {
@@ -330,6 +477,7 @@ describe("capstone_ethanbackhus", () => {
     console.log("Payer ATA:", payerAta.address.toBase58()); 
 
     // execute initialize payment session instruction
+    const indexes = await sessionIndexes();
     const tx = await program.methods
     .initPaymentSession(
       Array.from(refundUuid),
@@ -337,7 +485,9 @@ describe("capstone_ethanbackhus", () => {
       amount,
       referenceId,
       fiatCurrency,
-      merchantBank
+      merchantBank,
+      indexes.merchantPage,
+      indexes.payerPage
     )
     .accountsStrict({
       payer: payer,
@@ -346,6 +496,8 @@ describe("capstone_ethanbackhus", () => {
       paymentSession: paymentSession,
       settlementAuthority: settlementAuthorityPda,
       escrowAta: escrowAta,
+      merchantRewards: merchantRewardsPda,
+      ...indexes.accounts,
       tokenProgram: TOKEN_PROGRAM_ID,
       associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       systemProgram: anchor.web3.SystemProgram.programId,
@@ -371,8 +523,8 @@ describe("capstone_ethanbackhus", () => {
     // Fetch payment session and assert
     let refundSessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
-    const escrowAtaSessionAccount = await getAccount(connection, refundSessionAccount.escrowAta);
-    const payerAtaSessionAccount = await getAccount(connection, refundSessionAccount.payerAta);
+    const escrowAtaSessionAccount = await getAccount(connection, escrowAta);
+    const payerAtaSessionAccount = await getAccount(connection, payerAta.address);
 
     // get readable timestamps
     const createdTs = Number(refundSessionAccount.createdTs);
@@ -381,11 +533,11 @@ describe("capstone_ethanbackhus", () => {
 
     console.log("\n📊 PaymentSession PDA:")
     console.log("  Payer:", refundSessionAccount.payer.toBase58());
-    console.log("  Merchant ID:", refundSessionAccount.merchantId.toString());
+    console.log("  Merchant ID:", decodeFixed(refundSessionAccount.merchantId));
     console.log("  Amount:", refundSessionAccount.amount.toString());
     console.log("  Token Mint:", refundSessionAccount.tokenMint.toBase58());
-    console.log("  Escrow ATA:", refundSessionAccount.escrowAta.toBase58());
-    console.log("  Payer ATA:", refundSessionAccount.payerAta.toBase58());
+    console.log("  Escrow ATA:", escrowAta.toBase58());
+    console.log("  Payer ATA:", payerAta.address.toBase58());
     console.log("  Payer ATA amount:", refundSessionAccount.amount);
     console.log("  Status:", refundSessionAccount.status);
     console.log("  Created Timestamp:", formatDuration(createdTs));
@@ -399,26 +551,29 @@ describe("capstone_ethanbackhus", () => {
 
     assert.equal(tokenMint.toBase58(), refundSessionAccount.tokenMint.toBase58());                // make sure token mint is equal to sessionAccount mint
     assert.equal(payerBalanceBefore.amount, paymentAmount);                                 // DO WE NEED THIS? Need to make sure the mint amounts are equal
-    assert.equal(refundSessionAccount.payerAta.toBase58(), payerAta.address.toBase58());   // make sure payer ata is equal to sessionAccount payer ata
-    assert.equal(refundSessionAccount.escrowAta.toBase58(), escrowAta);                           // make sure escrow ata is equal to sessionAccount escrow ata
     assert.equal(escrowAtaSessionAccount.amount, escrowBalanceBefore.amount);               // make sure escrow ata amount is equal to sessionAccount escrow ata amount
     assert.equal(refundSessionAccount.payer.toBase58(), payer.toBase58());                        // make sure payer is equal to sessionAccount payer
-    assert.equal(refundSessionAccount.merchantId, "Amazon");                                      // make sure merchant id is equal to sessionAccount merchant id
+    assert.equal(decodeFixed(refundSessionAccount.merchantId), "Amazon");                                      // make sure merchant id is equal to sessionAccount merchant id
     assert.equal(refundSessionAccount.amount.toNumber(), paymentAmount);                          // make sure amount is equal to sessionAccount amount
-    assert.ok("initialized" in refundSessionAccount.status);                                      // make sure status is initialized     
+    assert.equal(refundSessionAccount.status, SessionStatus.initialized);                                    // make sure status is initialized     
     assert.ok(refundSessionAccount.expiryTs > bnZero);                                            // make sure expiry timestamp is greater than zero
 
 
     const depositTx = await program.methods
-    .depositStablecoin()
+    .depositStablecoin(bnZero, bnZero)
     .accountsStrict({
       payer: payer,
       paymentSession: paymentSession,
       payerAta: payerAta.address,
       settlementAuthority: settlementAuthorityPda,
       escrowAta: escrowAta,
+      config: configPda,
+      payerProfile: payerProfilePda(tokenMint),
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
       tokenMint: tokenMint,
       tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
     })
     .rpc();
 
@@ -429,8 +584,8 @@ describe("capstone_ethanbackhus", () => {
     refundSessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
     // get payer and escrow balances after transaction
-    payerBalanceAfter = await getAccount(connection, refundSessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, refundSessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAta.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
 
     console.log("\n💰 After Deposit:");
     console.log("Payer balance after:", payerBalanceAfter.amount.toString());
@@ -447,6 +602,9 @@ describe("capstone_ethanbackhus", () => {
       payer: payer,
       paymentSession: paymentSession,
       settlementAuthority: settlementAuthorityPda,
+      config: null,
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
       payerAta: payerAta.address,
       escrowAta: escrowAta,
       tokenMint: tokenMint,
@@ -461,8 +619,8 @@ describe("capstone_ethanbackhus", () => {
     const sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
     // get payer and escrow balances after refund
-    payerBalanceAfter = await getAccount(connection, sessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, sessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAta.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
 
     console.log("\n💰 After Refund:");
     console.log("Payer balance after:", payerBalanceAfter.amount.toString());
@@ -473,4 +631,318 @@ describe("capstone_ethanbackhus", () => {
     assert.equal(escrowBalanceAfter.amount, BigInt(0));
   });
 
+  it("Successfully settles a payment with a tip", async () => {
+
+    const tipUuid = randomBytes(16);
+    const tipAmount = new anchor.BN(15);
+
+    const [paymentSession] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payment_session"), wallet.publicKey.toBuffer(), Buffer.from(tipUuid)],
+      program.programId
+    );
+
+    const [settlementAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("settlement_authority"), paymentSession.toBuffer(), Buffer.from(tipUuid)],
+      program.programId
+    );
+
+    tokenMint = await createMint(
+      connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      decimals
+    );
+
+    escrowAta = getAssociatedTokenAddressSync(tokenMint, settlementAuthorityPda, true);
+
+    payerAta = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      tokenMint,
+      payer
+    );
+
+    // mint the payment amount plus the tip to the payer
+    await mintTo(
+      connection,
+      wallet.payer,
+      tokenMint,
+      payerAta.address,
+      wallet.payer,
+      BigInt(amount.add(tipAmount).toString())
+    );
+
+    const indexes = await sessionIndexes();
+    await program.methods
+    .initPaymentSession(
+      Array.from(tipUuid),
+      merchantId,
+      amount,
+      referenceId,
+      fiatCurrency,
+      merchantBank,
+      indexes.merchantPage,
+      indexes.payerPage
+    )
+    .accountsStrict({
+      payer: payer,
+      tokenMint: tokenMint,
+      payerAta: payerAta.address,
+      paymentSession: paymentSession,
+      settlementAuthority: settlementAuthorityPda,
+      escrowAta: escrowAta,
+      merchantRewards: merchantRewardsPda,
+      ...indexes.accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
+
+    await program.methods
+    .depositStablecoin(tipAmount, bnZero)
+    .accountsStrict({
+      payer: payer,
+      paymentSession: paymentSession,
+      payerAta: payerAta.address,
+      settlementAuthority: settlementAuthorityPda,
+      escrowAta: escrowAta,
+      config: configPda,
+      payerProfile: payerProfilePda(tokenMint),
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
+      tokenMint: tokenMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
+
+    let tipSessionAccount = await program.account.paymentSession.fetch(paymentSession);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
+
+    console.log("\n✅ Stablecoins Deposited with tip");
+    console.log("Tip amount:", tipSessionAccount.tipAmount.toString());
+    console.log("Escrow balance after:", escrowBalanceAfter.amount.toString());
+
+    assert.equal(tipSessionAccount.tipAmount.toNumber(), tipAmount.toNumber());                 // tip is stored separately from the amount
+    assert.equal(tipSessionAccount.amount.toNumber(), amount.toNumber());                       // amount is unchanged
+    assert.equal(escrowBalanceAfter.amount, BigInt(amount.add(tipAmount).toString()));          // escrow holds amount + tip
+
+    const bitpayAtaAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      tokenMint,
+      payoutWallet
+    );
+    const bitPayBalanceBefore = bitpayAtaAccount.amount;
+
+    const tipAtaAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      tokenMint,
+      tipWallet
+    );
+
+    await program.methods
+    .markPaymentSettled()
+    .accountsStrict({
+      payer: payer,
+      paymentSession: paymentSession,
+      config: configPda,
+      payerAta: payerAta.address,
+      escrowAta: escrowAta,
+      settlementAuthority: settlementAuthorityPda,
+      bitpayAta: bitpayAtaAccount.address,
+      tipAta: tipAtaAccount.address,
+      merchantRewards: merchantRewardsPda,
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
+      tokenMint: tokenMint,
+      tokenProgram: TOKEN_PROGRAM_ID
+    })
+    .rpc();
+
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
+    const bitPayBalanceAfter = await getAccount(connection, bitpayAtaAccount.address);
+    const tipBalanceAfter = await getAccount(connection, tipAtaAccount.address);
+
+    console.log("\n💰 After Payment Settled with tip:");
+    console.log("Escrow balance after:", escrowBalanceAfter.amount.toString());
+    console.log("Bitpay balance after:", bitPayBalanceAfter.amount.toString());
+    console.log("Tip balance after:", tipBalanceAfter.amount.toString());
+
+    assert.equal(escrowBalanceAfter.amount, BigInt(0));                                   // escrow is emptied
+    assert.equal(bitPayBalanceAfter.amount - bitPayBalanceBefore, BigInt(amount.toString())); // bitpay receives the payment amount only
+    assert.equal(tipBalanceAfter.amount, BigInt(tipAmount.toString()));                   // tip destination receives the full tip
+  });
+
+  it("Settles a large payment through an approved settlement proposal", async () => {
+
+    const largeUuid = randomBytes(16);
+    const largeAmount = largeSettlementThreshold.add(new anchor.BN(1));
+
+    const [paymentSession] = PublicKey.findProgramAddressSync(
+      [Buffer.from("payment_session"), wallet.publicKey.toBuffer(), Buffer.from(largeUuid)],
+      program.programId
+    );
+
+    const [settlementAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("settlement_authority"), paymentSession.toBuffer(), Buffer.from(largeUuid)],
+      program.programId
+    );
+
+    const [settlementProposal] = PublicKey.findProgramAddressSync(
+      [Buffer.from("settlement_proposal"), paymentSession.toBuffer()],
+      program.programId
+    );
+
+    tokenMint = await createMint(
+      connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      decimals
+    );
+
+    escrowAta = getAssociatedTokenAddressSync(tokenMint, settlementAuthorityPda, true);
+
+    payerAta = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      tokenMint,
+      payer
+    );
+
+    await mintTo(
+      connection,
+      wallet.payer,
+      tokenMint,
+      payerAta.address,
+      wallet.payer,
+      BigInt(largeAmount.toString())
+    );
+
+    const indexes = await sessionIndexes();
+    await program.methods
+    .initPaymentSession(
+      Array.from(largeUuid),
+      merchantId,
+      largeAmount,
+      referenceId,
+      fiatCurrency,
+      merchantBank,
+      indexes.merchantPage,
+      indexes.payerPage
+    )
+    .accountsStrict({
+      payer: payer,
+      tokenMint: tokenMint,
+      payerAta: payerAta.address,
+      paymentSession: paymentSession,
+      settlementAuthority: settlementAuthorityPda,
+      escrowAta: escrowAta,
+      merchantRewards: merchantRewardsPda,
+      ...indexes.accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
+
+    await program.methods
+    .depositStablecoin(bnZero, bnZero)
+    .accountsStrict({
+      payer: payer,
+      paymentSession: paymentSession,
+      payerAta: payerAta.address,
+      settlementAuthority: settlementAuthorityPda,
+      escrowAta: escrowAta,
+      config: configPda,
+      payerProfile: payerProfilePda(tokenMint),
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
+      tokenMint: tokenMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
+
+    const bitpayAtaAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      wallet.payer,
+      tokenMint,
+      payoutWallet
+    );
+    const bitPayBalanceBefore = bitpayAtaAccount.amount;
+
+    // mark payment settled must refuse sessions above the threshold
+    try {
+      await program.methods
+      .markPaymentSettled()
+      .accountsStrict({
+        payer: payer,
+        paymentSession: paymentSession,
+        config: configPda,
+        payerAta: payerAta.address,
+        escrowAta: escrowAta,
+        settlementAuthority: settlementAuthorityPda,
+        bitpayAta: bitpayAtaAccount.address,
+        tipAta: null,
+        merchantRewards: merchantRewardsPda,
+        loyaltyMint: null,
+        payerLoyaltyAta: null,
+        tokenMint: tokenMint,
+        tokenProgram: TOKEN_PROGRAM_ID
+      })
+      .rpc();
+      assert.fail("large settlement should require an approved proposal");
+    } catch (err) {
+      assert.include(err.toString(), "SettlementRequiresApproval");
+    }
+
+    // the proposer's approval is recorded when the proposal is created
+    await program.methods
+    .proposeSettlement()
+    .accountsStrict({
+      operator: wallet.publicKey,
+      config: configPda,
+      paymentSession: paymentSession,
+      settlementProposal: settlementProposal,
+      destination: bitpayAtaAccount.address,
+      merchantRewards: merchantRewardsPda,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    })
+    .rpc();
+
+    await program.methods
+    .executeSettlement()
+    .accountsStrict({
+      operator: wallet.publicKey,
+      config: configPda,
+      paymentSession: paymentSession,
+      settlementProposal: settlementProposal,
+      escrowAta: escrowAta,
+      settlementAuthority: settlementAuthorityPda,
+      bitpayAta: bitpayAtaAccount.address,
+      tipAta: null,
+      merchantRewards: merchantRewardsPda,
+      loyaltyMint: null,
+      payerLoyaltyAta: null,
+      tokenMint: tokenMint,
+      tokenProgram: TOKEN_PROGRAM_ID
+    })
+    .rpc();
+
+    const proposalAccount = await program.account.settlementProposal.fetch(settlementProposal);
+    const bitPayBalanceAfter = await getAccount(connection, bitpayAtaAccount.address);
+
+    console.log("\n✅ Large Payment Settled through proposal");
+    console.log("Approvals:", proposalAccount.approvals.length);
+    console.log("Bitpay balance after:", bitPayBalanceAfter.amount.toString());
+
+    assert.ok(proposalAccount.executed);                                                  // proposal is marked executed
+    assert.equal(bitPayBalanceAfter.amount - bitPayBalanceBefore, BigInt(largeAmount.toString())); // bitpay receives the full amount
+  });
+
 });
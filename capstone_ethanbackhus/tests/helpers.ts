@@ -7,3 +7,21 @@ export const formatDuration = (seconds: number): string =>
   [Math.floor(seconds / 3600), Math.floor((seconds % 3600) / 60), seconds % 60]
     .map(n => n.toString().padStart(2, '0'))
     .join(':');
+
+/**
+ * Decodes a zero padded fixed-size text field of a PaymentSession
+ */
+export const decodeFixed = (bytes: number[]): string =>
+  Buffer.from(bytes).toString('utf8').replace(/\0+$/, '');
+
+/**
+ * PaymentSession status values, stored on the account as a u8
+ */
+export const SessionStatus = {
+  initialized: 0,
+  funded: 1,
+  refunded: 2,
+  pendingFiat: 3,
+  settled: 4,
+  cancelled: 5,
+} as const;
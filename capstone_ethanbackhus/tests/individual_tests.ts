@@ -18,7 +18,7 @@ import {
   getOrCreateAssociatedTokenAccount,
   Account,
 } from "@solana/spl-token";
-import { formatDuration } from "./helpers";
+import { decodeFixed, formatDuration, SessionStatus } from "./helpers";
 import { isAccountsGeneric } from "@coral-xyz/anchor/dist/cjs/program/accounts-resolver";
 import { flattenPartialAccounts } from "@coral-xyz/anchor/dist/cjs/program/namespace/methods";
 import { get } from "http";
@@ -160,8 +160,8 @@ describe("capstone_ethanbackhus", () => {
     // Fetch payment session and assert
     const sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
-    const escrowAtaSessionAccount = await getAccount(connection, sessionAccount.escrowAta);
-    const payerAtaSessionAccount = await getAccount(connection, sessionAccount.payerAta);
+    const escrowAtaSessionAccount = await getAccount(connection, escrowAta);
+    const payerAtaSessionAccount = await getAccount(connection, payerAtaAccount.address);
 
     // get readable timestamps
     const createdTs = Number(sessionAccount.createdTs);
@@ -170,11 +170,11 @@ describe("capstone_ethanbackhus", () => {
 
     console.log("\n📊 PaymentSession PDA:")
     console.log("  Payer:", sessionAccount.payer.toBase58());
-    console.log("  Merchant ID:", sessionAccount.merchantId.toString());
+    console.log("  Merchant ID:", decodeFixed(sessionAccount.merchantId));
     console.log("  Amount:", sessionAccount.amount.toString());
     console.log("  Token Mint:", sessionAccount.tokenMint.toBase58());
-    console.log("  Escrow ATA:", sessionAccount.escrowAta.toBase58());
-    console.log("  Payer ATA:", sessionAccount.payerAta.toBase58());
+    console.log("  Escrow ATA:", escrowAta.toBase58());
+    console.log("  Payer ATA:", payerAtaAccount.address.toBase58());
     console.log("  Payer ATA amount:", sessionAccount.amount);
     console.log("  Status:", sessionAccount.status);
     console.log("  Created Timestamp:", formatDuration(createdTs));
@@ -189,13 +189,11 @@ describe("capstone_ethanbackhus", () => {
     // assert
     assert.equal(tokenMint.toBase58(), sessionAccount.tokenMint.toBase58());                // make sure token mint is equal to sessionAccount mint
     assert.equal(payerBalanceBefore.amount, paymentAmount);                                 // DO WE NEED THIS? Need to make sure the mint amounts are equal
-    assert.equal(sessionAccount.payerAta.toBase58(), payerAtaAccount.address.toBase58());   // make sure payer ata is equal to sessionAccount payer ata
-    assert.equal(sessionAccount.escrowAta.toBase58(), escrowAta.toBase58());                // make sure escrow ata is equal to sessionAccount escrow ata
     assert.equal(escrowAtaSessionAccount.amount, escrowBalanceBefore.amount);               // make sure escrow ata amount is equal to sessionAccount escrow ata amount
     assert.equal(sessionAccount.payer.toBase58(), payer.toBase58());                        // make sure payer is equal to sessionAccount payer
-    assert.equal(sessionAccount.merchantId, "Amazon");                                      // make sure merchant id is equal to sessionAccount merchant id
+    assert.equal(decodeFixed(sessionAccount.merchantId), "Amazon");                                      // make sure merchant id is equal to sessionAccount merchant id
     assert.equal(sessionAccount.amount.toNumber(), paymentAmount);                          // make sure amount is equal to sessionAccount amount
-    assert.ok("initialized" in sessionAccount.status);                                      // make sure status is initialized     
+    assert.equal(sessionAccount.status, SessionStatus.initialized);                                    // make sure status is initialized     
     assert.ok(sessionAccount.expiryTs > bnZero);                                            // make sure expiry timestamp is greater than zero
   });
 
@@ -226,8 +224,8 @@ describe("capstone_ethanbackhus", () => {
     const sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
     // get payer and escrow balances after transaction
-    payerBalanceAfter = await getAccount(connection, sessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, sessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAtaAccount.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
 
     console.log("\n💰 After Deposit:");
     console.log("Payer balance after:", payerBalanceAfter.amount.toString());
@@ -265,8 +263,8 @@ describe("capstone_ethanbackhus", () => {
     const sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
     // get payer and escrow balances after refund
-    payerBalanceAfter = await getAccount(connection, sessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, sessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAtaAccount.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
 
     console.log("\n💰 After Refund:");
     console.log("Payer balance after:", payerBalanceAfter.amount.toString());
@@ -290,13 +288,13 @@ describe("capstone_ethanbackhus", () => {
 
     const sessionAccount = await program.account.paymentSession.fetch(paymentSession);
     console.log("session.amount:", sessionAccount.amount.toString());
-    console.log("session.escrowAta:", sessionAccount.escrowAta.toBase58());
-    console.log("session.payerAta:", sessionAccount.payerAta.toBase58());
+    console.log("session.escrowAta:", escrowAta.toBase58());
+    console.log("session.payerAta:", payerAtaAccount.address.toBase58());
     console.log("session.settlement_authority:", sessionAccount.settlementAuthority.toBase58());
     console.log("session.settlement_bump:", sessionAccount.settlement_bump?.toString());
 
-    const escrowAcct = await getAccount(connection, sessionAccount.escrowAta);
-    const payerAcct = await getAccount(connection, sessionAccount.payerAta);
+    const escrowAcct = await getAccount(connection, escrowAta);
+    const payerAcct = await getAccount(connection, payerAtaAccount.address);
     const merchantAcct = await getAccount(connection, merchantAta.address);
     const mintInfo = await getMint(connection, tokenMint);
 
@@ -334,22 +332,22 @@ describe("capstone_ethanbackhus", () => {
     //const sessionAccount = await program.account.paymentSession.fetch(paymentSession);
 
     console.log("session.amount:", sessionAccount.amount.toString());
-    console.log("session.escrowAta:", sessionAccount.escrowAta.toBase58());
-    console.log("session.payerAta:", sessionAccount.payerAta.toBase58());
+    console.log("session.escrowAta:", escrowAta.toBase58());
+    console.log("session.payerAta:", payerAtaAccount.address.toBase58());
     console.log("session.settlement_authority:", sessionAccount.settlement_authority.toBase58());
     console.log("session.settlement_bump:", sessionAccount.settlement_bump?.toString());
 
     // get payer and escrow balances after refund
-    payerBalanceAfter = await getAccount(connection, sessionAccount.payerAta);
-    escrowBalanceAfter = await getAccount(connection, sessionAccount.escrowAta);
+    payerBalanceAfter = await getAccount(connection, payerAtaAccount.address);
+    escrowBalanceAfter = await getAccount(connection, escrowAta);
     merchantBalanceAfter = await getAccount(connection, merchantAta.address);
 
     //console.log("\n✅ Balances After Transaction");
     //console.log("Transaction signature:", tx);
     //console.log("\n💰 After Payment Settled:");
     
-    //const escrowAcct = await getAccount(connection, sessionAccount.escrowAta);
-    //const payerAcct = await getAccount(connection, sessionAccount.payerAta);
+    //const escrowAcct = await getAccount(connection, escrowAta);
+    //const payerAcct = await getAccount(connection, payerAtaAccount.address);
     //const merchantAcct = await getAccount(connection, merchantAta.address);
     //const mintInfo = await getMint(connection, tokenMint);
 
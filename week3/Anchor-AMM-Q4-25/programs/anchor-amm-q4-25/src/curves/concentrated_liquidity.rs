@@ -0,0 +1,219 @@
+use ethnum::U256;
+
+use super::FEE_DENOMINATOR;
+
+// Token amounts for liquidity L between two Q64.64 sqrt prices a < b:
+//
+//     x = L * (b - a) / (a * b)        y = L * (b - a)
+//
+// Amounts owed to the pool round up, amounts paid out by the pool round down.
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price: u128, // Sqrt price reached by the step
+    pub amount_in: u64,   // Input consumed, fee excluded
+    pub amount_out: u64,  // Output paid out
+    pub fee: u64,         // Fee charged on top of amount_in
+}
+
+fn div_round(numerator: U256, denominator: U256, round_up: bool) -> U256 {
+    let quotient = numerator / denominator;
+    match round_up && numerator % denominator != 0 {
+        true => quotient + 1,
+        false => quotient,
+    }
+}
+
+pub fn amount_x_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Option<u64> {
+    let (low, high) = (sqrt_price_a.min(sqrt_price_b), sqrt_price_a.max(sqrt_price_b));
+    if low == 0 {
+        return None;
+    }
+
+    let numerator = (U256::from(liquidity) << 64u32).checked_mul(U256::from(high - low))?;
+    let amount = div_round(div_round(numerator, U256::from(high), round_up), U256::from(low), round_up);
+    u64::try_from(amount).ok()
+}
+
+pub fn amount_y_delta(sqrt_price_a: u128, sqrt_price_b: u128, liquidity: u128, round_up: bool) -> Option<u64> {
+    let diff = sqrt_price_a.abs_diff(sqrt_price_b);
+    let amount = div_round(U256::from(liquidity) * U256::from(diff), U256::ONE << 64u32, round_up);
+    u64::try_from(amount).ok()
+}
+
+// Sqrt price after adding `amount` of X, L / (L / sqrt_price + amount), rounded up
+pub fn next_sqrt_price_from_x_in(sqrt_price: u128, liquidity: u128, amount: u64) -> Option<u128> {
+    if amount == 0 {
+        return Some(sqrt_price);
+    }
+
+    let numerator = U256::from(liquidity) << 64u32;
+    let denominator = (numerator / U256::from(sqrt_price)).checked_add(U256::from(amount))?;
+    u128::try_from(div_round(numerator, denominator, true)).ok()
+}
+
+// Sqrt price after adding `amount` of Y, sqrt_price + amount / L, rounded down
+pub fn next_sqrt_price_from_y_in(sqrt_price: u128, liquidity: u128, amount: u64) -> Option<u128> {
+    if amount == 0 {
+        return Some(sqrt_price);
+    }
+
+    let delta = (U256::from(amount) << 64u32) / U256::from(liquidity);
+    sqrt_price.checked_add(u128::try_from(delta).ok()?)
+}
+
+// Amounts of X and Y backing `liquidity` over [lower, upper) at the current price
+pub fn amounts_for_liquidity(
+    sqrt_price: u128,
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Option<(u64, u64)> {
+    if sqrt_price <= sqrt_price_lower {
+        Some((amount_x_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?, 0))
+    } else if sqrt_price >= sqrt_price_upper {
+        Some((0, amount_y_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?))
+    } else {
+        Some((
+            amount_x_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            amount_y_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        ))
+    }
+}
+
+// Swaps up to `amount_remaining` (fee included) within a single liquidity range,
+// moving the price from `sqrt_price` towards `sqrt_price_target`. X is the input
+// when the target is below the current price.
+pub fn compute_swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee: u16,
+) -> Option<SwapStep> {
+    let x_in = sqrt_price_target < sqrt_price;
+    let fee_rate = fee as u128;
+    let denominator = FEE_DENOMINATOR as u128;
+
+    let amount_less_fee = (amount_remaining as u128 * (denominator - fee_rate) / denominator) as u64;
+
+    // input needed to reach the target, None when it does not fit in a u64
+    let to_target = match x_in {
+        true => amount_x_delta(sqrt_price_target, sqrt_price, liquidity, true),
+        false => amount_y_delta(sqrt_price, sqrt_price_target, liquidity, true),
+    };
+
+    let (next_sqrt_price, amount_in) = match to_target {
+        Some(to_target) if to_target <= amount_less_fee => (sqrt_price_target, to_target),
+        _ => {
+            let next = match x_in {
+                true => next_sqrt_price_from_x_in(sqrt_price, liquidity, amount_less_fee)?,
+                false => next_sqrt_price_from_y_in(sqrt_price, liquidity, amount_less_fee)?,
+            };
+            let amount_in = match x_in {
+                true => amount_x_delta(next, sqrt_price, liquidity, true)?,
+                false => amount_y_delta(sqrt_price, next, liquidity, true)?,
+            };
+            (next, amount_in)
+        }
+    };
+
+    let amount_out = match x_in {
+        true => amount_y_delta(next_sqrt_price, sqrt_price, liquidity, false)?,
+        false => amount_x_delta(sqrt_price, next_sqrt_price, liquidity, false)?,
+    };
+
+    // a step that stops short of the target keeps everything left over as the fee
+    let fee_amount = match next_sqrt_price == sqrt_price_target {
+        true => u64::try_from((amount_in as u128 * fee_rate).div_ceil(denominator - fee_rate)).ok()?,
+        false => amount_remaining.checked_sub(amount_in)?,
+    };
+
+    Some(SwapStep {
+        sqrt_price: next_sqrt_price,
+        amount_in,
+        amount_out,
+        fee: fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::tick_math::sqrt_price_at_tick;
+
+    const ONE: u128 = 1 << 64;
+
+    #[test]
+    fn amounts_round_in_the_pools_favour() {
+        let (low, high) = (sqrt_price_at_tick(-100).unwrap(), sqrt_price_at_tick(100).unwrap());
+
+        let up = amounts_for_liquidity(ONE, low, high, 1_000_000, true).unwrap();
+        let down = amounts_for_liquidity(ONE, low, high, 1_000_000, false).unwrap();
+
+        assert_eq!(up, (down.0 + 1, down.1 + 1));
+        // L * (1 - 1 / sqrt(1.0001^100)) ~ 4_987.6
+        assert_eq!(down, (4_987, 4_987));
+    }
+
+    #[test]
+    fn amounts_outside_the_range_are_single_sided() {
+        let (low, high) = (sqrt_price_at_tick(10).unwrap(), sqrt_price_at_tick(20).unwrap());
+
+        let (x, y) = amounts_for_liquidity(ONE, low, high, 1_000_000_000, true).unwrap();
+        assert!(x > 0 && y == 0);
+
+        let (x, y) = amounts_for_liquidity(sqrt_price_at_tick(30).unwrap(), low, high, 1_000_000_000, true).unwrap();
+        assert!(x == 0 && y > 0);
+    }
+
+    #[test]
+    fn step_reaches_the_target_when_input_suffices() {
+        let target = sqrt_price_at_tick(-10).unwrap();
+        let step = compute_swap_step(ONE, target, 1_000_000_000_000, 1_000_000_000, 30).unwrap();
+
+        assert_eq!(step.sqrt_price, target);
+        assert_eq!(step.amount_in, amount_x_delta(target, ONE, 1_000_000_000_000, true).unwrap());
+        assert!(step.amount_in + step.fee <= 1_000_000_000);
+    }
+
+    #[test]
+    fn partial_step_consumes_all_input() {
+        let target = sqrt_price_at_tick(1_000).unwrap();
+        let step = compute_swap_step(ONE, target, 1_000_000_000_000, 1_000_000, 30).unwrap();
+
+        assert!(step.sqrt_price > ONE && step.sqrt_price < target);
+        assert_eq!(step.amount_in + step.fee, 1_000_000);
+        // close to 1:1 with a 0.3% fee
+        assert!(step.amount_out < 997_000 && step.amount_out > 996_000);
+    }
+
+    #[test]
+    fn steps_never_pay_out_more_than_the_curve() {
+        for liquidity in [1_000, 1_000_000_007, 1 << 80] {
+            for amount in [1, 999, 1_000_000, u32::MAX as u64] {
+                for target_tick in [-5_000, 5_000] {
+                    let target = sqrt_price_at_tick(target_tick).unwrap();
+                    let step = compute_swap_step(ONE, target, liquidity, amount, 0).unwrap();
+
+                    // what the curve owes for the price move, rounded up for the pool
+                    let owed = match target_tick < 0 {
+                        true => amount_y_delta(step.sqrt_price, ONE, liquidity, true).unwrap(),
+                        false => amount_x_delta(ONE, step.sqrt_price, liquidity, true).unwrap(),
+                    };
+                    assert!(step.amount_out <= owed);
+                    assert!(step.amount_in <= amount);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_liquidity_moves_straight_to_the_target() {
+        let target = sqrt_price_at_tick(64).unwrap();
+        let step = compute_swap_step(ONE, target, 0, 1_000, 30).unwrap();
+
+        assert_eq!(step, SwapStep { sqrt_price: target, amount_in: 0, amount_out: 0, fee: 0 });
+    }
+}
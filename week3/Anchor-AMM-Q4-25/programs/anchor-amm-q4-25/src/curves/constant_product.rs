@@ -0,0 +1,80 @@
+use super::{add_fee, SwapAmounts};
+
+// Exact-output pricing for x * y = k. Exact-input swaps go through the
+// constant_product_curve crate.
+
+// Prices taking exactly `amount_out` from the `to` reserve. The required input is
+// ceil(from * amount_out / (to - amount_out)), grossed up by the fee, so k never
+// decreases.
+pub fn swap_exact_out(fee: u16, from: u64, to: u64, amount_out: u64) -> Option<SwapAmounts> {
+    if from == 0 || amount_out == 0 {
+        return None;
+    }
+
+    let new_to = to.checked_sub(amount_out)?;
+    if new_to == 0 {
+        return None;
+    }
+
+    let numerator = (from as u128).checked_mul(amount_out as u128)?;
+    let amount_in = u64::try_from(numerator.div_ceil(new_to as u128)).ok()?;
+    let (deposit, fee_amount) = add_fee(amount_in, fee)?;
+
+    Some(SwapAmounts {
+        deposit,
+        withdraw: amount_out,
+        fee: fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn k(x: u64, y: u64) -> u128 {
+        x as u128 * y as u128
+    }
+
+    #[test]
+    fn exact_out_never_decreases_k() {
+        for (x, y) in [(1_000, 1_000), (7, 1_000_003), (1_000_000_000, 3), (u64::MAX / 2, 999_999_999)] {
+            for amount_out in [1, 2, y / 3, y / 2, y - 1] {
+                if amount_out == 0 {
+                    continue;
+                }
+                for fee in [0, 1, 30, 9_999] {
+                    let Some(result) = swap_exact_out(fee, x, y, amount_out) else {
+                        continue;
+                    };
+
+                    let net_in = result.deposit - result.fee;
+                    assert!(k(x + net_in, y - amount_out) >= k(x, y), "x {x} y {y} out {amount_out}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_rounds_input_up() {
+        // 100 * 1 / 99 = 1.01, so 2 has to be paid in
+        let result = swap_exact_out(0, 100, 100, 1).unwrap();
+        assert_eq!(result.deposit, 2);
+        assert_eq!(result.fee, 0);
+    }
+
+    #[test]
+    fn exact_out_charges_fee_on_top() {
+        // 1_000 * 100 / 900 = 111.1 -> 112 net, grossed up by 3% -> 115.46 -> 116
+        let result = swap_exact_out(300, 1_000, 1_000, 100).unwrap();
+        assert_eq!(result.deposit, 116);
+        assert_eq!(result.fee, 4);
+        assert_eq!(result.withdraw, 100);
+    }
+
+    #[test]
+    fn exact_out_rejects_emptying_the_pool() {
+        assert_eq!(swap_exact_out(30, 1_000, 1_000, 1_000), None);
+        assert_eq!(swap_exact_out(30, 1_000, 1_000, 1_001), None);
+        assert_eq!(swap_exact_out(10_000, 1_000, 1_000, 10), None);
+    }
+}
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use ethnum::U256;
+
+use super::FEE_DENOMINATOR;
+use crate::{errors::AmmError, state::DynamicFee};
+
+// The volatility accumulator sums the price moves of recent swaps, in basis
+// points of the price before each swap. It decays linearly back to zero over
+// `decay_period` seconds without swaps, and every basis point of it adds
+// `variable_fee_control` / 10_000 basis points on top of the fee floor.
+
+impl DynamicFee {
+    pub fn new(fee_floor: u16, fee_cap: u16, variable_fee_control: u16, decay_period: u32) -> Result<Self> {
+        require!(
+            fee_floor <= fee_cap && (fee_cap as u64) < FEE_DENOMINATOR,
+            AmmError::InvalidFee
+        );
+        require!(decay_period != 0, AmmError::InvalidFee);
+
+        Ok(DynamicFee {
+            fee_floor,
+            fee_cap,
+            variable_fee_control,
+            decay_period,
+            volatility_accumulator: 0,
+            last_update: 0,
+        })
+    }
+
+    pub fn decayed_volatility(&self, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.last_update).clamp(0, self.decay_period as i64) as u128;
+        let remaining = self.decay_period as u128 - elapsed;
+        (self.volatility_accumulator as u128 * remaining / self.decay_period as u128) as u64
+    }
+
+    pub fn effective_fee(&self, now: i64) -> u16 {
+        let variable = self.decayed_volatility(now) as u128 * self.variable_fee_control as u128
+            / FEE_DENOMINATOR as u128;
+        (self.fee_floor as u128 + variable).min(self.fee_cap as u128) as u16
+    }
+
+    // Fee once a move from `price_before` to `price_after` is in the accumulator
+    pub fn fee_after_move(&self, price_before: u128, price_after: u128, now: i64) -> u16 {
+        let mut after = *self;
+        after.record_price_move(price_before, price_after, now);
+        after.effective_fee(now)
+    }
+
+    pub fn record_price_move(&mut self, price_before: u128, price_after: u128, now: i64) {
+        let move_bps = match price_before {
+            0 => 0,
+            _ => {
+                let bps = U256::from(price_before.abs_diff(price_after)) * U256::from(FEE_DENOMINATOR)
+                    / U256::from(price_before);
+                u64::try_from(bps).unwrap_or(u64::MAX)
+            }
+        };
+
+        self.volatility_accumulator = self.decayed_volatility(now).saturating_add(move_bps);
+        self.last_update = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dynamic_fee() -> DynamicFee {
+        // 0.05% to 1%, +1 bp of fee per 10 bps of volatility, decays over 10 minutes
+        DynamicFee::new(5, 100, 1_000, 600).unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_bounds() {
+        assert!(DynamicFee::new(100, 5, 1_000, 600).is_err());
+        assert!(DynamicFee::new(5, 10_000, 1_000, 600).is_err());
+        assert!(DynamicFee::new(5, 100, 1_000, 0).is_err());
+    }
+
+    #[test]
+    fn calm_pool_charges_the_floor() {
+        let mut fee = dynamic_fee();
+        assert_eq!(fee.effective_fee(0), 5);
+
+        fee.record_price_move(1_000_000, 1_000_000, 10);
+        assert_eq!(fee.effective_fee(10), 5);
+    }
+
+    #[test]
+    fn price_moves_raise_the_fee_up_to_the_cap() {
+        let mut fee = dynamic_fee();
+
+        // 2% move
+        fee.record_price_move(1_000_000, 980_000, 0);
+        assert_eq!(fee.volatility_accumulator, 200);
+        assert_eq!(fee.effective_fee(0), 25);
+
+        // another 2% move back up piles on
+        fee.record_price_move(980_000, 999_600, 0);
+        assert_eq!(fee.effective_fee(0), 45);
+
+        // a 50% crash hits the cap
+        fee.record_price_move(1_000_000, 500_000, 0);
+        assert_eq!(fee.effective_fee(0), 100);
+    }
+
+    #[test]
+    fn fee_after_move_includes_the_move() {
+        let fee = dynamic_fee();
+
+        // a calm pool quotes the floor, but a 2% move pays for itself
+        assert_eq!(fee.effective_fee(0), 5);
+        assert_eq!(fee.fee_after_move(1_000_000, 980_000, 0), 25);
+        assert_eq!(fee.volatility_accumulator, 0);
+    }
+
+    #[test]
+    fn volatility_decays_back_to_the_floor() {
+        let mut fee = dynamic_fee();
+        fee.record_price_move(1_000_000, 980_000, 1_000);
+
+        assert_eq!(fee.effective_fee(1_000), 25);
+        assert_eq!(fee.effective_fee(1_300), 15);
+        assert_eq!(fee.effective_fee(1_600), 5);
+        assert_eq!(fee.effective_fee(99_999), 5);
+
+        // half decayed, then a new move adds to what is left
+        fee.record_price_move(1_000_000, 990_000, 1_300);
+        assert_eq!(fee.volatility_accumulator, 200);
+    }
+}
@@ -0,0 +1,199 @@
+pub mod concentrated_liquidity;
+pub mod constant_product;
+pub mod dynamic_fee;
+pub mod stable_swap;
+pub mod tick_math;
+
+use anchor_lang::prelude::*;
+use constant_product_curve::{ConstantProduct, LiquidityPair, XYAmounts};
+
+use crate::{errors::AmmError, state::CurveType};
+
+pub const FEE_DENOMINATOR: u64 = 10_000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SwapAmounts {
+    pub deposit: u64,  // Amount the vault takes in, fee included
+    pub withdraw: u64, // Amount the vault pays out
+    pub fee: u64,      // Part of the deposit kept by the pool
+}
+
+// Grosses a net input up by the swap fee, rounding up. Returns (gross, fee).
+pub fn add_fee(amount: u64, fee: u16) -> Option<(u64, u64)> {
+    let denominator = FEE_DENOMINATOR.checked_sub(fee as u64).filter(|d| *d != 0)?;
+    let gross = (amount as u128 * FEE_DENOMINATOR as u128).div_ceil(denominator as u128);
+    let gross = u64::try_from(gross).ok()?;
+
+    Some((gross, gross - amount))
+}
+
+// Fee on a flash loan of `amount`, rounding up
+pub fn flash_loan_fee(amount: u64, fee: u16) -> Option<u64> {
+    let fee = (amount as u128 * fee as u128).div_ceil(FEE_DENOMINATOR as u128);
+    u64::try_from(fee).ok()
+}
+
+impl CurveType {
+    pub fn validate(&self, decimals_x: u8, decimals_y: u8) -> Result<()> {
+        if let CurveType::StableSwap { amp } = *self {
+            require!(
+                (stable_swap::MIN_AMP..=stable_swap::MAX_AMP).contains(&amp),
+                AmmError::InvalidCurve
+            );
+            require!(decimals_x == decimals_y, AmmError::InvalidCurve);
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap(
+        &self,
+        x: u64,   // Vault X balance
+        y: u64,   // Vault Y balance
+        l: u64,   // LP supply
+        fee: u16, // Swap fee in basis points
+        is_x: bool,
+        amount: u64,
+        min: u64,
+    ) -> Result<SwapAmounts> {
+        match *self {
+            CurveType::ConstantProduct => {
+                let mut curve = ConstantProduct::init(x, y, l, fee, None).map_err(AmmError::from)?;
+
+                let pair = match is_x {
+                    true => LiquidityPair::X,
+                    false => LiquidityPair::Y,
+                };
+
+                let result = curve.swap(pair, amount, min).map_err(AmmError::from)?;
+
+                Ok(SwapAmounts {
+                    deposit: result.deposit,
+                    withdraw: result.withdraw,
+                    fee: result.fee,
+                })
+            }
+            CurveType::StableSwap { amp } => {
+                let (from, to) = match is_x {
+                    true => (x, y),
+                    false => (y, x),
+                };
+
+                let result = stable_swap::swap(amp, fee, from, to, amount).ok_or(AmmError::Overflow)?;
+                require!(result.withdraw >= min, AmmError::SlippageExceeded);
+
+                Ok(result)
+            }
+        }
+    }
+
+    // Input needed, fee included, to take exactly `amount_out` of the other token
+    pub fn swap_exact_out(&self, x: u64, y: u64, fee: u16, is_x: bool, amount_out: u64) -> Result<SwapAmounts> {
+        let (from, to) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+        require!(amount_out < to, AmmError::InsufficientBalance);
+
+        let result = match *self {
+            CurveType::ConstantProduct => constant_product::swap_exact_out(fee, from, to, amount_out),
+            CurveType::StableSwap { amp } => stable_swap::swap_exact_out(amp, fee, from, to, amount_out),
+        };
+
+        Ok(result.ok_or(AmmError::Overflow)?)
+    }
+
+    // LP shares are a pro-rata claim on both reserves for every curve, since
+    // D scales linearly with the reserves just like sqrt(k) does.
+    pub fn deposit_amounts(&self, x: u64, y: u64, l: u64, amount: u64) -> Result<XYAmounts> {
+        match *self {
+            CurveType::ConstantProduct => {
+                Ok(ConstantProduct::xy_deposit_amounts_from_l(x, y, l, amount, 6).map_err(AmmError::from)?)
+            }
+            CurveType::StableSwap { .. } => Ok(XYAmounts {
+                x: pro_rata(x, amount, l, true)?,
+                y: pro_rata(y, amount, l, true)?,
+            }),
+        }
+    }
+
+    pub fn withdraw_amounts(&self, x: u64, y: u64, l: u64, amount: u64) -> Result<XYAmounts> {
+        match *self {
+            CurveType::ConstantProduct => {
+                Ok(ConstantProduct::xy_withdraw_amounts_from_l(x, y, l, amount, 6).map_err(AmmError::from)?)
+            }
+            CurveType::StableSwap { .. } => Ok(XYAmounts {
+                x: pro_rata(x, amount, l, false)?,
+                y: pro_rata(y, amount, l, false)?,
+            }),
+        }
+    }
+}
+
+// LP tokens owed for adding `add_x` and `add_y` to the reserves, limited by the
+// scarcer side so any excess is left to the pool
+pub fn lp_from_amounts(x: u64, y: u64, l: u64, add_x: u64, add_y: u64) -> Result<u64> {
+    require!(x != 0 && y != 0, AmmError::NoLiquidityInPool);
+
+    let lp_x = pro_rata(l, add_x, x, false)?;
+    let lp_y = pro_rata(l, add_y, y, false)?;
+
+    Ok(lp_x.min(lp_y))
+}
+
+// floor(sqrt(value)), by Newton's method
+pub fn integer_sqrt(value: u128) -> u64 {
+    if value < 2 {
+        return value as u64;
+    }
+
+    let mut x = value;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+
+    x as u64
+}
+
+// reserve * amount / supply, rounded in the pool's favour
+fn pro_rata(reserve: u64, amount: u64, supply: u64, round_up: bool) -> Result<u64> {
+    require!(supply != 0, AmmError::NoLiquidityInPool);
+
+    let product = (reserve as u128)
+        .checked_mul(amount as u128)
+        .ok_or(AmmError::Overflow)?;
+    let mut share = product / supply as u128;
+    if round_up && product % supply as u128 != 0 {
+        share += 1;
+    }
+
+    u64::try_from(share).map_err(|_| AmmError::Overflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_sqrt_rounds_down() {
+        assert_eq!(integer_sqrt(0), 0);
+        assert_eq!(integer_sqrt(1), 1);
+        assert_eq!(integer_sqrt(3), 1);
+        assert_eq!(integer_sqrt(4), 2);
+        assert_eq!(integer_sqrt(1_000_000 * 4_000_000), 2_000_000);
+        assert_eq!(integer_sqrt(u64::MAX as u128 * u64::MAX as u128), u64::MAX);
+        assert_eq!(integer_sqrt(u64::MAX as u128 * u64::MAX as u128 - 1), u64::MAX - 1);
+    }
+
+    #[test]
+    fn flash_loan_fee_rounds_up() {
+        assert_eq!(flash_loan_fee(0, 30), Some(0));
+        assert_eq!(flash_loan_fee(1, 30), Some(1));
+        assert_eq!(flash_loan_fee(10_000, 30), Some(30));
+        assert_eq!(flash_loan_fee(10_001, 30), Some(31));
+        assert_eq!(flash_loan_fee(u64::MAX, 10_000), Some(u64::MAX));
+    }
+}
@@ -0,0 +1,229 @@
+use ethnum::U256;
+
+use super::{add_fee, SwapAmounts, FEE_DENOMINATOR};
+
+// StableSwap invariant for two coins (https://curve.fi/files/stableswap-paper.pdf):
+//
+//     A * n^n * (x + y) + D = A * n^n * D + D^(n+1) / (n^n * x * y)
+//
+// Reserves are compared as raw amounts, so both mints must use the same decimals.
+
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+
+const N_COINS: u64 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+// A * n^n
+fn amp_times_coins(amp: u64) -> U256 {
+    U256::from(amp) * U256::from(N_COINS * N_COINS)
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b { a - b } else { b - a }
+}
+
+// Solves the invariant for D with Newton's method
+pub fn compute_d(amp: u64, x: u64, y: u64) -> Option<u128> {
+    let sum = U256::from(x) + U256::from(y);
+    if sum == 0 {
+        return Some(0);
+    }
+    if x == 0 || y == 0 {
+        return None;
+    }
+
+    let ann = amp_times_coins(amp);
+    let n = U256::from(N_COINS);
+    let mut d = sum;
+
+    for _ in 0..MAX_ITERATIONS {
+        // D^(n+1) / (n^n * x * y), divided once so rounding cannot make the loop oscillate
+        let d_p = d.checked_mul(d)?.checked_mul(d)? / (U256::from(x) * U256::from(y) * n * n);
+
+        let previous = d;
+        let numerator = ann
+            .checked_mul(sum)?
+            .checked_add(d_p.checked_mul(n)?)?
+            .checked_mul(d)?;
+        let denominator = (ann - 1)
+            .checked_mul(d)?
+            .checked_add((n + 1).checked_mul(d_p)?)?;
+        d = numerator / denominator;
+
+        if abs_diff(d, previous) <= 1 {
+            return u128::try_from(d).ok();
+        }
+    }
+
+    None
+}
+
+// Solves the invariant for the other reserve once one side holds `x`
+pub fn compute_y(amp: u64, x: u64, d: u128) -> Option<u64> {
+    if x == 0 {
+        return None;
+    }
+
+    let ann = amp_times_coins(amp);
+    let n = U256::from(N_COINS);
+    let d = U256::from(d);
+
+    // y^2 + (b - D) * y = c
+    let c = d.checked_mul(d)?.checked_mul(d)? / (U256::from(x) * ann * n * n);
+    let b = U256::from(x) + d / ann;
+    let mut y = d;
+
+    for _ in 0..MAX_ITERATIONS {
+        let previous = y;
+        let numerator = y.checked_mul(y)?.checked_add(c)?;
+        let denominator = (y * 2 + b).checked_sub(d)?;
+        y = numerator / denominator;
+
+        if abs_diff(y, previous) <= 1 {
+            return u64::try_from(y).ok();
+        }
+    }
+
+    None
+}
+
+// Prices a swap of `amount` into the `from` reserve. The fee stays in the pool and
+// the payout is rounded down by one unit so the invariant never shrinks.
+pub fn swap(amp: u64, fee: u16, from: u64, to: u64, amount: u64) -> Option<SwapAmounts> {
+    let fee_amount = (amount as u128)
+        .checked_mul(fee as u128)?
+        .checked_div(FEE_DENOMINATOR as u128)? as u64;
+    let amount_in = amount.checked_sub(fee_amount)?;
+
+    let d = compute_d(amp, from, to)?;
+    let new_to = compute_y(amp, from.checked_add(amount_in)?, d)?;
+    let withdraw = to.checked_sub(new_to)?.saturating_sub(1);
+
+    Some(SwapAmounts {
+        deposit: amount,
+        withdraw,
+        fee: fee_amount,
+    })
+}
+
+// Prices taking exactly `amount_out` from the `to` reserve. The required input is
+// rounded up by one unit and grossed up by the fee.
+pub fn swap_exact_out(amp: u64, fee: u16, from: u64, to: u64, amount_out: u64) -> Option<SwapAmounts> {
+    let new_to = to.checked_sub(amount_out)?;
+    if new_to == 0 {
+        return None;
+    }
+
+    let d = compute_d(amp, from, to)?;
+    let new_from = compute_y(amp, new_to, d)?;
+    let amount_in = new_from.checked_sub(from)?.checked_add(1)?;
+    let (deposit, fee_amount) = add_fee(amount_in, fee)?;
+
+    Some(SwapAmounts {
+        deposit,
+        withdraw: amount_out,
+        fee: fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMP: u64 = 100;
+
+    #[test]
+    fn balanced_pool_d_is_the_sum_of_reserves() {
+        assert_eq!(compute_d(AMP, 1_000_000, 1_000_000), Some(2_000_000));
+        assert_eq!(compute_d(AMP, 0, 0), Some(0));
+        assert_eq!(compute_d(AMP, 0, 1_000_000), None);
+    }
+
+    #[test]
+    fn compute_y_recovers_the_other_reserve() {
+        let d = compute_d(AMP, 1_500_000, 700_000).unwrap();
+        let y = compute_y(AMP, 1_500_000, d).unwrap();
+
+        assert!(y.abs_diff(700_000) <= 1);
+    }
+
+    #[test]
+    fn swap_near_peg_has_low_slippage() {
+        let stable = swap(AMP, 0, 1_000_000_000, 1_000_000_000, 10_000_000).unwrap();
+        // constant product would pay out 9_900_990
+        assert!(stable.withdraw > 9_990_000);
+        assert!(stable.withdraw < 10_000_000);
+    }
+
+    #[test]
+    fn swap_charges_fee_on_the_input() {
+        let without_fee = swap(AMP, 0, 1_000_000, 1_000_000, 10_000).unwrap();
+        let with_fee = swap(AMP, 30, 1_000_000, 1_000_000, 10_000).unwrap();
+
+        assert_eq!(with_fee.deposit, 10_000);
+        assert_eq!(with_fee.fee, 30);
+        assert!(with_fee.withdraw < without_fee.withdraw);
+    }
+
+    #[test]
+    fn swap_never_decreases_invariant() {
+        for amp in [MIN_AMP, AMP, MAX_AMP] {
+            for (x, y, amount) in [
+                (1_000_000, 1_000_000, 1),
+                (1_000_000, 3_000_000, 250_000),
+                (50_000_000_000, 1_000_000, 9_000_000_000),
+            ] {
+                let before = compute_d(amp, x, y).unwrap();
+                let result = swap(amp, 0, x, y, amount).unwrap();
+                let after = compute_d(amp, x + result.deposit, y - result.withdraw).unwrap();
+
+                assert!(after >= before, "amp {amp}: {before} -> {after}");
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_never_decreases_invariant() {
+        for amp in [MIN_AMP, AMP, MAX_AMP] {
+            for (x, y, amount_out) in [
+                (1_000_000, 1_000_000, 1),
+                (1_000_000, 3_000_000, 250_000),
+                (50_000_000_000, 1_000_000, 999_000),
+            ] {
+                let before = compute_d(amp, x, y).unwrap();
+                let result = swap_exact_out(amp, 0, x, y, amount_out).unwrap();
+                let after = compute_d(amp, x + result.deposit, y - amount_out).unwrap();
+
+                assert!(after >= before, "amp {amp}: {before} -> {after}");
+            }
+        }
+    }
+
+    #[test]
+    fn exact_out_costs_at_least_exact_in() {
+        let exact_out = swap_exact_out(AMP, 30, 1_000_000, 1_000_000, 10_000).unwrap();
+        let exact_in = swap(AMP, 30, 1_000_000, 1_000_000, exact_out.deposit).unwrap();
+
+        assert!(exact_in.withdraw >= 10_000);
+        assert!(exact_out.fee >= 30);
+    }
+
+    #[test]
+    fn exact_out_cannot_empty_the_reserve() {
+        assert_eq!(swap_exact_out(AMP, 0, 1_000_000, 1_000_000, 1_000_000), None);
+    }
+
+    #[test]
+    fn swap_cannot_drain_the_pool() {
+        let result = swap(AMP, 0, 1_000_000, 1_000_000, u64::MAX / 2).unwrap();
+        assert!(result.withdraw < 1_000_000);
+    }
+
+    #[test]
+    fn large_reserves_do_not_overflow() {
+        let reserve = u64::MAX / 4;
+        let result = swap(MAX_AMP, 30, reserve, reserve, reserve / 10);
+        assert!(result.is_some());
+    }
+}
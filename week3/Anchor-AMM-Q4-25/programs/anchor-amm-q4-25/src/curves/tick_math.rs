@@ -0,0 +1,167 @@
+use ethnum::U256;
+
+// Ticks index prices: the price of X in Y at tick i is 1.0001^i. Pools keep the
+// square root of the price as a Q64.64 fixed point number.
+
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+
+pub const MIN_SQRT_PRICE: u128 = 4_295_048_016;
+pub const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_061;
+
+// floor(2^128 / sqrt(1.0001)^(2^i)) for i = 0..=18, 2^19 is past MAX_TICK
+const INVERSE_SQRT_POWERS: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e2139,
+    0xfff2e50f5f656932ef12357cf3c7fdcb,
+    0xffe5caca7e10e4e61c3624eaa0941ccf,
+    0xffcb9843d60f6159c9db58835c926643,
+    0xff973b41fa98c081472e6896dfb254bf,
+    0xff2ea16466c96a3843ec78b326b52860,
+    0xfe5dee046a99a2a811c461f1969c3052,
+    0xfcbe86c7900a88aedcffc83b479aa3a3,
+    0xf987a7253ac413176f2b074cf7815e53,
+    0xf3392b0822b70005940c7a398e4b70f2,
+    0xe7159475a2c29b7443b29c7fa6e889d8,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e4,
+    0x70d869a156d2a1b890bb3df62baf32f6,
+    0x31be135f97d08fd981231505542fcfa5,
+    0x09aa508b5b7a84e1c677de54f3e99bc8,
+    0x005d6af8dedb81196699c329225ee604,
+    0x00002216e584f5fa1ea926041bedfe97,
+];
+
+// sqrt(1.0001^tick) as Q64.64. Works on 1 / sqrt price in Q128 for |tick| and
+// inverts it for positive ticks.
+pub fn sqrt_price_at_tick(tick: i32) -> Option<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return None;
+    }
+
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = U256::ONE << 128;
+    for (bit, power) in INVERSE_SQRT_POWERS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * U256::from(*power)) >> 128;
+        }
+    }
+
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    u128::try_from(ratio >> 64).ok()
+}
+
+// Concentrated-liquidity fee tiers in basis points and the tick spacing each
+// one is created with. Wider spacing for higher tiers, which suit volatile
+// pairs whose positions cover wider ranges.
+pub const FEE_TIERS: [(u16, u16); 4] = [(1, 1), (5, 10), (30, 60), (100, 200)];
+
+pub fn tick_spacing_for(fee: u16) -> Option<u16> {
+    FEE_TIERS
+        .iter()
+        .find(|(tier, _)| *tier == fee)
+        .map(|(_, spacing)| *spacing)
+}
+
+// Q64.64 prices of X in Y and Y in X at a Q64.64 sqrt price, saturating at u128::MAX
+pub fn prices_at_sqrt_price(sqrt_price: u128) -> (u128, u128) {
+    let squared = U256::from(sqrt_price) * U256::from(sqrt_price);
+    if squared == 0 {
+        return (0, 0);
+    }
+
+    let price_x = u128::try_from(squared >> 64).unwrap_or(u128::MAX);
+    let price_y = u128::try_from((U256::ONE << 192) / squared).unwrap_or(u128::MAX);
+    (price_x, price_y)
+}
+
+// Greatest tick whose sqrt price is at most `sqrt_price`
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> Option<i32> {
+    if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+        return None;
+    }
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let mid = low + (high - low + 1) / 2;
+        match sqrt_price_at_tick(mid)? <= sqrt_price {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+
+    Some(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << 64;
+
+    #[test]
+    fn fee_tiers_map_to_fixed_spacings() {
+        assert_eq!(tick_spacing_for(30), Some(60));
+        assert_eq!(tick_spacing_for(100), Some(200));
+        assert_eq!(tick_spacing_for(0), None);
+        assert_eq!(tick_spacing_for(25), None);
+    }
+
+    #[test]
+    fn tick_zero_is_price_one() {
+        assert_eq!(sqrt_price_at_tick(0), Some(ONE));
+        assert_eq!(tick_at_sqrt_price(ONE), Some(0));
+    }
+
+    #[test]
+    fn prices_square_the_sqrt_price() {
+        assert_eq!(prices_at_sqrt_price(ONE), (ONE, ONE));
+        assert_eq!(prices_at_sqrt_price(2 * ONE), (4 * ONE, ONE / 4));
+        assert_eq!(prices_at_sqrt_price(ONE / 2), (ONE / 4, 4 * ONE));
+    }
+
+    #[test]
+    fn bounds_match_the_extreme_ticks() {
+        assert_eq!(sqrt_price_at_tick(MIN_TICK), Some(MIN_SQRT_PRICE));
+        assert_eq!(sqrt_price_at_tick(MAX_TICK), Some(MAX_SQRT_PRICE));
+        assert_eq!(sqrt_price_at_tick(MIN_TICK - 1), None);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK + 1), None);
+        assert_eq!(tick_at_sqrt_price(MIN_SQRT_PRICE - 1), None);
+        assert_eq!(tick_at_sqrt_price(MAX_SQRT_PRICE + 1), None);
+    }
+
+    #[test]
+    fn sqrt_price_matches_floating_point() {
+        for tick in [-400_000, -50_000, -1_000, -1, 1, 10, 6_932, 100_000, 400_000] {
+            let expected = 1.0001f64.powf(tick as f64 / 2.0);
+            let actual = sqrt_price_at_tick(tick).unwrap() as f64 / ONE as f64;
+
+            assert!((actual / expected - 1.0).abs() < 1e-9, "tick {tick}: {actual} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn sqrt_price_is_strictly_increasing() {
+        let mut previous = sqrt_price_at_tick(MIN_TICK).unwrap();
+        for tick in (MIN_TICK + 1..=MAX_TICK).step_by(997).chain(MAX_TICK - 5..=MAX_TICK) {
+            let current = sqrt_price_at_tick(tick).unwrap();
+            assert!(current > previous, "tick {tick}");
+            previous = current;
+        }
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_inverts_sqrt_price_at_tick() {
+        for tick in [MIN_TICK, -300_001, -64, -1, 0, 1, 63, 64, 250_000, MAX_TICK - 1, MAX_TICK] {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+
+            assert_eq!(tick_at_sqrt_price(sqrt_price), Some(tick));
+            if tick < MAX_TICK {
+                assert_eq!(tick_at_sqrt_price(sqrt_price_at_tick(tick + 1).unwrap() - 1), Some(tick));
+            }
+        }
+    }
+}
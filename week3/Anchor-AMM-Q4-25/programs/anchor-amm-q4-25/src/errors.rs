@@ -39,6 +39,30 @@ pub enum AmmError {
     InsufficientBalance,
     #[msg("Zero balance.")]
     ZeroBalance,
+    #[msg("Invalid curve parameters.")]
+    InvalidCurve,
+    #[msg("Mint uses an unsupported token extension.")]
+    UnsupportedMintExtension,
+    #[msg("Invalid swap route.")]
+    InvalidRoute,
+    #[msg("Not enough price history for the TWAP window.")]
+    InsufficientObservations,
+    #[msg("A flash loan is outstanding.")]
+    FlashLoanActive,
+    #[msg("Flash loan is not repaid in the same transaction.")]
+    FlashLoanNotRepaid,
+    #[msg("No flash loan to repay.")]
+    NoFlashLoan,
+    #[msg("Pool mints must be different.")]
+    IdenticalMints,
+    #[msg("Pool mints must be in canonical order, mint_x before mint_y.")]
+    InvalidMintOrder,
+    #[msg("Invalid tick range.")]
+    InvalidTickRange,
+    #[msg("Sqrt price is out of range.")]
+    InvalidSqrtPrice,
+    #[msg("Invalid tick array.")]
+    InvalidTickArray,
 }
 
 impl From<CurveError> for AmmError {
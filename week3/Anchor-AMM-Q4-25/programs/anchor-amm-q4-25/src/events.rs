@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+
+use crate::state::DynamicFee;
+
+#[event]
+pub struct PoolLockUpdated {
+    pub config: Pubkey,
+    pub locked: bool,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub config: Pubkey,
+    pub old_fee: u16,
+    pub new_fee: u16,
+}
+
+#[event]
+pub struct DynamicFeeUpdated {
+    pub config: Pubkey,
+    pub dynamic_fee: Option<DynamicFee>,
+}
+
+#[event]
+pub struct SwapExecuted {
+    pub config: Pubkey, // Pool config, or ClPool account
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee_amount: u64,
+    pub effective_fee: u16, // Fee rate charged, in basis points
+}
+
+#[event]
+pub struct ProtocolFeeUpdated {
+    pub config: Pubkey,
+    pub old_protocol_fee: u16,
+    pub new_protocol_fee: u16,
+}
+
+#[event]
+pub struct ProtocolFeesCollected {
+    pub config: Pubkey,
+    pub treasury_x: Pubkey,
+    pub treasury_y: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
+
+#[event]
+pub struct AuthorityTransferStarted {
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub config: Pubkey,
+    pub previous_authority: Option<Pubkey>,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct FlashLoanRepaid {
+    pub config: Pubkey,
+    pub amount_x: u64,
+    pub amount_y: u64,
+}
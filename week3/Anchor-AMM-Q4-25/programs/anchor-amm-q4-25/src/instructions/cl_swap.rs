@@ -0,0 +1,248 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::{
+    curves::{
+        concentrated_liquidity::compute_swap_step,
+        tick_math::{
+            prices_at_sqrt_price, sqrt_price_at_tick, tick_at_sqrt_price, MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE,
+            MIN_TICK,
+        },
+    },
+    errors::AmmError,
+    events::SwapExecuted,
+    state::{load_tick_array, ClPool, ObservationState},
+    token_extensions::amount_with_transfer_fee,
+};
+
+// remaining_accounts holds every tick array the price moves through, starting
+// with the one holding the current tick
+#[derive(Accounts)]
+pub struct ClSwap<'info> {
+    pub user: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"cl_pool", mint_x.key().as_ref(), mint_y.key().as_ref(), pool.fee.to_le_bytes().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, ClPool>,
+    #[account(
+        mut,
+        seeds = [b"observation", pool.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::authority = user,
+        token::token_program = token_program_x,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::authority = user,
+        token::token_program = token_program_y,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ClSwap<'info> {
+    // Swaps up to `amount_in`, stopping early only at the price bounds. The input
+    // actually used is what the user pays.
+    pub fn swap(
+        &mut self,
+        tick_arrays: &'info [AccountInfo<'info>],
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        require!(!self.pool.locked, AmmError::PoolLocked);
+        require!(amount_in > 0, AmmError::InvalidAmount);
+
+        let (price_x, price_y) = prices_at_sqrt_price(self.pool.sqrt_price);
+        self.observation.load_mut()?.update_prices(price_x, price_y, Clock::get()?.unix_timestamp);
+
+        let tick_arrays = tick_arrays
+            .iter()
+            .map(|account| load_tick_array(account, &self.pool.key()))
+            .collect::<Result<Vec<_>>>()?;
+
+        // X in moves the price of X down
+        let down = is_x;
+        let sqrt_price_limit = match down {
+            true => MIN_SQRT_PRICE,
+            false => MAX_SQRT_PRICE,
+        };
+
+        let pool = &mut self.pool;
+        let spacing = pool.tick_spacing;
+        let mut remaining = amount_in;
+        let mut amount_out: u64 = 0;
+        let mut fee_amount: u64 = 0;
+
+        while remaining > 0 && pool.sqrt_price != sqrt_price_limit {
+            let mut tick_array = None;
+            for candidate in &tick_arrays {
+                if candidate.load()?.contains(pool.tick_current, spacing) {
+                    tick_array = Some(candidate);
+                    break;
+                }
+            }
+            let tick_array = tick_array.ok_or(AmmError::InvalidTickArray)?;
+
+            let (next_tick, initialized) = tick_array.load()?.next_initialized_tick(pool.tick_current, spacing, down);
+            let next_tick = next_tick.clamp(MIN_TICK, MAX_TICK);
+            let next_sqrt_price = sqrt_price_at_tick(next_tick).ok_or(AmmError::InvalidSqrtPrice)?;
+
+            let step = compute_swap_step(pool.sqrt_price, next_sqrt_price, pool.liquidity, remaining, pool.fee)
+                .ok_or(AmmError::Overflow)?;
+
+            remaining = step
+                .amount_in
+                .checked_add(step.fee)
+                .and_then(|used| remaining.checked_sub(used))
+                .ok_or(AmmError::Underflow)?;
+            amount_out = amount_out.checked_add(step.amount_out).ok_or(AmmError::Overflow)?;
+            fee_amount = fee_amount.checked_add(step.fee).ok_or(AmmError::Overflow)?;
+
+            // fees are shared by the liquidity in range
+            if pool.liquidity != 0 {
+                let growth = ((step.fee as u128) << 64) / pool.liquidity;
+                match is_x {
+                    true => pool.fee_growth_global_x = pool.fee_growth_global_x.wrapping_add(growth),
+                    false => pool.fee_growth_global_y = pool.fee_growth_global_y.wrapping_add(growth),
+                }
+            }
+
+            pool.sqrt_price = step.sqrt_price;
+            if step.sqrt_price == next_sqrt_price {
+                if initialized {
+                    let liquidity_net = tick_array
+                        .load_mut()?
+                        .tick_mut(next_tick, spacing)?
+                        .cross(pool.fee_growth_global_x, pool.fee_growth_global_y);
+                    let liquidity_net = match down {
+                        true => liquidity_net.checked_neg().ok_or(AmmError::Overflow)?,
+                        false => liquidity_net,
+                    };
+                    pool.liquidity = pool
+                        .liquidity
+                        .checked_add_signed(liquidity_net)
+                        .ok_or(AmmError::Overflow)?;
+                }
+                pool.tick_current = match down {
+                    true => next_tick - 1,
+                    false => next_tick,
+                };
+            } else {
+                pool.tick_current = tick_at_sqrt_price(pool.sqrt_price).ok_or(AmmError::InvalidSqrtPrice)?;
+            }
+        }
+
+        // amount the vault has to receive, after any transfer fee
+        let used = amount_in - remaining;
+        let mint_in = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        let send = amount_with_transfer_fee(mint_in, used)?;
+
+        let received = self.deposit_tokens(is_x, send)?;
+        require!(received >= used, AmmError::SlippageExceeded);
+
+        // slippage is checked on what arrived, after any transfer fee
+        let paid_out = self.withdraw_tokens(!is_x, amount_out)?;
+        require!(paid_out >= min_amount_out, AmmError::SlippageExceeded);
+
+        emit!(SwapExecuted {
+            config: self.pool.key(),
+            is_x,
+            amount_in: used,
+            amount_out,
+            fee_amount,
+            effective_fee: self.pool.fee,
+        });
+
+        Ok(())
+    }
+
+    // Returns the amount the vault received
+    fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.user_x, &mut self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.user_y, &mut self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program.to_account_info(), cpi_accounts);
+
+        let before = to.amount;
+        transfer_checked(ctx, amount, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
+    }
+
+    // Returns the amount the user received
+    fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &mut self.user_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &mut self.user_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.pool.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"cl_pool",
+            self.pool.mint_x.as_ref(),
+            self.pool.mint_y.as_ref(),
+            &self.pool.fee.to_le_bytes(),
+            &[self.pool.bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            cpi_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        let before = to.amount;
+        transfer_checked(ctx, amount, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
+    }
+}
@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{events::ProtocolFeesCollected, state::Config};
+
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    pub authority: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::token_program = token_program_x,
+    )]
+    pub treasury_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::token_program = token_program_y,
+    )]
+    pub treasury_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> CollectProtocolFees<'info> {
+    pub fn collect_protocol_fees(&mut self) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+
+        // fails while a flash loan is outstanding or the vaults do not cover
+        // the accrued fees, the fees must come out of the LPs' reserves intact
+        self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        let amount_x = self.config.protocol_fees_x;
+        let amount_y = self.config.protocol_fees_y;
+
+        self.config.protocol_fees_x = 0;
+        self.config.protocol_fees_y = 0;
+
+        // pay token x
+        self.withdraw_fees(true, amount_x)?;
+        // pay token y
+        self.withdraw_fees(false, amount_y)?;
+
+        emit!(ProtocolFeesCollected {
+            config: self.config.key(),
+            treasury_x: self.treasury_x.key(),
+            treasury_y: self.treasury_y.key(),
+            amount_x,
+            amount_y,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_fees(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &self.treasury_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &self.treasury_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            cpi_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+}
@@ -1,63 +1,98 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token::Token,
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::{errors::AmmError, state::Config};
+use crate::{
+    curves::{integer_sqrt, lp_from_amounts},
+    errors::AmmError,
+    events::SwapExecuted,
+    state::{Config, ObservationState, MINIMUM_LIQUIDITY},
+    token_extensions::amount_with_transfer_fee,
+};
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
         bump = config.config_bump,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"observation", config.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program_x,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program_y,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: never signs, it only owns the locked minimum liquidity
+    #[account(
+        seeds = [b"dead", config.key().as_ref()],
+        bump = config.dead_bump,
+    )]
+    pub dead: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = dead,
+        associated_token::token_program = token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub locked_lp: InterfaceAccount<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
@@ -65,72 +100,175 @@ pub struct Deposit<'info> {
 impl<'info> Deposit<'info> {
     pub fn deposit(
         &mut self,
-        amount: u64, // Amount of LP tokens that the user wants to "claim"
+        amount: u64, // Amount of LP tokens that the user wants to "claim", a minimum on the first deposit
         max_x: u64,  // Maximum amount of token X that the user is willing to deposit
         max_y: u64,  // Maximum amount of token Y that the user is willing to deposit
     ) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
         require!(amount != 0, AmmError::InvalidAmount);
 
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
-            true => (max_x, max_y),
-            false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
-                    amount,
-                    6,
-                )
-                .unwrap();
-                (amounts.x, amounts.y) 
-            }
-        };
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.observation.load_mut()?.update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
 
-        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
+        if self.mint_lp.supply == 0 {
+            return self.first_deposit(amount, max_x, max_y);
+        }
+
+        // amounts the vaults have to receive, after any transfer fee
+        let amounts = self.config.curve.deposit_amounts(
+            reserve_x,
+            reserve_y,
+            self.mint_lp.supply,
+            amount,
+        )?;
+        let (x, y) = (amounts.x, amounts.y);
+
+        let send_x = amount_with_transfer_fee(&self.mint_x, x)?;
+        let send_y = amount_with_transfer_fee(&self.mint_y, y)?;
+
+        require!(send_x <= max_x && send_y <= max_y, AmmError::SlippageExceeded);
+
+        let vault_x_before = self.vault_x.amount;
+        let vault_y_before = self.vault_y.amount;
 
         // deposit token x
-        self.deposit_tokens(true, x)?;
+        self.deposit_tokens(true, send_x)?;
         // deposit token y
-        self.deposit_tokens(false, y)?;
+        self.deposit_tokens(false, send_y)?;
+
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        require!(
+            self.vault_x.amount - vault_x_before >= x && self.vault_y.amount - vault_y_before >= y,
+            AmmError::InsufficientBalance
+        );
+
         // mint lp tokens
-        self.mint_lp_tokens(amount)
+        self.mint_lp_tokens(&self.user_lp, amount)
     }
 
-    pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+    // The first deposit sets the price and mints sqrt(x * y) LP tokens for what the
+    // vaults received. MINIMUM_LIQUIDITY of them are locked forever, so the pool can
+    // never be emptied and a donation cannot inflate the value of a single LP token.
+    fn first_deposit(&mut self, min_lp: u64, amount_x: u64, amount_y: u64) -> Result<()> {
+        require!(amount_x != 0 && amount_y != 0, AmmError::InvalidAmount);
+
+        let vault_x_before = self.vault_x.amount;
+        let vault_y_before = self.vault_y.amount;
+
+        self.deposit_tokens(true, amount_x)?;
+        self.deposit_tokens(false, amount_y)?;
+
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        let received_x = self.vault_x.amount - vault_x_before;
+        let received_y = self.vault_y.amount - vault_y_before;
+
+        let liquidity = integer_sqrt(received_x as u128 * received_y as u128);
+        require!(liquidity > MINIMUM_LIQUIDITY, AmmError::LiquidityLessThanMinimum);
+
+        let lp = liquidity - MINIMUM_LIQUIDITY;
+        require!(lp >= min_lp, AmmError::SlippageExceeded);
+
+        self.mint_lp_tokens(&self.locked_lp, MINIMUM_LIQUIDITY)?;
+        self.mint_lp_tokens(&self.user_lp, lp)
+    }
+
+    // Deposits one token only. Half of it is swapped through the curve for the
+    // other token, paying the swap fee, and the rest is added as liquidity.
+    pub fn deposit_single(&mut self, is_x: bool, amount_in: u64, min_lp: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(self.mint_lp.supply != 0, AmmError::NoLiquidityInPool);
+
+        let now = Clock::get()?.unix_timestamp;
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.observation.load_mut()?.update(x, y, now);
+
+        let vault = match is_x {
+            true => &self.vault_x,
+            false => &self.vault_y,
+        };
+        let vault_before = vault.amount;
+
+        self.deposit_tokens(is_x, amount_in)?;
+
+        let vault = match is_x {
+            true => &mut self.vault_x,
+            false => &mut self.vault_y,
+        };
+        vault.reload()?;
+        let received = vault.amount - vault_before;
+
+        let swap_in = received / 2;
+        require!(swap_in != 0, AmmError::InvalidAmount);
+
+        let (curve, supply) = (self.config.curve, self.mint_lp.supply);
+        let (swaps, fee) = self.config.quote_swap(x, y, is_x, now, |fee| {
+            curve.swap(x, y, supply, fee, is_x, swap_in, 0)
+        })?;
+        let protocol_fee = self.config.accrue_protocol_fee(is_x, swaps.fee)?;
+        self.config.record_swap(x, y, is_x, swap_in, swaps.withdraw, now);
+
+        // half of the deposit is swapped inside the pool
+        emit!(SwapExecuted {
+            config: self.config.key(),
+            is_x,
+            amount_in: swap_in,
+            amount_out: swaps.withdraw,
+            fee_amount: swaps.fee,
+            effective_fee: fee,
+        });
+
+        // reserves once the implied swap has settled
         let (from, to) = match is_x {
+            true => (x, y),
+            false => (y, x),
+        };
+        let from = from
+            .checked_add(swap_in - protocol_fee)
+            .ok_or(AmmError::Overflow)?;
+        let to = to.checked_sub(swaps.withdraw).ok_or(AmmError::Underflow)?;
+
+        let lp = lp_from_amounts(from, to, self.mint_lp.supply, received - swap_in, swaps.withdraw)?;
+        require!(lp != 0 && lp >= min_lp, AmmError::SlippageExceeded);
+
+        self.mint_lp_tokens(&self.user_lp, lp)
+    }
+
+    pub fn deposit_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, cpi_program) = match is_x {
             true => (
                 self.user_x.to_account_info(),
                 self.vault_x.to_account_info(),
+                &self.mint_x,
+                self.token_program_x.to_account_info(),
             ),
             false => (
                 self.user_y.to_account_info(),
                 self.vault_y.to_account_info(),
+                &self.mint_y,
+                self.token_program_y.to_account_info(),
             ),
         };
 
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint: mint.to_account_info(),
             to,
             authority: self.user.to_account_info(),
         };
 
         let ctx = CpiContext::new(cpi_program, cpi_accounts);
 
-        transfer(ctx, amount)
+        transfer_checked(ctx, amount, mint.decimals)
     }
 
-    pub fn mint_lp_tokens(&self, amount: u64) -> Result<()> {
+    pub fn mint_lp_tokens(&self, to: &InterfaceAccount<'info, TokenAccount>, amount: u64) -> Result<()> {
         let cpi_program = self.token_program.to_account_info();
 
         let cpi_accounts = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.user_lp.to_account_info(),
+            to: to.to_account_info(),
             authority: self.config.to_account_info(),
         };
 
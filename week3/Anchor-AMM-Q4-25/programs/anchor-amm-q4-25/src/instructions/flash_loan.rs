@@ -0,0 +1,195 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    Discriminator,
+};
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::{
+    curves::flash_loan_fee,
+    errors::AmmError,
+    events::FlashLoanRepaid,
+    state::Config,
+    token_extensions::amount_with_transfer_fee,
+};
+
+// Position of `config` in FlashLoan, checked on the flash_repay instruction
+const CONFIG_ACCOUNT_INDEX: usize = 3;
+
+// Accounts shared by flash_loan and flash_repay
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::authority = user,
+        token::token_program = token_program_x,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::authority = user,
+        token::token_program = token_program_y,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        address = anchor_lang::solana_program::sysvar::instructions::ID
+    )]
+    /// CHECK: address is checked against the instructions sysvar
+    pub instruction_sysvar: UncheckedAccount<'info>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FlashLoan<'info> {
+    pub fn flash_loan(&mut self, amount_x: u64, amount_y: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount_x != 0 || amount_y != 0, AmmError::InvalidAmount);
+
+        // fails while another loan is outstanding
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        require!(amount_x <= reserve_x && amount_y <= reserve_y, AmmError::InsufficientBalance);
+
+        self.verify_repay_instruction()?;
+
+        // the fee stays in the vaults, so it accrues to LPs
+        let fee = self.config.swap_fee(Clock::get()?.unix_timestamp);
+        let fee_x = flash_loan_fee(amount_x, fee).ok_or(AmmError::Overflow)?;
+        let fee_y = flash_loan_fee(amount_y, fee).ok_or(AmmError::Overflow)?;
+        self.config.flash_loan_x = amount_x.checked_add(fee_x).ok_or(AmmError::Overflow)?;
+        self.config.flash_loan_y = amount_y.checked_add(fee_y).ok_or(AmmError::Overflow)?;
+
+        self.lend_tokens(true, amount_x)?;
+        self.lend_tokens(false, amount_y)
+    }
+
+    pub fn flash_repay(&mut self) -> Result<()> {
+        let amount_x = self.config.flash_loan_x;
+        let amount_y = self.config.flash_loan_y;
+        require!(amount_x != 0 || amount_y != 0, AmmError::NoFlashLoan);
+
+        let received_x = self.repay_tokens(true, amount_x)?;
+        let received_y = self.repay_tokens(false, amount_y)?;
+        require!(received_x >= amount_x && received_y >= amount_y, AmmError::FlashLoanNotRepaid);
+
+        self.config.flash_loan_x = 0;
+        self.config.flash_loan_y = 0;
+
+        emit!(FlashLoanRepaid {
+            config: self.config.key(),
+            amount_x,
+            amount_y,
+        });
+
+        Ok(())
+    }
+
+    // A top-level flash_repay for this pool has to follow the current instruction,
+    // otherwise the transaction could end with the loan outstanding
+    fn verify_repay_instruction(&self) -> Result<()> {
+        let sysvar = self.instruction_sysvar.to_account_info();
+        let current = load_current_index_checked(&sysvar)?;
+
+        let mut index = current as usize + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &sysvar) {
+            if ix.program_id == crate::ID
+                && ix.data.starts_with(crate::instruction::FlashRepay::DISCRIMINATOR)
+                && ix
+                    .accounts
+                    .get(CONFIG_ACCOUNT_INDEX)
+                    .is_some_and(|meta| meta.pubkey == self.config.key())
+            {
+                return Ok(());
+            }
+            index += 1;
+        }
+
+        err!(AmmError::FlashLoanNotRepaid)
+    }
+
+    fn lend_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &self.user_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &self.user_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            cpi_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, mint.decimals)
+    }
+
+    // Returns the amount the vault received
+    fn repay_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.user_x, &mut self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.user_y, &mut self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program.to_account_info(), cpi_accounts);
+
+        let before = to.amount;
+        transfer_checked(ctx, amount_with_transfer_fee(mint, amount)?, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
+    }
+}
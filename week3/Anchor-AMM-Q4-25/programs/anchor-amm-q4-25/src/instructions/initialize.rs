@@ -1,18 +1,24 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    token::Token,
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::state::Config;
+use crate::{
+    state::{Config, CurveType, ObservationState, PoolRegistry},
+    token_extensions::validate_mint,
+};
 
 #[derive(Accounts)]
-#[instruction(seed: u64)]
+#[instruction(seed: u64, fee: u16)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: InterfaceAccount<'info, Mint>,
     #[account(
         init,
         payer = initializer,
@@ -20,22 +26,25 @@ pub struct Initialize<'info> {
         bump,
         mint::decimals = 6,
         mint::authority = config,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program_x,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program_y,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = initializer,
@@ -44,30 +53,93 @@ pub struct Initialize<'info> {
         space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
     )]
     pub config: Account<'info, Config>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"pool", mint_x.key().as_ref(), mint_y.key().as_ref(), fee.to_le_bytes().as_ref()],
+        bump,
+        space = PoolRegistry::DISCRIMINATOR.len() + PoolRegistry::INIT_SPACE,
+    )]
+    pub registry: Account<'info, PoolRegistry>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"observation", config.key().as_ref()],
+        bump,
+        space = ObservationState::DISCRIMINATOR.len() + ObservationState::INIT_SPACE,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+    /// CHECK: never signs, it only owns the locked minimum liquidity
+    #[account(
+        seeds = [b"dead", config.key().as_ref()],
+        bump,
+    )]
+    pub dead: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_lp,
+        associated_token::authority = dead,
+        associated_token::token_program = token_program,
+    )]
+    pub locked_lp: InterfaceAccount<'info, TokenAccount>,
     pub token_program: Program<'info, Token>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Initialize<'info> {
+    // The pool is listed under the fee tier it starts with. The authority can
+    // change the fee later, the tier stays what the registry is keyed on.
     pub fn init(
         &mut self,
         seed: u64,
         fee: u16,
         authority: Option<Pubkey>,
+        curve: CurveType,
         bumps: InitializeBumps,
     ) -> Result<()> {
+        PoolRegistry::check_pair(&self.mint_x.key(), &self.mint_y.key())?;
+        validate_mint(&self.mint_x)?;
+        validate_mint(&self.mint_y)?;
+        curve.validate(self.mint_x.decimals, self.mint_y.decimals)?;
+
         self.config.set_inner(Config {
             seed,
             authority,
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
             fee,
+            fee_tier: fee,
             locked: false,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
+            curve,
+            pending_authority: None,
+            protocol_fee: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            dead_bump: bumps.dead,
+            flash_loan_x: 0,
+            flash_loan_y: 0,
+            dynamic_fee: None,
+        });
+
+        self.registry.set_inner(PoolRegistry {
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee_tier: fee,
+            config: self.config.key(),
+            bump: bumps.registry,
         });
 
+        let mut observation = self.observation.load_init()?;
+        observation.config = self.config.key();
+        observation.bump = bumps.observation;
+        observation.observations[0].timestamp = Clock::get()?.unix_timestamp;
+
         Ok(())
     }
 }
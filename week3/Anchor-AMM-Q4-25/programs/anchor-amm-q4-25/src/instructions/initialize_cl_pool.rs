@@ -0,0 +1,110 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{
+    curves::tick_math::{tick_at_sqrt_price, tick_spacing_for},
+    errors::AmmError,
+    state::{ClPool, ObservationState, PoolRegistry},
+    token_extensions::validate_mint,
+};
+
+#[derive(Accounts)]
+#[instruction(fee: u16)]
+pub struct InitializeClPool<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    #[account(mint::token_program = token_program_x)]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_y)]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"cl_pool", mint_x.key().as_ref(), mint_y.key().as_ref(), fee.to_le_bytes().as_ref()],
+        bump,
+        space = ClPool::DISCRIMINATOR.len() + ClPool::INIT_SPACE,
+    )]
+    pub pool: Account<'info, ClPool>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"cl_registry", mint_x.key().as_ref(), mint_y.key().as_ref(), fee.to_le_bytes().as_ref()],
+        bump,
+        space = PoolRegistry::DISCRIMINATOR.len() + PoolRegistry::INIT_SPACE,
+    )]
+    pub registry: Account<'info, PoolRegistry>,
+    #[account(
+        init,
+        payer = initializer,
+        seeds = [b"observation", pool.key().as_ref()],
+        bump,
+        space = ObservationState::DISCRIMINATOR.len() + ObservationState::INIT_SPACE,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeClPool<'info> {
+    // The fee tier picks the tick spacing, so there is one pool per pair and
+    // tier, listed in a registry of its own next to the curve pools'.
+    pub fn init(&mut self, fee: u16, authority: Option<Pubkey>, sqrt_price: u128, bumps: InitializeClPoolBumps) -> Result<()> {
+        PoolRegistry::check_pair(&self.mint_x.key(), &self.mint_y.key())?;
+        validate_mint(&self.mint_x)?;
+        validate_mint(&self.mint_y)?;
+
+        let tick_spacing = tick_spacing_for(fee).ok_or(AmmError::InvalidFee)?;
+        let tick_current = tick_at_sqrt_price(sqrt_price).ok_or(AmmError::InvalidSqrtPrice)?;
+
+        self.pool.set_inner(ClPool {
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            tick_spacing,
+            fee,
+            authority,
+            locked: false,
+            sqrt_price,
+            tick_current,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            bump: bumps.pool,
+        });
+
+        self.registry.set_inner(PoolRegistry {
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee_tier: fee,
+            config: self.pool.key(),
+            bump: bumps.registry,
+        });
+
+        let mut observation = self.observation.load_init()?;
+        observation.config = self.pool.key();
+        observation.bump = bumps.observation;
+        observation.observations[0].timestamp = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+}
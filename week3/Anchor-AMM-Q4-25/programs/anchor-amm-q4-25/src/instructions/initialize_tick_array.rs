@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    curves::tick_math::{MAX_TICK, MIN_TICK},
+    errors::AmmError,
+    state::{ClPool, TickArray, TICK_ARRAY_SIZE},
+};
+
+#[derive(Accounts)]
+#[instruction(start_tick_index: i32)]
+pub struct InitializeTickArray<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub pool: Account<'info, ClPool>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"tick_array", pool.key().as_ref(), start_tick_index.to_le_bytes().as_ref()],
+        bump,
+        space = TickArray::DISCRIMINATOR.len() + TickArray::INIT_SPACE,
+    )]
+    pub tick_array: AccountLoader<'info, TickArray>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeTickArray<'info> {
+    pub fn init(&mut self, start_tick_index: i32) -> Result<()> {
+        let span = self.pool.tick_spacing as i32 * TICK_ARRAY_SIZE as i32;
+        require!(
+            start_tick_index == TickArray::start_index(start_tick_index, self.pool.tick_spacing)
+                && start_tick_index + span > MIN_TICK
+                && start_tick_index <= MAX_TICK,
+            AmmError::InvalidTickArray
+        );
+
+        let mut tick_array = self.tick_array.load_init()?;
+        tick_array.pool = self.pool.key();
+        tick_array.start_tick_index = start_tick_index;
+
+        Ok(())
+    }
+}
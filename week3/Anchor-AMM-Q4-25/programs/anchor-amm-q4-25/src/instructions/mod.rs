@@ -1,9 +1,33 @@
+pub mod accept_authority;
+pub mod cl_swap;
+pub mod collect_protocol_fees;
 pub mod deposit;
+pub mod flash_loan;
 pub mod initialize;
+pub mod initialize_cl_pool;
+pub mod initialize_tick_array;
+pub mod modify_position;
+pub mod observe_twap;
+pub mod open_position;
+pub mod route_swap;
 pub mod swap;
+pub mod update_cl_pool;
+pub mod update_config;
 pub mod withdraw;
 
+pub use accept_authority::*;
+pub use cl_swap::*;
+pub use collect_protocol_fees::*;
 pub use deposit::*;
+pub use flash_loan::*;
 pub use initialize::*;
+pub use initialize_cl_pool::*;
+pub use initialize_tick_array::*;
+pub use modify_position::*;
+pub use observe_twap::*;
+pub use open_position::*;
+pub use route_swap::*;
 pub use swap::*;
+pub use update_cl_pool::*;
+pub use update_config::*;
 pub use withdraw::*;
@@ -0,0 +1,288 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::{
+    curves::{
+        concentrated_liquidity::amounts_for_liquidity,
+        tick_math::{prices_at_sqrt_price, sqrt_price_at_tick},
+    },
+    errors::AmmError,
+    state::{fee_growth_inside, load_tick_array, ClPool, ObservationState, Position, Tick, TickArray},
+    token_extensions::amount_with_transfer_fee,
+};
+
+// remaining_accounts holds the tick arrays of the position's lower and upper
+// ticks, once if both ticks are in the same array
+#[derive(Accounts)]
+pub struct ModifyPosition<'info> {
+    pub owner: Signer<'info>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"cl_pool", mint_x.key().as_ref(), mint_y.key().as_ref(), pool.fee.to_le_bytes().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, ClPool>,
+    #[account(
+        mut,
+        has_one = pool,
+        has_one = owner,
+    )]
+    pub position: Account<'info, Position>,
+    #[account(
+        mut,
+        seeds = [b"observation", pool.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_x,
+        token::authority = owner,
+        token::token_program = token_program_x,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_y,
+        token::authority = owner,
+        token::token_program = token_program_y,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ModifyPosition<'info> {
+    pub fn increase_liquidity(
+        &mut self,
+        tick_arrays: &'info [AccountInfo<'info>],
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<()> {
+        require!(!self.pool.locked, AmmError::PoolLocked);
+        require!(liquidity != 0, AmmError::InvalidAmount);
+        let delta = i128::try_from(liquidity).map_err(|_| AmmError::Overflow)?;
+
+        self.modify(tick_arrays, delta)?;
+
+        // amounts the vaults have to receive, after any transfer fee
+        let (x, y) = self.amounts(liquidity, true)?;
+        let send_x = amount_with_transfer_fee(&self.mint_x, x)?;
+        let send_y = amount_with_transfer_fee(&self.mint_y, y)?;
+        require!(send_x <= max_x && send_y <= max_y, AmmError::SlippageExceeded);
+
+        let received_x = self.deposit_tokens(true, send_x)?;
+        let received_y = self.deposit_tokens(false, send_y)?;
+        require!(received_x >= x && received_y >= y, AmmError::SlippageExceeded);
+
+        Ok(())
+    }
+
+    pub fn decrease_liquidity(
+        &mut self,
+        tick_arrays: &'info [AccountInfo<'info>],
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+        require!(!self.pool.locked, AmmError::PoolLocked);
+        require!(liquidity != 0, AmmError::InvalidAmount);
+        let delta = i128::try_from(liquidity).map_err(|_| AmmError::Overflow)?;
+
+        self.modify(tick_arrays, -delta)?;
+
+        let (x, y) = self.amounts(liquidity, false)?;
+        let received_x = self.withdraw_tokens(true, x)?;
+        let received_y = self.withdraw_tokens(false, y)?;
+        require!(received_x >= min_x && received_y >= min_y, AmmError::SlippageExceeded);
+
+        Ok(())
+    }
+
+    pub fn collect_fees(&mut self, tick_arrays: &'info [AccountInfo<'info>]) -> Result<()> {
+        require!(!self.pool.locked, AmmError::PoolLocked);
+
+        // settle what the position earned since its last update
+        if self.position.liquidity != 0 {
+            self.modify(tick_arrays, 0)?;
+        }
+
+        let amount_x = self.position.fees_owed_x;
+        let amount_y = self.position.fees_owed_y;
+        self.position.fees_owed_x = 0;
+        self.position.fees_owed_y = 0;
+
+        self.withdraw_tokens(true, amount_x)?;
+        self.withdraw_tokens(false, amount_y)?;
+
+        Ok(())
+    }
+
+    // Updates the bounding ticks, settles the position's fees and applies the
+    // liquidity change to the position and, when in range, to the pool
+    fn modify(&mut self, tick_arrays: &'info [AccountInfo<'info>], liquidity_delta: i128) -> Result<()> {
+        let (price_x, price_y) = prices_at_sqrt_price(self.pool.sqrt_price);
+        self.observation.load_mut()?.update_prices(price_x, price_y, Clock::get()?.unix_timestamp);
+
+        let tick_arrays = tick_arrays
+            .iter()
+            .map(|account| load_tick_array(account, &self.pool.key()))
+            .collect::<Result<Vec<_>>>()?;
+
+        let (tick_lower, tick_upper) = (self.position.tick_lower, self.position.tick_upper);
+        let lower = self.update_tick(&tick_arrays, tick_lower, liquidity_delta, false)?;
+        let upper = self.update_tick(&tick_arrays, tick_upper, liquidity_delta, true)?;
+
+        let pool = &mut self.pool;
+        let (inside_x, inside_y) = fee_growth_inside(
+            &lower,
+            &upper,
+            tick_lower,
+            tick_upper,
+            pool.tick_current,
+            pool.fee_growth_global_x,
+            pool.fee_growth_global_y,
+        );
+        self.position.update(liquidity_delta, inside_x, inside_y)?;
+
+        if (tick_lower..tick_upper).contains(&pool.tick_current) {
+            pool.liquidity = pool
+                .liquidity
+                .checked_add_signed(liquidity_delta)
+                .ok_or(AmmError::Overflow)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the tick after the update
+    fn update_tick(
+        &self,
+        tick_arrays: &[AccountLoader<'info, TickArray>],
+        tick: i32,
+        liquidity_delta: i128,
+        upper: bool,
+    ) -> Result<Tick> {
+        let spacing = self.pool.tick_spacing;
+        for tick_array in tick_arrays {
+            let mut tick_array = tick_array.load_mut()?;
+            if !tick_array.contains(tick, spacing) {
+                continue;
+            }
+
+            let state = tick_array.tick_mut(tick, spacing)?;
+            if liquidity_delta != 0 {
+                state.update(
+                    tick,
+                    self.pool.tick_current,
+                    liquidity_delta,
+                    upper,
+                    self.pool.fee_growth_global_x,
+                    self.pool.fee_growth_global_y,
+                )?;
+            }
+            return Ok(*state);
+        }
+
+        err!(AmmError::InvalidTickArray)
+    }
+
+    fn amounts(&self, liquidity: u128, round_up: bool) -> Result<(u64, u64)> {
+        let sqrt_price_lower = sqrt_price_at_tick(self.position.tick_lower).ok_or(AmmError::InvalidTickRange)?;
+        let sqrt_price_upper = sqrt_price_at_tick(self.position.tick_upper).ok_or(AmmError::InvalidTickRange)?;
+
+        Ok(amounts_for_liquidity(
+            self.pool.sqrt_price,
+            sqrt_price_lower,
+            sqrt_price_upper,
+            liquidity,
+            round_up,
+        )
+        .ok_or(AmmError::Overflow)?)
+    }
+
+    // Returns the amount the vault received
+    fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.user_x, &mut self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.user_y, &mut self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.owner.to_account_info(),
+        };
+
+        let ctx = CpiContext::new(cpi_program.to_account_info(), cpi_accounts);
+
+        let before = to.amount;
+        transfer_checked(ctx, amount, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
+    }
+
+    // Returns the amount the owner received
+    fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &mut self.user_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &mut self.user_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
+            authority: self.pool.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"cl_pool",
+            self.pool.mint_x.as_ref(),
+            self.pool.mint_y.as_ref(),
+            &self.pool.fee.to_le_bytes(),
+            &[self.pool.bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            cpi_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        let before = to.amount;
+        transfer_checked(ctx, amount, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
+    }
+}
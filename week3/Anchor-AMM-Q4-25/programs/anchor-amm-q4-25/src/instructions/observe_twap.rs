@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    curves::tick_math::prices_at_sqrt_price,
+    errors::AmmError,
+    state::{ClPool, Config, ObservationState, Twap},
+};
+
+// Read-only, the TWAP is returned to the caller, see ObservationState
+#[derive(Accounts)]
+pub struct ObserveTwap<'info> {
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(
+        seeds = [b"observation", config.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+    #[account(
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
+}
+
+impl<'info> ObserveTwap<'info> {
+    // Average over at least the last `window` seconds, up to now at the current reserves
+    pub fn observe_twap(&self, window: u32) -> Result<Twap> {
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        let twap = self.observation.load()?.twap(x, y, now, window);
+        Ok(twap.ok_or(AmmError::InsufficientObservations)?)
+    }
+}
+
+#[derive(Accounts)]
+pub struct ObserveClTwap<'info> {
+    #[account(
+        seeds = [b"cl_pool", pool.mint_x.as_ref(), pool.mint_y.as_ref(), pool.fee.to_le_bytes().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, ClPool>,
+    #[account(
+        seeds = [b"observation", pool.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+}
+
+impl<'info> ObserveClTwap<'info> {
+    // Average over at least the last `window` seconds, up to now at the current price
+    pub fn observe_twap(&self, window: u32) -> Result<Twap> {
+        let (price_x, price_y) = prices_at_sqrt_price(self.pool.sqrt_price);
+        let now = Clock::get()?.unix_timestamp;
+
+        let twap = self.observation.load()?.twap_prices(price_x, price_y, now, window);
+        Ok(twap.ok_or(AmmError::InsufficientObservations)?)
+    }
+}
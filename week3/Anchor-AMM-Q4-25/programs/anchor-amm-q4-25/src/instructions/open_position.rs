@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    curves::tick_math::{MAX_TICK, MIN_TICK},
+    errors::AmmError,
+    state::{ClPool, Position},
+};
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct OpenPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub pool: Account<'info, ClPool>,
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump,
+        space = Position::DISCRIMINATOR.len() + Position::INIT_SPACE,
+    )]
+    pub position: Account<'info, Position>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> OpenPosition<'info> {
+    pub fn open_position(&mut self, tick_lower: i32, tick_upper: i32, bumps: OpenPositionBumps) -> Result<()> {
+        let spacing = self.pool.tick_spacing as i32;
+        require!(
+            tick_lower < tick_upper
+                && tick_lower >= MIN_TICK
+                && tick_upper <= MAX_TICK
+                && tick_lower % spacing == 0
+                && tick_upper % spacing == 0,
+            AmmError::InvalidTickRange
+        );
+
+        self.position.set_inner(Position {
+            pool: self.pool.key(),
+            owner: self.owner.key(),
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_x_last: 0,
+            fee_growth_inside_y_last: 0,
+            fees_owed_x: 0,
+            fees_owed_y: 0,
+            bump: bumps.position,
+        });
+
+        Ok(())
+    }
+}
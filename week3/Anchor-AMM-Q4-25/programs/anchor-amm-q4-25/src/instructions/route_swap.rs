@@ -0,0 +1,240 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    errors::AmmError,
+    events::SwapExecuted,
+    state::{Config, ObservationState},
+};
+
+// Accounts each pool of the route passes through remaining_accounts:
+// [config, mint_lp, vault_x, vault_y, mint_x, mint_y, token_program_x, token_program_y, observation]
+pub const ROUTE_POOL_ACCOUNTS: usize = 9;
+
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mint::token_program = token_program_in)]
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    #[account(mint::token_program = token_program_out)]
+    pub mint_out: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = mint_in,
+        token::authority = user,
+        token::token_program = token_program_in,
+    )]
+    pub user_in: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint_out,
+        token::authority = user,
+        token::token_program = token_program_out,
+    )]
+    pub user_out: InterfaceAccount<'info, TokenAccount>,
+    pub token_program_in: Interface<'info, TokenInterface>,
+    pub token_program_out: Interface<'info, TokenInterface>,
+}
+
+impl<'info> RouteSwap<'info> {
+    pub fn route_swap(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64, // Checked once, on what reaches user_out
+    ) -> Result<()> {
+        require!(amount_in > 0, AmmError::InvalidAmount);
+        require!(
+            !remaining_accounts.is_empty() && remaining_accounts.len() % ROUTE_POOL_ACCOUNTS == 0,
+            AmmError::InvalidRoute
+        );
+
+        let mut pools = remaining_accounts
+            .chunks(ROUTE_POOL_ACCOUNTS)
+            .map(RoutePool::load)
+            .collect::<Result<Vec<_>>>()?;
+
+        // walk the mints to find which side of each pool the input lands on
+        let mut mint = self.mint_in.key();
+        let mut sides = Vec::with_capacity(pools.len());
+        for (i, pool) in pools.iter().enumerate() {
+            require!(!pool.config.locked, AmmError::PoolLocked);
+            require!(
+                pools[..i].iter().all(|other| other.config.key() != pool.config.key()),
+                AmmError::InvalidRoute
+            );
+
+            let side = pool.side_of(&mint)?;
+            mint = pool.mints[1 - side].key();
+            sides.push(side);
+        }
+        require_keys_eq!(mint, self.mint_out.key(), AmmError::InvalidRoute);
+
+        // the first pool is paid by the user
+        let first_vault = pools[0].vaults[sides[0]];
+        let before = balance(first_vault)?;
+
+        let cpi_accounts = TransferChecked {
+            from: self.user_in.to_account_info(),
+            mint: self.mint_in.to_account_info(),
+            to: first_vault.clone(),
+            authority: self.user.to_account_info(),
+        };
+        let ctx = CpiContext::new(self.token_program_in.to_account_info(), cpi_accounts);
+        transfer_checked(ctx, amount_in, self.mint_in.decimals)?;
+
+        let mut amount = balance(first_vault)? - before;
+        let now = Clock::get()?.unix_timestamp;
+
+        for i in 0..pools.len() {
+            let side = sides[i];
+            let is_x = side == 0;
+
+            let withdraw = {
+                let pool = &mut pools[i];
+
+                // the input is already in the vault, price against the reserves before it
+                let (x, y) = pool.config.reserves(balance(pool.vaults[0])?, balance(pool.vaults[1])?)?;
+                let (x, y) = match is_x {
+                    true => (x.checked_sub(amount).ok_or(AmmError::Underflow)?, y),
+                    false => (x, y.checked_sub(amount).ok_or(AmmError::Underflow)?),
+                };
+                pool.observation.load_mut()?.update(x, y, now);
+
+                let (curve, supply) = (pool.config.curve, pool.lp_supply);
+                let (swaps, fee) = pool.config.quote_swap(x, y, is_x, now, |fee| {
+                    curve.swap(x, y, supply, fee, is_x, amount, 0)
+                })?;
+                require!(swaps.deposit != 0, AmmError::InvalidAmount);
+
+                pool.config.accrue_protocol_fee(is_x, swaps.fee)?;
+                pool.config.record_swap(x, y, is_x, swaps.deposit, swaps.withdraw, now);
+                pool.config.exit(&crate::ID)?;
+
+                emit!(SwapExecuted {
+                    config: pool.config.key(),
+                    is_x,
+                    amount_in: swaps.deposit,
+                    amount_out: swaps.withdraw,
+                    fee_amount: swaps.fee,
+                    effective_fee: fee,
+                });
+
+                swaps.withdraw
+            };
+
+            // intermediate amounts go straight into the next pool's vault
+            let destination = match pools.get(i + 1) {
+                Some(next) => next.vaults[sides[i + 1]].clone(),
+                None => self.user_out.to_account_info(),
+            };
+
+            let before = balance(&destination)?;
+            pools[i].pay_out(1 - side, &destination, withdraw)?;
+            amount = balance(&destination)? - before;
+        }
+
+        require!(amount >= min_amount_out, AmmError::SlippageExceeded);
+
+        Ok(())
+    }
+}
+
+struct RoutePool<'info> {
+    config: Account<'info, Config>,
+    lp_supply: u64,
+    vaults: [&'info AccountInfo<'info>; 2],         // indexed by side, 0 is x
+    mints: [InterfaceAccount<'info, Mint>; 2],
+    token_programs: [&'info AccountInfo<'info>; 2],
+    observation: AccountLoader<'info, ObservationState>,
+}
+
+impl<'info> RoutePool<'info> {
+    fn load(accounts: &'info [AccountInfo<'info>]) -> Result<Self> {
+        let config = Account::<Config>::try_from(&accounts[0])?;
+
+        let mint_lp = InterfaceAccount::<Mint>::try_from(&accounts[1])?;
+        let expected_lp = Pubkey::create_program_address(
+            &[b"lp", config.key().as_ref(), &[config.lp_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::ConstraintSeeds)?;
+        require_keys_eq!(mint_lp.key(), expected_lp, ErrorCode::ConstraintSeeds);
+
+        let mints = [
+            InterfaceAccount::<Mint>::try_from(&accounts[4])?,
+            InterfaceAccount::<Mint>::try_from(&accounts[5])?,
+        ];
+        require_keys_eq!(mints[0].key(), config.mint_x, AmmError::InvalidToken);
+        require_keys_eq!(mints[1].key(), config.mint_y, AmmError::InvalidToken);
+
+        for side in 0..2 {
+            let token_program = &accounts[6 + side];
+            require_keys_eq!(*mints[side].to_account_info().owner, token_program.key(), AmmError::InvalidToken);
+
+            let vault = get_associated_token_address_with_program_id(
+                &config.key(),
+                &mints[side].key(),
+                &token_program.key(),
+            );
+            require_keys_eq!(accounts[2 + side].key(), vault, AmmError::InvalidToken);
+        }
+
+        let observation = AccountLoader::<ObservationState>::try_from(&accounts[8])?;
+        let expected_observation = Pubkey::create_program_address(
+            &[b"observation", config.key().as_ref(), &[observation.load()?.bump]],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::ConstraintSeeds)?;
+        require_keys_eq!(observation.key(), expected_observation, ErrorCode::ConstraintSeeds);
+
+        Ok(Self {
+            lp_supply: mint_lp.supply,
+            config,
+            vaults: [&accounts[2], &accounts[3]],
+            mints,
+            token_programs: [&accounts[6], &accounts[7]],
+            observation,
+        })
+    }
+
+    fn side_of(&self, mint: &Pubkey) -> Result<usize> {
+        match *mint {
+            m if m == self.config.mint_x => Ok(0),
+            m if m == self.config.mint_y => Ok(1),
+            _ => err!(AmmError::InvalidRoute),
+        }
+    }
+
+    fn pay_out(&self, side: usize, to: &AccountInfo<'info>, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.vaults[side].clone(),
+            mint: self.mints[side].to_account_info(),
+            to: to.clone(),
+            authority: self.config.to_account_info(),
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"config",
+            &self.config.seed.to_le_bytes(),
+            &[self.config.config_bump],
+        ]];
+
+        let ctx = CpiContext::new_with_signer(
+            self.token_programs[side].clone(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(ctx, amount, self.mints[side].decimals)
+    }
+}
+
+fn balance(token_account: &AccountInfo) -> Result<u64> {
+    let data = token_account.try_borrow_data()?;
+    Ok(TokenAccount::try_deserialize(&mut &data[..])?.amount)
+}
@@ -1,20 +1,25 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer, Mint, Token, TokenAccount, Transfer},
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use constant_product_curve::{ConstantProduct, LiquidityPair};
 
-use crate::{errors::AmmError, state::Config};
+use crate::{
+    errors::AmmError,
+    events::SwapExecuted,
+    state::{Config, ObservationState},
+    token_extensions::amount_with_transfer_fee,
+};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -22,106 +27,171 @@ pub struct Swap<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    #[account(
+        mut,
+        seeds = [b"observation", config.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = user 
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>
 }
 
 impl<'info> Swap<'info> {
     pub fn swap(&mut self, is_x: bool, amount: u64, min: u64) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
         require!(amount > 0, AmmError::InvalidAmount);
 
-        let mut curve = ConstantProduct::init(
-            self.vault_x.amount,
-            self.vault_y.amount,
-            self.mint_lp.supply,
-            self.config.fee,
-            None
-        ).map_err(AmmError::from)?;
-
-        let res = match is_x {
-            true => LiquidityPair::X,
-            false => LiquidityPair::Y
-        };
+        let now = Clock::get()?.unix_timestamp;
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.observation.load_mut()?.update(x, y, now);
 
-        let swaps = curve.swap(res, amount, min).map_err(AmmError::from)?;
+        // price the swap on what the vault actually received, after any transfer fee
+        let received = self.deposit_tokens(is_x, amount)?;
+
+        let (curve, supply) = (self.config.curve, self.mint_lp.supply);
+        let (swaps, fee) = self.config.quote_swap(x, y, is_x, now, |fee| {
+            curve.swap(x, y, supply, fee, is_x, received, 0)
+        })?;
 
         require!(swaps.deposit != 0, AmmError::InvalidAmount);
 
-        self.deposit_tokens(is_x, swaps.deposit)?;
-        self.withdraw_tokens(!is_x, swaps.withdraw)?;
+        self.config.accrue_protocol_fee(is_x, swaps.fee)?;
+        self.config.record_swap(x, y, is_x, swaps.deposit, swaps.withdraw, now);
+
+        let paid_out = self.withdraw_tokens(!is_x, swaps.withdraw)?;
+        require!(paid_out >= min, AmmError::SlippageExceeded);
+
+        emit!(SwapExecuted {
+            config: self.config.key(),
+            is_x,
+            amount_in: swaps.deposit,
+            amount_out: swaps.withdraw,
+            fee_amount: swaps.fee,
+            effective_fee: fee,
+        });
+
         Ok(())
     }
 
-    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
-            true => (
-                self.user_x.to_account_info(),
-                self.vault_x.to_account_info(),
-            ),
-            false => (
-                self.user_y.to_account_info(),
-                self.vault_y.to_account_info(),
-            ),
+    pub fn swap_exact_out(&mut self, is_x: bool, amount_out: u64, max_amount_in: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount_out > 0, AmmError::InvalidAmount);
+
+        let now = Clock::get()?.unix_timestamp;
+        let (x, y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.observation.load_mut()?.update(x, y, now);
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
         };
 
-        let cpi_program = self.token_program.to_account_info();
+        // the vault pays any transfer fee on top so the user gets exactly amount_out
+        let withdraw = amount_with_transfer_fee(mint_out, amount_out)?;
+        let curve = self.config.curve;
+        let (swaps, fee) = self
+            .config
+            .quote_swap(x, y, is_x, now, |fee| curve.swap_exact_out(x, y, fee, is_x, withdraw))?;
+
+        let amount_in = amount_with_transfer_fee(mint_in, swaps.deposit)?;
+        require!(amount_in <= max_amount_in, AmmError::SlippageExceeded);
+
+        let received = self.deposit_tokens(is_x, amount_in)?;
+        require!(received >= swaps.deposit, AmmError::InsufficientBalance);
 
-        let cpi_accounts = Transfer {
-            from,
-            to,
+        self.config.accrue_protocol_fee(is_x, swaps.fee)?;
+        self.config.record_swap(x, y, is_x, swaps.deposit, swaps.withdraw, now);
+
+        let paid_out = self.withdraw_tokens(!is_x, swaps.withdraw)?;
+        require!(paid_out >= amount_out, AmmError::SlippageExceeded);
+
+        emit!(SwapExecuted {
+            config: self.config.key(),
+            is_x,
+            amount_in: swaps.deposit,
+            amount_out: swaps.withdraw,
+            fee_amount: swaps.fee,
+            effective_fee: fee,
+        });
+
+        Ok(())
+    }
+
+    // Returns the amount the vault received
+    pub fn deposit_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.user_x, &mut self.vault_x, &self.mint_x, &self.token_program_x),
+            false => (&self.user_y, &mut self.vault_y, &self.mint_y, &self.token_program_y),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
             authority: self.user.to_account_info(),
         };
 
-        let ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let ctx = CpiContext::new(cpi_program.to_account_info(), cpi_accounts);
+
+        let before = to.amount;
+        transfer_checked(ctx, amount, mint.decimals)?;
+        to.reload()?;
 
-        transfer(ctx, amount)
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
     }
 
-    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
-            true => (&self.vault_x, &self.user_x),
-            false => (&self.vault_y, &self.user_y),
+    // Returns the amount the user received
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &mut self.user_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &mut self.user_y, &self.mint_y, &self.token_program_y),
         };
 
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from: from.to_account_info(),
+            mint: mint.to_account_info(),
             to: to.to_account_info(),
             authority: self.config.to_account_info(),
         };
@@ -133,11 +203,15 @@ impl<'info> Swap<'info> {
         ]];
 
         let cpi_ctx = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
+            cpi_program.to_account_info(),
             cpi_accounts,
             signer_seeds,
         );
-        transfer(cpi_ctx, amount)?;
-        Ok(())
+
+        let before = to.amount;
+        transfer_checked(cpi_ctx, amount, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
     }
 }
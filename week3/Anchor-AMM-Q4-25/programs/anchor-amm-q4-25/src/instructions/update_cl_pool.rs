@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::{events::PoolLockUpdated, state::ClPool};
+
+#[derive(Accounts)]
+pub struct UpdateClPool<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"cl_pool", pool.mint_x.as_ref(), pool.mint_y.as_ref(), pool.fee.to_le_bytes().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, ClPool>,
+}
+
+impl<'info> UpdateClPool<'info> {
+    pub fn set_locked(&mut self, locked: bool) -> Result<()> {
+        self.pool.check_authority(&self.authority.key())?;
+
+        self.pool.locked = locked;
+
+        emit!(PoolLockUpdated {
+            config: self.pool.key(),
+            locked,
+        });
+
+        Ok(())
+    }
+}
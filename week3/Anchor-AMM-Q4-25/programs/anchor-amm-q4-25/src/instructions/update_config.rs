@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    errors::AmmError,
+    events::{AuthorityTransferStarted, DynamicFeeUpdated, FeeUpdated, PoolLockUpdated, ProtocolFeeUpdated},
+    state::{Config, DynamicFee},
+};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn set_locked(&mut self, locked: bool) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+
+        self.config.locked = locked;
+
+        emit!(PoolLockUpdated {
+            config: self.config.key(),
+            locked,
+        });
+
+        Ok(())
+    }
+
+    // The registry lists the pool under `fee_tier`, which does not change with the fee
+    pub fn update_fee(&mut self, fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(fee <= 10_000, AmmError::InvalidFee);
+
+        let old_fee = self.config.fee;
+        self.config.fee = fee;
+
+        emit!(FeeUpdated {
+            config: self.config.key(),
+            old_fee,
+            new_fee: fee,
+        });
+
+        Ok(())
+    }
+
+    // Replaces the static fee with a volatility-driven one, None goes back to `fee`
+    pub fn set_dynamic_fee(&mut self, dynamic_fee: Option<DynamicFee>) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+
+        self.config.dynamic_fee = dynamic_fee;
+
+        emit!(DynamicFeeUpdated {
+            config: self.config.key(),
+            dynamic_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+        require!(protocol_fee <= 10_000, AmmError::InvalidFee);
+
+        let old_protocol_fee = self.config.protocol_fee;
+        self.config.protocol_fee = protocol_fee;
+
+        emit!(ProtocolFeeUpdated {
+            config: self.config.key(),
+            old_protocol_fee,
+            new_protocol_fee: protocol_fee,
+        });
+
+        Ok(())
+    }
+
+    // First step of the handover, the new authority has to accept it
+    pub fn transfer_authority(&mut self, new_authority: Pubkey) -> Result<()> {
+        self.config.check_authority(&self.authority.key())?;
+
+        self.config.pending_authority = Some(new_authority);
+
+        emit!(AuthorityTransferStarted {
+            config: self.config.key(),
+            authority: self.authority.key(),
+            pending_authority: new_authority,
+        });
+
+        Ok(())
+    }
+}
@@ -1,20 +1,27 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer, Burn, Mint, Token, TokenAccount, Transfer},
+    token::Token,
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
-use crate::{errors::AmmError, state::Config};
+use crate::{
+    errors::AmmError,
+    events::SwapExecuted,
+    state::{Config, ObservationState, MINIMUM_LIQUIDITY},
+};
 
 #[derive(Accounts)]
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    pub mint_y: InterfaceAccount<'info, Mint>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -22,49 +29,63 @@ pub struct Withdraw<'info> {
     )]
     pub config: Account<'info, Config>,
 
+    #[account(
+        mut,
+        seeds = [b"observation", config.key().as_ref()],
+        bump = observation.load()?.bump,
+    )]
+    pub observation: AccountLoader<'info, ObservationState>,
+
     #[account(
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_x
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program_y
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program_x
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = user 
+        associated_token::authority = user,
+        associated_token::token_program = token_program_y
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_lp,
-        associated_token::authority = user 
+        associated_token::authority = user,
+        associated_token::token_program = token_program
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
+    pub token_program_x: Interface<'info, TokenInterface>,
+    pub token_program_y: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub associated_token_program: Program<'info, AssociatedToken>
 }
@@ -76,58 +97,114 @@ impl<'info> Withdraw<'info> {
         min_x: u64,  // Minimum amount of token X that the user wants to receive
         min_y: u64,  // Minimum amount of token Y that the user wants to receive
     ) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
         require!(amount != 0, AmmError::InvalidAmount);
         require!(self.user_lp.amount >= amount, AmmError::InsufficientBalance);
 
 
         let total_supply = self.mint_lp.supply;
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0 {
-            true => (min_x, min_y),
-            false => {
-                let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount, 
-                    total_supply, 
-                    amount, 
-                    6
-                )
-                .unwrap();
-                (amounts.x, amounts.y)
-            }
-        };
-
-        require!(x >= min_x && y >= min_y, AmmError::SlippageExceeded);
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.observation.load_mut()?.update(reserve_x, reserve_y, Clock::get()?.unix_timestamp);
+
+        require!(
+            total_supply.saturating_sub(amount) >= MINIMUM_LIQUIDITY,
+            AmmError::LiquidityLessThanMinimum
+        );
+
+        let amounts = self.config.curve.withdraw_amounts(
+            reserve_x,
+            reserve_y,
+            total_supply,
+            amount,
+        )?;
+        let (x, y) = (amounts.x, amounts.y);
 
         // withdraw token x
-        self.withdraw_tokens(true, x)?;
+        let received_x = self.withdraw_tokens(true, x)?;
         // withdraw token y
-        self.withdraw_tokens(false, y)?;
+        let received_y = self.withdraw_tokens(false, y)?;
+
+        // slippage is checked on what arrived, after any transfer fee
+        require!(received_x >= min_x && received_y >= min_y, AmmError::SlippageExceeded);
+
         // burn lp tokens
-        self.burn_lp_tokens(amount);
+        self.burn_lp_tokens(amount)?;
 
         Ok(())
     }
 
-    pub fn withdraw_tokens(&self, is_x: bool, amount: u64) -> Result<()> {
-        let(from, to) = match is_x {
-            true => (
-                self.vault_x.to_account_info(),
-                self.user_x.to_account_info(),
-            ),
-            false => (
-                self.vault_y.to_account_info(),
-                self.user_y.to_account_info(),
-            ),
+    // Withdraws one token only. The other token's share stays in the pool and is
+    // swapped through the curve for more of the requested token, paying the swap fee.
+    pub fn withdraw_single(&mut self, is_x: bool, amount: u64, min_out: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount != 0, AmmError::InvalidAmount);
+        require!(self.user_lp.amount >= amount, AmmError::InsufficientBalance);
+
+        let total_supply = self.mint_lp.supply;
+        require!(
+            total_supply.saturating_sub(amount) >= MINIMUM_LIQUIDITY,
+            AmmError::LiquidityLessThanMinimum
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let (reserve_x, reserve_y) = self.config.reserves(self.vault_x.amount, self.vault_y.amount)?;
+        self.observation.load_mut()?.update(reserve_x, reserve_y, now);
+        let amounts = self.config.curve.withdraw_amounts(
+            reserve_x,
+            reserve_y,
+            total_supply,
+            amount,
+        )?;
+
+        let (keep, swap_in) = match is_x {
+            true => (amounts.x, amounts.y),
+            false => (amounts.y, amounts.x),
         };
 
-        let cpi_program = self.token_program.to_account_info();
+        let swapped = match swap_in {
+            0 => 0,
+            _ => {
+                let (x, y) = (reserve_x - amounts.x, reserve_y - amounts.y);
+                let curve = self.config.curve;
+                let (swaps, fee) = self.config.quote_swap(x, y, !is_x, now, |fee| {
+                    curve.swap(x, y, total_supply - amount, fee, !is_x, swap_in, 0)
+                })?;
+                self.config.accrue_protocol_fee(!is_x, swaps.fee)?;
+                self.config.record_swap(x, y, !is_x, swap_in, swaps.withdraw, now);
+
+                // the other token's share is swapped inside the pool
+                emit!(SwapExecuted {
+                    config: self.config.key(),
+                    is_x: !is_x,
+                    amount_in: swap_in,
+                    amount_out: swaps.withdraw,
+                    fee_amount: swaps.fee,
+                    effective_fee: fee,
+                });
+                swaps.withdraw
+            }
+        };
+
+        let out = keep.checked_add(swapped).ok_or(AmmError::Overflow)?;
+        let received = self.withdraw_tokens(is_x, out)?;
+
+        // slippage is checked on what arrived, after any transfer fee
+        require!(received >= min_out, AmmError::SlippageExceeded);
+
+        self.burn_lp_tokens(amount)
+    }
+
+    // Returns the amount the user received
+    pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<u64> {
+        let (from, to, mint, cpi_program) = match is_x {
+            true => (&self.vault_x, &mut self.user_x, &self.mint_x, &self.token_program_x),
+            false => (&self.vault_y, &mut self.user_y, &self.mint_y, &self.token_program_y),
+        };
 
-        let cpi_accounts = Transfer {
-            from,
-            to,
+        let cpi_accounts = TransferChecked {
+            from: from.to_account_info(),
+            mint: mint.to_account_info(),
+            to: to.to_account_info(),
             authority: self.config.to_account_info()
         };
 
@@ -137,9 +214,13 @@ impl<'info> Withdraw<'info> {
             &[self.config.config_bump],
         ]];
 
-        let ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
-        transfer(ctx, amount)?;
-        Ok(())
+        let ctx = CpiContext::new_with_signer(cpi_program.to_account_info(), cpi_accounts, signer_seeds);
+
+        let before = to.amount;
+        transfer_checked(ctx, amount, mint.decimals)?;
+        to.reload()?;
+
+        Ok(to.amount.checked_sub(before).ok_or(AmmError::Underflow)?)
     }
 
     pub fn burn_lp_tokens(&self, amount: u64) -> Result<()> {
@@ -152,7 +233,6 @@ impl<'info> Withdraw<'info> {
         };
 
         let ctx = CpiContext::new(cpi_program, cpi_accounts);
-        burn(ctx, amount);
-        Ok(())
+        burn(ctx, amount)
     }
 }
@@ -1,10 +1,14 @@
 use anchor_lang::prelude::*;
 
+mod curves;
 mod errors;
+mod events;
 mod instructions;
-mod state;
+pub mod state;
+mod token_extensions;
 
 use instructions::*;
+use state::{CurveType, DynamicFee, Twap};
 declare_id!("BHBTCTguSuhHF6uCcZQu8oR7GgaFA7daN9aYpqCv3vuF");
 
 #[program]
@@ -16,19 +20,160 @@ pub mod anchor_amm_q4_25 {
         seed: u64,
         fee: u16,
         authority: Option<Pubkey>,
+        curve: CurveType,
     ) -> Result<()> {
-        ctx.accounts.init(seed, fee, authority, ctx.bumps)
+        ctx.accounts.init(seed, fee, authority, curve, ctx.bumps)
     }
 
     pub fn deposit(ctx: Context<Deposit>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
         ctx.accounts.deposit(amount, max_x, max_y)
     }
 
+    pub fn deposit_single(ctx: Context<Deposit>, is_x: bool, amount_in: u64, min_lp: u64) -> Result<()> {
+        ctx.accounts.deposit_single(is_x, amount_in, min_lp)
+    }
+
     pub fn withdraw(ctx: Context<Withdraw>, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
         ctx.accounts.withdraw(amount, max_x, max_y)
     }
 
+    pub fn withdraw_single(ctx: Context<Withdraw>, is_x: bool, lp_amount: u64, min_out: u64) -> Result<()> {
+        ctx.accounts.withdraw_single(is_x, lp_amount, min_out)
+    }
+
     pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
         ctx.accounts.swap(is_x, amount_in, min_amount_out)
     }
+
+    pub fn swap_exact_out(
+        ctx: Context<Swap>,
+        is_x: bool,
+        amount_out: u64,
+        max_amount_in: u64,
+    ) -> Result<()> {
+        ctx.accounts.swap_exact_out(is_x, amount_out, max_amount_in)
+    }
+
+    pub fn route_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        ctx.accounts.route_swap(ctx.remaining_accounts, amount_in, min_amount_out)
+    }
+
+    pub fn observe_twap(ctx: Context<ObserveTwap>, window: u32) -> Result<Twap> {
+        ctx.accounts.observe_twap(window)
+    }
+
+    pub fn observe_cl_twap(ctx: Context<ObserveClTwap>, window: u32) -> Result<Twap> {
+        ctx.accounts.observe_twap(window)
+    }
+
+    pub fn flash_loan(ctx: Context<FlashLoan>, amount_x: u64, amount_y: u64) -> Result<()> {
+        ctx.accounts.flash_loan(amount_x, amount_y)
+    }
+
+    pub fn flash_repay(ctx: Context<FlashLoan>) -> Result<()> {
+        ctx.accounts.flash_repay()
+    }
+
+    pub fn initialize_cl_pool(
+        ctx: Context<InitializeClPool>,
+        fee: u16,
+        authority: Option<Pubkey>,
+        sqrt_price: u128,
+    ) -> Result<()> {
+        ctx.accounts.init(fee, authority, sqrt_price, ctx.bumps)
+    }
+
+    pub fn initialize_tick_array(ctx: Context<InitializeTickArray>, start_tick_index: i32) -> Result<()> {
+        ctx.accounts.init(start_tick_index)
+    }
+
+    pub fn open_position(ctx: Context<OpenPosition>, tick_lower: i32, tick_upper: i32) -> Result<()> {
+        ctx.accounts.open_position(tick_lower, tick_upper, ctx.bumps)
+    }
+
+    pub fn increase_liquidity<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ModifyPosition<'info>>,
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<()> {
+        ctx.accounts.increase_liquidity(ctx.remaining_accounts, liquidity, max_x, max_y)
+    }
+
+    pub fn decrease_liquidity<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ModifyPosition<'info>>,
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+        ctx.accounts.decrease_liquidity(ctx.remaining_accounts, liquidity, min_x, min_y)
+    }
+
+    pub fn collect_position_fees<'info>(ctx: Context<'_, '_, 'info, 'info, ModifyPosition<'info>>) -> Result<()> {
+        ctx.accounts.collect_fees(ctx.remaining_accounts)
+    }
+
+    pub fn cl_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClSwap<'info>>,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        ctx.accounts.swap(ctx.remaining_accounts, is_x, amount_in, min_amount_out)
+    }
+
+    pub fn lock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.set_locked(true)
+    }
+
+    pub fn unlock(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.set_locked(false)
+    }
+
+    pub fn lock_cl_pool(ctx: Context<UpdateClPool>) -> Result<()> {
+        ctx.accounts.set_locked(true)
+    }
+
+    pub fn unlock_cl_pool(ctx: Context<UpdateClPool>) -> Result<()> {
+        ctx.accounts.set_locked(false)
+    }
+
+    pub fn update_fee(ctx: Context<UpdateConfig>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee)
+    }
+
+    pub fn enable_dynamic_fee(
+        ctx: Context<UpdateConfig>,
+        fee_floor: u16,
+        fee_cap: u16,
+        variable_fee_control: u16,
+        decay_period: u32,
+    ) -> Result<()> {
+        let dynamic_fee = DynamicFee::new(fee_floor, fee_cap, variable_fee_control, decay_period)?;
+        ctx.accounts.set_dynamic_fee(Some(dynamic_fee))
+    }
+
+    pub fn disable_dynamic_fee(ctx: Context<UpdateConfig>) -> Result<()> {
+        ctx.accounts.set_dynamic_fee(None)
+    }
+
+    pub fn update_protocol_fee(ctx: Context<UpdateConfig>, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_protocol_fee(protocol_fee)
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()
+    }
+
+    pub fn transfer_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.transfer_authority(new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        ctx.accounts.accept_authority()
+    }
 }
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+
+// Concentrated-liquidity pool, at [b"cl_pool", mint_x, mint_y, fee].
+// LPs provide liquidity over tick ranges through Position accounts, and swaps
+// only use the liquidity of the positions in range. Prices are recorded in an
+// ObservationState at [b"observation", pool], like the curve pools.
+#[account]
+#[derive(InitSpace)]
+pub struct ClPool {
+    pub mint_x: Pubkey,             // Token X, sorts before mint_y
+    pub mint_y: Pubkey,             // Token Y
+    pub tick_spacing: u16,          // Fixed by the fee tier, position bounds are multiples of it
+    pub fee: u16,                   // Swap fee in basis points
+    pub authority: Option<Pubkey>,  // Can lock the pool, None if the pool can never be locked
+    pub locked: bool,               // If the pool is locked
+    pub sqrt_price: u128,           // Q64.64 sqrt of the price of X in Y
+    pub tick_current: i32,          // Greatest tick at or below the current price
+    pub liquidity: u128,            // Liquidity of the positions in range
+    pub fee_growth_global_x: u128,  // Q64.64 fees in X earned per unit of liquidity
+    pub fee_growth_global_y: u128,  // Q64.64 fees in Y earned per unit of liquidity
+    pub bump: u8,                   // Bump seed for the pool account
+}
+
+impl ClPool {
+    pub fn check_authority(&self, signer: &Pubkey) -> Result<()> {
+        let authority = self.authority.ok_or(AmmError::NoAuthoritySet)?;
+        require_keys_eq!(authority, *signer, AmmError::InvalidAuthority);
+        Ok(())
+    }
+}
@@ -1,5 +1,14 @@
 use anchor_lang::prelude::*;
 
+use super::q64_price;
+use crate::{curves::SwapAmounts, errors::AmmError};
+
+// LP tokens minted to the dead PDA on the first deposit and never redeemable
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// Requotes of a swap while its own price move raises the dynamic fee
+const MAX_FEE_ROUNDS: usize = 4;
+
 #[account]
 #[derive(InitSpace)]
 pub struct Config {
@@ -8,7 +17,222 @@ pub struct Config {
     pub mint_x: Pubkey,            // Token X
     pub mint_y: Pubkey,            // Token Y
     pub fee: u16,                  // Swap fee in basis points
+    pub fee_tier: u16,             // Fee tier the pool is registered under, fixed at creation
     pub locked: bool,              // If the pool is locked
     pub config_bump: u8,           // Bump seed for the config account
     pub lp_bump: u8,               // Bump seed for the LP token
+    pub curve: CurveType,          // Invariant used to price swaps
+    pub pending_authority: Option<Pubkey>, // Authority waiting to accept a handover
+    pub protocol_fee: u16,         // Share of the swap fee kept for the protocol, in basis points
+    pub protocol_fees_x: u64,      // Accrued protocol fees held in vault X
+    pub protocol_fees_y: u64,      // Accrued protocol fees held in vault Y
+    pub dead_bump: u8,             // Bump seed for the PDA holding the locked minimum liquidity
+    pub flash_loan_x: u64,         // Outstanding flash loan repayment in X, principal plus fee
+    pub flash_loan_y: u64,         // Outstanding flash loan repayment in Y, principal plus fee
+    pub dynamic_fee: Option<DynamicFee>, // Volatility-driven swap fee, replaces `fee` when set
+}
+
+impl Config {
+    pub fn check_authority(&self, signer: &Pubkey) -> Result<()> {
+        let authority = self.authority.ok_or(AmmError::NoAuthoritySet)?;
+        require_keys_eq!(authority, *signer, AmmError::InvalidAuthority);
+        Ok(())
+    }
+
+    // Vault balances that belong to LPs, without protocol fees owed to the treasury.
+    // Fails while a flash loan is outstanding, the vaults are short until it is repaid.
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        require!(
+            self.flash_loan_x == 0 && self.flash_loan_y == 0,
+            AmmError::FlashLoanActive
+        );
+
+        let x = vault_x.checked_sub(self.protocol_fees_x).ok_or(AmmError::Underflow)?;
+        let y = vault_y.checked_sub(self.protocol_fees_y).ok_or(AmmError::Underflow)?;
+        Ok((x, y))
+    }
+
+    // Swap fee in basis points for a swap at `now`
+    pub fn swap_fee(&self, now: i64) -> u16 {
+        match &self.dynamic_fee {
+            Some(dynamic_fee) => dynamic_fee.effective_fee(now),
+            None => self.fee,
+        }
+    }
+
+    // Prices a swap on reserves `x` and `y` at the fee it leaves behind. A large
+    // swap is charged on the accumulator after its own price move, so it cannot
+    // pay the calm fee for the volatility it causes. `quote` prices the swap at
+    // a given fee, and the fee only ever goes up between rounds.
+    pub fn quote_swap(
+        &self,
+        x: u64,
+        y: u64,
+        is_x: bool,
+        now: i64,
+        quote: impl Fn(u16) -> Result<SwapAmounts>,
+    ) -> Result<(SwapAmounts, u16)> {
+        let mut fee = self.swap_fee(now);
+        let mut swaps = quote(fee)?;
+
+        if let Some(dynamic_fee) = &self.dynamic_fee {
+            for _ in 0..MAX_FEE_ROUNDS {
+                let (price_before, price_after) = price_move(x, y, is_x, swaps.deposit, swaps.withdraw);
+                let charged = dynamic_fee.fee_after_move(price_before, price_after, now);
+                if charged <= fee {
+                    break;
+                }
+                fee = charged;
+                swaps = quote(fee)?;
+            }
+        }
+
+        Ok((swaps, fee))
+    }
+
+    // Feeds the price move of a swap on reserves `x` and `y` into the dynamic fee
+    pub fn record_swap(&mut self, x: u64, y: u64, is_x: bool, amount_in: u64, amount_out: u64, now: i64) {
+        let Some(dynamic_fee) = &mut self.dynamic_fee else {
+            return;
+        };
+
+        let (price_before, price_after) = price_move(x, y, is_x, amount_in, amount_out);
+        dynamic_fee.record_price_move(price_before, price_after, now);
+    }
+
+    // Sets aside the protocol share of a swap fee paid in token X or Y, returns the share
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, fee: u64) -> Result<u64> {
+        let share = (fee as u128)
+            .checked_mul(self.protocol_fee as u128)
+            .ok_or(AmmError::Overflow)?
+            / 10_000;
+        let share = share as u64;
+
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = accrued.checked_add(share).ok_or(AmmError::Overflow)?;
+
+        Ok(share)
+    }
+}
+
+// Q64.64 prices of X in Y before and after a swap on reserves `x` and `y`
+fn price_move(x: u64, y: u64, is_x: bool, amount_in: u64, amount_out: u64) -> (u128, u128) {
+    let (x_after, y_after) = match is_x {
+        true => (x.saturating_add(amount_in), y.saturating_sub(amount_out)),
+        false => (x.saturating_sub(amount_out), y.saturating_add(amount_in)),
+    };
+    match x != 0 && x_after != 0 {
+        true => (q64_price(x, y), q64_price(x_after, y_after)),
+        false => (0, 0),
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct,         // x * y = k
+    StableSwap { amp: u64 }, // Curve StableSwap, amp sets how flat the price is around 1:1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct DynamicFee {
+    pub fee_floor: u16,              // Lowest swap fee, in basis points
+    pub fee_cap: u16,                // Highest swap fee, in basis points
+    pub variable_fee_control: u16,   // Fee added per basis point of volatility, in 1/10_000 of a basis point
+    pub decay_period: u32,           // Seconds without swaps for the volatility to decay to zero
+    pub volatility_accumulator: u64, // Recent price moves, in basis points
+    pub last_update: i64,            // Time of the last swap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dynamic_fee: Option<DynamicFee>) -> Config {
+        Config {
+            seed: 0,
+            authority: None,
+            mint_x: Pubkey::default(),
+            mint_y: Pubkey::default(),
+            fee: 5,
+            fee_tier: 5,
+            locked: false,
+            config_bump: 0,
+            lp_bump: 0,
+            curve: CurveType::StableSwap { amp: 10 },
+            pending_authority: None,
+            protocol_fee: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            dead_bump: 0,
+            flash_loan_x: 0,
+            flash_loan_y: 0,
+            dynamic_fee,
+        }
+    }
+
+    fn quote(config: &Config, amount: u64) -> (SwapAmounts, u16) {
+        let (x, y) = (1_000_000_000, 1_000_000_000);
+        config
+            .quote_swap(x, y, true, 0, |fee| {
+                config.curve.swap(x, y, 1_000_000_000, fee, true, amount, 0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn protocol_takes_its_share_of_the_fee() {
+        let mut config = config(None);
+        config.protocol_fee = 2_000;
+
+        assert_eq!(config.accrue_protocol_fee(true, 1_001).unwrap(), 200);
+        assert_eq!(config.accrue_protocol_fee(false, 50).unwrap(), 10);
+        assert_eq!((config.protocol_fees_x, config.protocol_fees_y), (200, 10));
+    }
+
+    #[test]
+    fn reserves_exclude_protocol_fees() {
+        let mut config = config(None);
+        config.protocol_fees_x = 200;
+        config.protocol_fees_y = 10;
+
+        assert_eq!(config.reserves(1_000, 500).unwrap(), (800, 490));
+        assert!(config.reserves(100, 500).is_err());
+    }
+
+    #[test]
+    fn reserves_are_unavailable_during_a_flash_loan() {
+        let mut config = config(None);
+        config.flash_loan_y = 1;
+
+        assert_eq!(config.reserves(1_000, 500), Err(AmmError::FlashLoanActive.into()));
+    }
+
+    #[test]
+    fn static_fee_is_charged_as_is() {
+        let (_, fee) = quote(&config(None), 500_000_000);
+        assert_eq!(fee, 5);
+    }
+
+    #[test]
+    fn large_swap_pays_for_its_own_move() {
+        // 0.05% to 1%, +1 bp of fee per 10 bps of volatility
+        let config = config(Some(DynamicFee::new(5, 100, 1_000, 600).unwrap()));
+
+        // a small swap barely moves the price and pays the floor
+        let (_, fee) = quote(&config, 10_000);
+        assert_eq!(fee, 5);
+
+        // half the reserve moves the price enough to be priced at the cap
+        let (swaps, fee) = quote(&config, 500_000_000);
+        assert_eq!(fee, 100);
+        assert_eq!(swaps.fee, 5_000_000);
+
+        // the charged fee covers the accumulator after the swap
+        let mut after = config.clone();
+        after.record_swap(1_000_000_000, 1_000_000_000, true, swaps.deposit, swaps.withdraw, 0);
+        assert!(after.swap_fee(0) <= fee);
+    }
 }
@@ -1,3 +1,13 @@
+pub mod cl_pool;
 pub mod config;
+pub mod observation;
+pub mod pool_registry;
+pub mod position;
+pub mod tick_array;
 
+pub use cl_pool::*;
 pub use config::*;
+pub use observation::*;
+pub use pool_registry::*;
+pub use position::*;
+pub use tick_array::*;
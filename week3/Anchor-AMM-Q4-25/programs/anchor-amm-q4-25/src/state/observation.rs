@@ -0,0 +1,275 @@
+use anchor_lang::prelude::*;
+
+// Number of observations kept per pool. The latest one is updated on the first
+// swap, deposit or withdraw in each second.
+pub const OBSERVATION_CAPACITY: usize = 32;
+
+// Seconds an observation must span before the next one takes a new slot. Until
+// then the latest slot is updated in place, so the buffer always covers at least
+// (OBSERVATION_CAPACITY - 1) * MIN_OBSERVATION_INTERVAL seconds, no matter how
+// many small swaps are made to push history out.
+pub const MIN_OBSERVATION_INTERVAL: i64 = 60;
+
+// Cumulative prices are Q64.64 prices summed once per elapsed second. They are
+// allowed to wrap, only differences between two observations are meaningful.
+// They are stored as little-endian u64 words to keep the account 8-byte aligned.
+//
+// Other programs read a pool's TWAP by CPI into `observe_twap`, or
+// `observe_cl_twap` for a concentrated-liquidity pool, and decode the Twap from
+// the return data (`get_return_data`, check the program id). Clients simulate
+// the same instructions. The average runs up to the current time at the current
+// price, so it is fresh even if the pool has not been touched for a while. A
+// window of up to (OBSERVATION_CAPACITY - 1) * MIN_OBSERVATION_INTERVAL seconds
+// is always covered once the pool is that old.
+//
+// The zero-copy structs here and in tick_array have no padding, so they use
+// anchor's Pod impls (`unsafe`) with an explicit repr(C) instead of deriving
+// them through a bytemuck dependency of their own.
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[derive(InitSpace)]
+pub struct Observation {
+    pub timestamp: i64,                // 0 while the slot is unused
+    pub price_x_cumulative: [u64; 2],  // Price of X in Y
+    pub price_y_cumulative: [u64; 2],  // Price of Y in X
+}
+
+impl Observation {
+    pub fn price_x_cumulative(&self) -> u128 {
+        from_words(self.price_x_cumulative)
+    }
+
+    pub fn price_y_cumulative(&self) -> u128 {
+        from_words(self.price_y_cumulative)
+    }
+}
+
+// Ring buffer of price observations for one pool
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+#[derive(InitSpace)]
+pub struct ObservationState {
+    pub config: Pubkey,                // Pool the observations belong to
+    pub index: u16,                    // Slot of the latest observation
+    pub bump: u8,                      // Bump seed for the observation account
+    pub reserved: [u8; 5],
+    pub observations: [Observation; OBSERVATION_CAPACITY],
+}
+
+impl ObservationState {
+    pub fn latest(&self) -> &Observation {
+        &self.observations[self.index as usize]
+    }
+
+    // Accumulates the price `x` and `y` held since the latest observation and
+    // records a new one, in place of the latest while that one is younger than
+    // MIN_OBSERVATION_INTERVAL. Must be called with the reserves from before
+    // the current operation changes them.
+    pub fn update(&mut self, x: u64, y: u64, now: i64) {
+        let (price_x, price_y) = reserve_prices(x, y);
+        self.update_prices(price_x, price_y, now);
+    }
+
+    // Same as `update` for pools that keep a price rather than reserves, given
+    // the Q64.64 prices of X in Y and Y in X held since the latest observation
+    pub fn update_prices(&mut self, price_x: u128, price_y: u128, now: i64) {
+        let latest = *self.latest();
+        if now <= latest.timestamp {
+            return;
+        }
+
+        let elapsed = (now - latest.timestamp) as u128;
+
+        let previous = &self.observations[(self.index as usize + OBSERVATION_CAPACITY - 1) % OBSERVATION_CAPACITY];
+        if previous.timestamp == 0 || latest.timestamp - previous.timestamp >= MIN_OBSERVATION_INTERVAL {
+            self.index = ((self.index as usize + 1) % OBSERVATION_CAPACITY) as u16;
+        }
+
+        self.observations[self.index as usize] = Observation {
+            timestamp: now,
+            price_x_cumulative: to_words(
+                latest.price_x_cumulative().wrapping_add(price_x.wrapping_mul(elapsed)),
+            ),
+            price_y_cumulative: to_words(
+                latest.price_y_cumulative().wrapping_add(price_y.wrapping_mul(elapsed)),
+            ),
+        };
+    }
+
+    // Time-weighted average Q64.64 prices of X in Y and Y in X over at least the
+    // last `window` seconds up to `now`, with the reserves `x` and `y` held since
+    // the latest observation. Starts at the newest observation `window` or more
+    // seconds old. Returns None if the buffer does not reach that far back.
+    pub fn twap(&self, x: u64, y: u64, now: i64, window: u32) -> Option<Twap> {
+        let (price_x, price_y) = reserve_prices(x, y);
+        self.twap_prices(price_x, price_y, now, window)
+    }
+
+    // Same as `twap` for pools that keep a price rather than reserves
+    pub fn twap_prices(&self, price_x: u128, price_y: u128, now: i64, window: u32) -> Option<Twap> {
+        let latest = self.latest();
+        let since_latest = u128::try_from(now.checked_sub(latest.timestamp)?).ok()?;
+        let end_x = latest.price_x_cumulative().wrapping_add(price_x.wrapping_mul(since_latest));
+        let end_y = latest.price_y_cumulative().wrapping_add(price_y.wrapping_mul(since_latest));
+
+        for offset in 0..OBSERVATION_CAPACITY {
+            let index = (self.index as usize + OBSERVATION_CAPACITY - offset) % OBSERVATION_CAPACITY;
+            let start = &self.observations[index];
+            if start.timestamp == 0 {
+                return None;
+            }
+
+            let elapsed = now - start.timestamp;
+            if elapsed >= window.max(1) as i64 {
+                let elapsed = elapsed as u128;
+                return Some(Twap {
+                    price_x: end_x.wrapping_sub(start.price_x_cumulative()) / elapsed,
+                    price_y: end_y.wrapping_sub(start.price_y_cumulative()) / elapsed,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+// Returned by the observe instructions
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Twap {
+    pub price_x: u128, // Q64.64 average price of X in Y
+    pub price_y: u128, // Q64.64 average price of Y in X
+}
+
+// Q64.64 prices of X in Y and Y in X for reserves `x` and `y`
+fn reserve_prices(x: u64, y: u64) -> (u128, u128) {
+    match x == 0 || y == 0 {
+        true => (0, 0),
+        false => (q64_price(x, y), q64_price(y, x)),
+    }
+}
+
+// `quote` per unit of `base`, as a Q64.64 fixed point number
+pub fn q64_price(base: u64, quote: u64) -> u128 {
+    ((quote as u128) << 64) / base as u128
+}
+
+pub(crate) fn from_words(words: [u64; 2]) -> u128 {
+    words[0] as u128 | (words[1] as u128) << 64
+}
+
+pub(crate) fn to_words(value: u128) -> [u64; 2] {
+    [value as u64, (value >> 64) as u64]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(start: i64) -> ObservationState {
+        let mut state = ObservationState {
+            config: Pubkey::default(),
+            index: 0,
+            bump: 0,
+            reserved: [0; 5],
+            observations: [Observation {
+                timestamp: 0,
+                price_x_cumulative: [0; 2],
+                price_y_cumulative: [0; 2],
+            }; OBSERVATION_CAPACITY],
+        };
+        state.observations[0].timestamp = start;
+        state
+    }
+
+    #[test]
+    fn constant_price_twap_is_spot() {
+        let mut state = state(1_000);
+        for now in (1_010..1_100).step_by(10) {
+            state.update(1_000, 2_000, now);
+        }
+
+        let twap = state.twap(1_000, 2_000, 1_090, 50).unwrap();
+        assert_eq!(twap.price_x, q64_price(1_000, 2_000));
+        assert_eq!(twap.price_y, q64_price(2_000, 1_000));
+        assert_eq!(twap.price_x >> 64, 2);
+    }
+
+    #[test]
+    fn twap_weights_prices_by_time() {
+        let mut state = state(0);
+        state.observations[0].timestamp = 100;
+
+        // 1:1 for 30 seconds, then 1:4 for 10 seconds
+        state.update(1_000, 1_000, 130);
+        state.update(1_000, 4_000, 140);
+
+        let twap = state.twap(1_000, 9_000, 140, 40).unwrap();
+        assert_eq!(twap.price_x, (30 * q64_price(1_000, 1_000) + 10 * q64_price(1_000, 4_000)) / 40);
+    }
+
+    #[test]
+    fn twap_runs_up_to_now() {
+        let mut state = state(1_000);
+        state.update(1_000, 1_000, 1_060);
+
+        // nothing has touched the pool since the price moved to 1:4
+        let twap = state.twap(1_000, 4_000, 1_120, 120).unwrap();
+        assert_eq!(twap.price_x, (60 * q64_price(1_000, 1_000) + 60 * q64_price(1_000, 4_000)) / 120);
+
+        // a window shorter than the time since the latest observation is the spot price
+        let twap = state.twap(1_000, 4_000, 1_120, 30).unwrap();
+        assert_eq!(twap.price_x, q64_price(1_000, 4_000));
+
+        assert!(state.twap(1_000, 4_000, 1_059, 10).is_none());
+    }
+
+    #[test]
+    fn same_second_updates_are_ignored() {
+        let mut state = state(1_000);
+        state.update(1_000, 2_000, 1_010);
+        state.update(1, 1_000_000, 1_010);
+
+        assert_eq!(state.index, 1);
+        assert_eq!(state.twap(1_000, 2_000, 1_010, 10).unwrap().price_x, q64_price(1_000, 2_000));
+    }
+
+    #[test]
+    fn twap_needs_enough_history() {
+        let mut state = state(1_000);
+        state.update(1_000, 2_000, 1_010);
+
+        assert!(state.twap(1_000, 2_000, 1_010, 10).is_some());
+        assert!(state.twap(1_000, 2_000, 1_010, 11).is_none());
+    }
+
+    #[test]
+    fn ring_buffer_wraps() {
+        let mut state = state(1);
+        let span = (OBSERVATION_CAPACITY as i64 - 1) * MIN_OBSERVATION_INTERVAL;
+        for step in 1..=2 * OBSERVATION_CAPACITY as i64 {
+            state.update(1_000, 3_000, 1 + step * MIN_OBSERVATION_INTERVAL);
+        }
+
+        let now = 1 + 2 * OBSERVATION_CAPACITY as i64 * MIN_OBSERVATION_INTERVAL;
+        assert_eq!(state.latest().timestamp, now);
+        assert!(state.twap(1_000, 3_000, now, span as u32).is_some());
+        assert!(state.twap(1_000, 3_000, now, span as u32 + 1).is_none());
+    }
+
+    #[test]
+    fn frequent_updates_do_not_evict_history() {
+        let mut state = state(1_000);
+        state.update(1_000, 2_000, 1_100);
+
+        // a dust swap every second for longer than the buffer has slots
+        for now in 1_101..1_101 + 4 * OBSERVATION_CAPACITY as i64 {
+            state.update(1_000, 2_000, now);
+        }
+
+        // two minutes of updates take one slot per minute, the start survives
+        assert_eq!(state.observations[0].timestamp, 1_000);
+        assert!(state.index <= 4);
+        let now = 1_100 + 4 * OBSERVATION_CAPACITY as i64;
+        assert_eq!(state.twap(1_000, 2_000, now, 200).unwrap().price_x, q64_price(1_000, 2_000));
+    }
+}
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::AmmError;
+
+// One per mint pair and fee tier, at [b"pool", mint_x, mint_y, fee_tier] for
+// curve pools and [b"cl_registry", mint_x, mint_y, fee_tier] for
+// concentrated-liquidity pools, so clients can find the pool for a pair without
+// knowing its seed and a pair can only be listed once per fee tier of each kind
+#[account]
+#[derive(InitSpace)]
+pub struct PoolRegistry {
+    pub mint_x: Pubkey,  // Lower of the two mints
+    pub mint_y: Pubkey,  // Higher of the two mints
+    pub fee_tier: u16,   // Swap fee the pool was created with, in basis points
+    pub config: Pubkey,  // Pool config, or ClPool account, for the pair and fee tier
+    pub bump: u8,        // Bump seed for the registry account
+}
+
+impl PoolRegistry {
+    // Pairs are stored in canonical order, mint_x sorting before mint_y
+    pub fn check_pair(mint_x: &Pubkey, mint_y: &Pubkey) -> Result<()> {
+        require_keys_neq!(*mint_x, *mint_y, AmmError::IdenticalMints);
+        require!(mint_x < mint_y, AmmError::InvalidMintOrder);
+        Ok(())
+    }
+}
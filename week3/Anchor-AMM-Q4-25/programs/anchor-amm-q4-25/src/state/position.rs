@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+use ethnum::U256;
+
+use crate::errors::AmmError;
+
+// Liquidity an owner provides to a ClPool over [tick_lower, tick_upper), at
+// [b"position", pool, owner, tick_lower, tick_upper]
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub pool: Pubkey,                    // Pool the position belongs to
+    pub owner: Pubkey,                   // Only signer allowed to change the position
+    pub tick_lower: i32,                 // Lower bound of the range, inclusive
+    pub tick_upper: i32,                 // Upper bound of the range, exclusive
+    pub liquidity: u128,                 // Liquidity provided over the range
+    pub fee_growth_inside_x_last: u128,  // Fee growth inside the range when fees were last settled
+    pub fee_growth_inside_y_last: u128,
+    pub fees_owed_x: u64,                // Settled fees not collected yet
+    pub fees_owed_y: u64,
+    pub bump: u8,                        // Bump seed for the position account
+}
+
+impl Position {
+    // Settles the fees earned since the last update and applies a liquidity change
+    pub fn update(&mut self, liquidity_delta: i128, fee_growth_inside_x: u128, fee_growth_inside_y: u128) -> Result<()> {
+        let earned_x = fees_earned(fee_growth_inside_x, self.fee_growth_inside_x_last, self.liquidity)
+            .ok_or(AmmError::Overflow)?;
+        let earned_y = fees_earned(fee_growth_inside_y, self.fee_growth_inside_y_last, self.liquidity)
+            .ok_or(AmmError::Overflow)?;
+
+        self.fees_owed_x = self.fees_owed_x.checked_add(earned_x).ok_or(AmmError::Overflow)?;
+        self.fees_owed_y = self.fees_owed_y.checked_add(earned_y).ok_or(AmmError::Overflow)?;
+        self.fee_growth_inside_x_last = fee_growth_inside_x;
+        self.fee_growth_inside_y_last = fee_growth_inside_y;
+
+        self.liquidity = self
+            .liquidity
+            .checked_add_signed(liquidity_delta)
+            .ok_or(AmmError::InsufficientBalance)?;
+
+        Ok(())
+    }
+}
+
+// Fee growth is allowed to wrap, only the difference since the last update counts
+fn fees_earned(fee_growth: u128, fee_growth_last: u128, liquidity: u128) -> Option<u64> {
+    let growth = fee_growth.wrapping_sub(fee_growth_last);
+    let earned = (U256::from(growth) * U256::from(liquidity)) >> 64u32;
+    u64::try_from(earned).ok()
+}
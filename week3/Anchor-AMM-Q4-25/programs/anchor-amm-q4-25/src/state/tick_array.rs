@@ -0,0 +1,255 @@
+use anchor_lang::prelude::*;
+
+use super::observation::{from_words, to_words};
+use crate::errors::AmmError;
+
+// Ticks per TickArray account. An array at [b"tick_array", pool, start_tick_index]
+// covers TICK_ARRAY_SIZE * tick_spacing ticks starting at start_tick_index.
+pub const TICK_ARRAY_SIZE: usize = 64;
+
+// u128 and i128 values are stored as little-endian u64 words, like Observation
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[derive(InitSpace, Default)]
+pub struct Tick {
+    pub liquidity_net: [u64; 2],         // Liquidity added when the price crosses the tick upwards
+    pub liquidity_gross: [u64; 2],       // Liquidity of all positions bounded by the tick, 0 if unused
+    pub fee_growth_outside_x: [u64; 2],  // Fee growth on the other side of the tick from the price
+    pub fee_growth_outside_y: [u64; 2],
+}
+
+impl Tick {
+    pub fn liquidity_net(&self) -> i128 {
+        from_words(self.liquidity_net) as i128
+    }
+
+    pub fn liquidity_gross(&self) -> u128 {
+        from_words(self.liquidity_gross)
+    }
+
+    pub fn fee_growth_outside_x(&self) -> u128 {
+        from_words(self.fee_growth_outside_x)
+    }
+
+    pub fn fee_growth_outside_y(&self) -> u128 {
+        from_words(self.fee_growth_outside_y)
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.liquidity_gross() != 0
+    }
+
+    // Applies a position's liquidity change to one of its bounds. Fee growth before a
+    // tick is first used is assumed to have happened below it, as in Uniswap v3.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(
+        &mut self,
+        tick: i32,
+        tick_current: i32,
+        liquidity_delta: i128,
+        upper: bool,
+        fee_growth_global_x: u128,
+        fee_growth_global_y: u128,
+    ) -> Result<()> {
+        let gross = self
+            .liquidity_gross()
+            .checked_add_signed(liquidity_delta)
+            .ok_or(AmmError::InsufficientBalance)?;
+
+        if !self.is_initialized() {
+            let (outside_x, outside_y) = match tick <= tick_current {
+                true => (fee_growth_global_x, fee_growth_global_y),
+                false => (0, 0),
+            };
+            self.fee_growth_outside_x = to_words(outside_x);
+            self.fee_growth_outside_y = to_words(outside_y);
+        }
+
+        let net = match upper {
+            true => self.liquidity_net().checked_sub(liquidity_delta),
+            false => self.liquidity_net().checked_add(liquidity_delta),
+        };
+        self.liquidity_net = to_words(net.ok_or(AmmError::Overflow)? as u128);
+        self.liquidity_gross = to_words(gross);
+
+        Ok(())
+    }
+
+    // Called when the price moves across the tick, returns liquidity_net
+    pub fn cross(&mut self, fee_growth_global_x: u128, fee_growth_global_y: u128) -> i128 {
+        self.fee_growth_outside_x = to_words(fee_growth_global_x.wrapping_sub(self.fee_growth_outside_x()));
+        self.fee_growth_outside_y = to_words(fee_growth_global_y.wrapping_sub(self.fee_growth_outside_y()));
+        self.liquidity_net()
+    }
+}
+
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+#[derive(InitSpace)]
+pub struct TickArray {
+    pub pool: Pubkey,            // Pool the ticks belong to
+    pub start_tick_index: i32,   // First tick of the array, a multiple of TICK_ARRAY_SIZE * tick_spacing
+    pub reserved: [u8; 4],
+    pub ticks: [Tick; TICK_ARRAY_SIZE],
+}
+
+impl TickArray {
+    // Start of the array holding `tick`
+    pub fn start_index(tick: i32, tick_spacing: u16) -> i32 {
+        let span = tick_spacing as i32 * TICK_ARRAY_SIZE as i32;
+        tick.div_euclid(span) * span
+    }
+
+    pub fn contains(&self, tick: i32, tick_spacing: u16) -> bool {
+        Self::start_index(tick, tick_spacing) == self.start_tick_index
+    }
+
+    fn offset(&self, tick: i32, tick_spacing: u16) -> Result<usize> {
+        require!(
+            self.contains(tick, tick_spacing) && tick % tick_spacing as i32 == 0,
+            AmmError::InvalidTickArray
+        );
+        Ok(((tick - self.start_tick_index) / tick_spacing as i32) as usize)
+    }
+
+    pub fn tick(&self, tick: i32, tick_spacing: u16) -> Result<&Tick> {
+        Ok(&self.ticks[self.offset(tick, tick_spacing)?])
+    }
+
+    pub fn tick_mut(&mut self, tick: i32, tick_spacing: u16) -> Result<&mut Tick> {
+        let offset = self.offset(tick, tick_spacing)?;
+        Ok(&mut self.ticks[offset])
+    }
+
+    // Next initialized tick from `tick`, which has to be in the array: at or below it
+    // when moving down, above it when moving up. When there is none the array bound
+    // is returned instead, with false.
+    pub fn next_initialized_tick(&self, tick: i32, tick_spacing: u16, down: bool) -> (i32, bool) {
+        let spacing = tick_spacing as i32;
+        let offset = (tick - self.start_tick_index).div_euclid(spacing);
+
+        let found = match down {
+            true => (0..=offset).rev().find(|i| self.ticks[*i as usize].is_initialized()),
+            false => (offset + 1..TICK_ARRAY_SIZE as i32).find(|i| self.ticks[*i as usize].is_initialized()),
+        };
+
+        match (found, down) {
+            (Some(i), _) => (self.start_tick_index + i * spacing, true),
+            (None, true) => (self.start_tick_index, false),
+            (None, false) => (self.start_tick_index + TICK_ARRAY_SIZE as i32 * spacing, false),
+        }
+    }
+}
+
+// Loads a tick array passed through remaining_accounts, it has to belong to `pool`
+pub fn load_tick_array<'info>(
+    account: &'info AccountInfo<'info>,
+    pool: &Pubkey,
+) -> Result<AccountLoader<'info, TickArray>> {
+    let tick_array = AccountLoader::<TickArray>::try_from(account)?;
+    require_keys_eq!(tick_array.load()?.pool, *pool, AmmError::InvalidTickArray);
+    Ok(tick_array)
+}
+
+// Fee growth per unit of liquidity earned inside [tick_lower, tick_upper)
+pub fn fee_growth_inside(
+    lower: &Tick,
+    upper: &Tick,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_current: i32,
+    fee_growth_global_x: u128,
+    fee_growth_global_y: u128,
+) -> (u128, u128) {
+    let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+        let below = match tick_current >= tick_lower {
+            true => lower_outside,
+            false => global.wrapping_sub(lower_outside),
+        };
+        let above = match tick_current < tick_upper {
+            true => upper_outside,
+            false => global.wrapping_sub(upper_outside),
+        };
+        global.wrapping_sub(below).wrapping_sub(above)
+    };
+
+    (
+        inside(fee_growth_global_x, lower.fee_growth_outside_x(), upper.fee_growth_outside_x()),
+        inside(fee_growth_global_y, lower.fee_growth_outside_y(), upper.fee_growth_outside_y()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACING: u16 = 10;
+
+    fn array(start_tick_index: i32) -> TickArray {
+        TickArray {
+            pool: Pubkey::default(),
+            start_tick_index,
+            reserved: [0; 4],
+            ticks: [Tick::default(); TICK_ARRAY_SIZE],
+        }
+    }
+
+    #[test]
+    fn start_index_rounds_towards_negative_infinity() {
+        assert_eq!(TickArray::start_index(0, SPACING), 0);
+        assert_eq!(TickArray::start_index(639, SPACING), 0);
+        assert_eq!(TickArray::start_index(640, SPACING), 640);
+        assert_eq!(TickArray::start_index(-1, SPACING), -640);
+        assert_eq!(TickArray::start_index(-640, SPACING), -640);
+    }
+
+    #[test]
+    fn finds_the_next_initialized_tick_in_each_direction() {
+        let mut ticks = array(-640);
+        ticks.tick_mut(-300, SPACING).unwrap().update(-300, 0, 5, false, 0, 0).unwrap();
+        ticks.tick_mut(-100, SPACING).unwrap().update(-100, 0, 5, true, 0, 0).unwrap();
+
+        assert_eq!(ticks.next_initialized_tick(-1, SPACING, true), (-100, true));
+        assert_eq!(ticks.next_initialized_tick(-100, SPACING, true), (-100, true));
+        assert_eq!(ticks.next_initialized_tick(-101, SPACING, true), (-300, true));
+        assert_eq!(ticks.next_initialized_tick(-301, SPACING, true), (-640, false));
+
+        assert_eq!(ticks.next_initialized_tick(-640, SPACING, false), (-300, true));
+        assert_eq!(ticks.next_initialized_tick(-300, SPACING, false), (-100, true));
+        assert_eq!(ticks.next_initialized_tick(-100, SPACING, false), (0, false));
+    }
+
+    #[test]
+    fn ticks_track_net_liquidity_and_can_be_reused() {
+        let mut tick = Tick::default();
+        tick.update(0, 10, 100, false, 7, 9).unwrap();
+        tick.update(0, 10, 50, true, 8, 8).unwrap();
+
+        assert_eq!(tick.liquidity_net(), 50);
+        assert_eq!(tick.liquidity_gross(), 150);
+        // initialized below the price, so all fees so far count as outside
+        assert_eq!((tick.fee_growth_outside_x(), tick.fee_growth_outside_y()), (7, 9));
+
+        tick.update(0, 10, -100, false, 8, 8).unwrap();
+        tick.update(0, 10, -50, true, 8, 8).unwrap();
+        assert!(!tick.is_initialized());
+        assert_eq!(tick.liquidity_net(), 0);
+
+        // reused above the price, so no fees count as outside
+        tick.update(0, -10, 1, false, 8, 8).unwrap();
+        assert_eq!((tick.fee_growth_outside_x(), tick.fee_growth_outside_y()), (0, 0));
+    }
+
+    #[test]
+    fn fee_growth_inside_excludes_fees_earned_outside_the_range() {
+        let (mut lower, mut upper) = (Tick::default(), Tick::default());
+        // range [-10, 10) opened at tick 0 with 100 of fee growth already earned
+        lower.update(-10, 0, 1, false, 100, 100).unwrap();
+        upper.update(10, 0, 1, true, 100, 100).unwrap();
+        assert_eq!(fee_growth_inside(&lower, &upper, -10, 10, 0, 100, 100), (0, 0));
+
+        // 30 earned in range, then the price moves above and 50 more is earned there
+        upper.cross(130, 130);
+        assert_eq!(fee_growth_inside(&lower, &upper, -10, 10, 10, 180, 180), (30, 30));
+    }
+}
@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token,
+    token_2022::spl_token_2022::{
+        extension::{
+            transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+            StateWithExtensions,
+        },
+        state::Mint as MintState,
+    },
+    token_interface::Mint,
+};
+
+use crate::errors::AmmError;
+
+// Token-2022 mint extensions a pool can list. Anything that lets a third party
+// move, freeze or hook into vault transfers is rejected.
+pub const SUPPORTED_MINT_EXTENSIONS: [ExtensionType; 4] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+];
+
+pub fn validate_mint(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner == token::ID {
+        return Ok(());
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    validate_mint_data(&data)
+}
+
+// Amount to send so that `amount` arrives after the mint's transfer fee
+pub fn amount_with_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner == token::ID {
+        return Ok(amount);
+    }
+
+    let data = mint_info.try_borrow_data()?;
+    amount_with_transfer_fee_at(&data, Clock::get()?.epoch, amount)
+}
+
+fn validate_mint_data(data: &[u8]) -> Result<()> {
+    let state = StateWithExtensions::<MintState>::unpack(data)?;
+
+    for extension in state.get_extension_types()? {
+        require!(
+            SUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            AmmError::UnsupportedMintExtension
+        );
+    }
+
+    Ok(())
+}
+
+fn amount_with_transfer_fee_at(data: &[u8], epoch: u64, amount: u64) -> Result<u64> {
+    let state = StateWithExtensions::<MintState>::unpack(data)?;
+
+    let fee = match state.get_extension::<TransferFeeConfig>() {
+        Ok(transfer_fee) => transfer_fee
+            .calculate_inverse_epoch_fee(epoch, amount)
+            .ok_or(AmmError::Overflow)?,
+        Err(_) => 0,
+    };
+
+    Ok(amount.checked_add(fee).ok_or(AmmError::Overflow)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_spl::token_2022::spl_token_2022::extension::{
+        permanent_delegate::PermanentDelegate, BaseStateWithExtensionsMut, StateWithExtensionsMut,
+    };
+
+    use super::*;
+
+    // Initialized Token-2022 mint holding `extension`, which `init` sets up
+    fn mint_data(extension: ExtensionType, init: impl FnOnce(&mut StateWithExtensionsMut<MintState>)) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<MintState>(&[extension]).unwrap();
+        let mut data = vec![0; len];
+
+        let mut state = StateWithExtensionsMut::<MintState>::unpack_uninitialized(&mut data).unwrap();
+        init(&mut state);
+        state.base.decimals = 6;
+        state.base.is_initialized = true;
+        state.pack_base();
+        state.init_account_type().unwrap();
+
+        data
+    }
+
+    // 1% transfer fee, capped at 5 tokens
+    fn transfer_fee_mint() -> Vec<u8> {
+        mint_data(ExtensionType::TransferFeeConfig, |state| {
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.newer_transfer_fee.transfer_fee_basis_points = 100.into();
+            config.newer_transfer_fee.maximum_fee = 5_000_000.into();
+        })
+    }
+
+    #[test]
+    fn transfer_fee_mints_are_listed() {
+        assert!(validate_mint_data(&transfer_fee_mint()).is_ok());
+    }
+
+    #[test]
+    fn permanent_delegate_mints_are_rejected() {
+        let data = mint_data(ExtensionType::PermanentDelegate, |state| {
+            let delegate = state.init_extension::<PermanentDelegate>(true).unwrap();
+            delegate.delegate = Some(Pubkey::new_unique()).try_into().unwrap();
+        });
+
+        assert_eq!(validate_mint_data(&data), Err(AmmError::UnsupportedMintExtension.into()));
+    }
+
+    #[test]
+    fn sends_enough_to_cover_the_transfer_fee() {
+        let data = transfer_fee_mint();
+
+        // 100 sent, 1 withheld, 99 arrive
+        assert_eq!(amount_with_transfer_fee_at(&data, 0, 99).unwrap(), 100);
+        // the fee is capped for large transfers
+        assert_eq!(amount_with_transfer_fee_at(&data, 0, 1_000_000_000).unwrap(), 1_005_000_000);
+    }
+}
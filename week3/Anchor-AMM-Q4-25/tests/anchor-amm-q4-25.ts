@@ -1,10 +1,24 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { AnchorAmmQ425 } from "../target/types/anchor_amm_q4_25";
-import { Keypair, PublicKey } from "@solana/web3.js";
+import {
+  Keypair,
+  PublicKey,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { assert } from "chai";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
+  ExtensionType,
+  TOKEN_2022_PROGRAM_ID,
   TOKEN_PROGRAM_ID,
+  createInitializeMintInstruction,
+  createInitializePermanentDelegateInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  getMintLen,
   createMint,
   getAssociatedTokenAddressSync,
   createAccount,
@@ -36,6 +50,209 @@ describe("anchor-amm-q4-25", () => {
     program.programId
   );
 
+  const [observation] = PublicKey.findProgramAddressSync(
+    [Buffer.from("observation"), config.toBuffer()],
+    program.programId
+  );
+
+  const [dead] = PublicKey.findProgramAddressSync(
+    [Buffer.from("dead"), config.toBuffer()],
+    program.programId
+  );
+  const lockedLp = getAssociatedTokenAddressSync(mintLp, dead, true);
+
+  // Token-2022 mint with one extension, `initExtension` runs before the mint is initialized
+  const createMint2022 = async (extension: ExtensionType, initExtension: (mint: PublicKey) => TransactionInstruction) => {
+    const mint = Keypair.generate();
+    const space = getMintLen([extension]);
+    const lamports = await connection.getMinimumBalanceForRentExemption(space);
+
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: wallet.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        initExtension(mint.publicKey),
+        createInitializeMintInstruction(mint.publicKey, 6, wallet.publicKey, null, TOKEN_2022_PROGRAM_ID)
+      ),
+      [mint]
+    );
+    return mint.publicKey;
+  };
+
+  // Addresses of the curve pool at `poolSeed`, mints in canonical order
+  const curvePool = (
+    poolSeed: anchor.BN,
+    poolMintX: PublicKey,
+    poolMintY: PublicKey,
+    poolFee: number,
+    tokenProgramX = TOKEN_PROGRAM_ID,
+    tokenProgramY = TOKEN_PROGRAM_ID
+  ) => {
+    const [poolConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), poolSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [poolMintLp] = PublicKey.findProgramAddressSync([Buffer.from("lp"), poolConfig.toBuffer()], program.programId);
+    const [poolDead] = PublicKey.findProgramAddressSync([Buffer.from("dead"), poolConfig.toBuffer()], program.programId);
+    const [poolObservation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("observation"), poolConfig.toBuffer()],
+      program.programId
+    );
+    const [poolRegistry] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("pool"),
+        poolMintX.toBuffer(),
+        poolMintY.toBuffer(),
+        new anchor.BN(poolFee).toArrayLike(Buffer, "le", 2),
+      ],
+      program.programId
+    );
+
+    return {
+      mintX: poolMintX,
+      mintY: poolMintY,
+      config: poolConfig,
+      mintLp: poolMintLp,
+      observation: poolObservation,
+      registry: poolRegistry,
+      dead: poolDead,
+      lockedLp: getAssociatedTokenAddressSync(poolMintLp, poolDead, true),
+      vaultX: getAssociatedTokenAddressSync(poolMintX, poolConfig, true, tokenProgramX),
+      vaultY: getAssociatedTokenAddressSync(poolMintY, poolConfig, true, tokenProgramY),
+      tokenProgramX,
+      tokenProgramY,
+    };
+  };
+
+  const initializeAccounts = (pool: ReturnType<typeof curvePool>) => ({
+    initializer: wallet.publicKey,
+    mintX: pool.mintX,
+    mintY: pool.mintY,
+    mintLp: pool.mintLp,
+    vaultX: pool.vaultX,
+    vaultY: pool.vaultY,
+    config: pool.config,
+    registry: pool.registry,
+    observation: pool.observation,
+    dead: pool.dead,
+    lockedLp: pool.lockedLp,
+    tokenProgram: TOKEN_PROGRAM_ID,
+    tokenProgramX: pool.tokenProgramX,
+    tokenProgramY: pool.tokenProgramY,
+    associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    systemProgram: anchor.web3.SystemProgram.programId,
+  });
+
+  // SwapExecuted events emitted by a transaction
+  const swapEvents = async (signature: string) => {
+    await connection.confirmTransaction(signature, "confirmed");
+    const tx = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return [...parser.parseLogs(tx.meta.logMessages)]
+      .filter((event) => event.name === "swapExecuted")
+      .map((event) => event.data);
+  };
+
+  // The nine accounts route_swap takes per pool through remaining_accounts
+  const routeAccounts = (pool: ReturnType<typeof curvePool>) =>
+    [
+      [pool.config, true],
+      [pool.mintLp, false],
+      [pool.vaultX, true],
+      [pool.vaultY, true],
+      [pool.mintX, false],
+      [pool.mintY, false],
+      [pool.tokenProgramX, false],
+      [pool.tokenProgramY, false],
+      [pool.observation, true],
+    ].map(([pubkey, isWritable]: [PublicKey, boolean]) => ({ pubkey, isSigner: false, isWritable }));
+
+  // Accounts deposit, withdraw and swap take for a classic-token curve pool
+  const poolAccounts = (pool: ReturnType<typeof curvePool>) => {
+    const swap = {
+      user: wallet.publicKey,
+      mintX: pool.mintX,
+      mintY: pool.mintY,
+      config: pool.config,
+      observation: pool.observation,
+      mintLp: pool.mintLp,
+      vaultX: pool.vaultX,
+      vaultY: pool.vaultY,
+      userX: getAssociatedTokenAddressSync(pool.mintX, wallet.publicKey),
+      userY: getAssociatedTokenAddressSync(pool.mintY, wallet.publicKey),
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    };
+    const withdraw = {
+      ...swap,
+      userLp: getAssociatedTokenAddressSync(pool.mintLp, wallet.publicKey),
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+    const deposit = { ...withdraw, dead: pool.dead, lockedLp: pool.lockedLp };
+    return { swap, withdraw, deposit };
+  };
+
+  // Two new pools with the same 100/100 reserves and fee, both pairing a new
+  // mint A with another new mint, so one path can be compared against another
+  const twinPools = async (seeds: [number, number]) => {
+    const newMint = async () => {
+      const mint = await createMint(connection, wallet.payer, wallet.publicKey, null, 6);
+      const account = await createAccount(connection, wallet.payer, mint, wallet.publicKey);
+      await mintTo(connection, wallet.payer, mint, account, wallet.publicKey, 1_000_000_000);
+      return mint;
+    };
+
+    const mintA = await newMint();
+    const pools = [];
+    for (const poolSeed of seeds.map((value) => new anchor.BN(value))) {
+      const other = await newMint();
+      const pool =
+        Buffer.compare(mintA.toBuffer(), other.toBuffer()) < 0
+          ? curvePool(poolSeed, mintA, other, 30)
+          : curvePool(poolSeed, other, mintA, 30);
+      const accounts = poolAccounts(pool);
+
+      await program.methods
+        .initialize(poolSeed, 30, null, { constantProduct: {} })
+        .accountsStrict(initializeAccounts(pool))
+        .rpc();
+      await program.methods
+        .deposit(new anchor.BN(1), new anchor.BN(100_000_000), new anchor.BN(100_000_000))
+        .accountsStrict(accounts.deposit)
+        .rpc();
+
+      const aIsX = pool.mintX.equals(mintA);
+      pools.push({
+        pool,
+        aIsX,
+        ...accounts,
+        userA: getAssociatedTokenAddressSync(mintA, wallet.publicKey),
+        userOther: getAssociatedTokenAddressSync(other, wallet.publicKey),
+        vaultA: aIsX ? pool.vaultX : pool.vaultY,
+        vaultOther: aIsX ? pool.vaultY : pool.vaultX,
+      });
+    }
+    return pools;
+  };
+
+  const balance = async (account: PublicKey) => (await getAccount(connection, account)).amount;
+
+  // Curve pool of `mint2022` against mint X, mints put in canonical order
+  const token2022Pool = (poolSeed: anchor.BN, mint2022: PublicKey, poolFee: number) =>
+    Buffer.compare(mint2022.toBuffer(), mintX.toBuffer()) < 0
+      ? curvePool(poolSeed, mint2022, mintX, poolFee, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID)
+      : curvePool(poolSeed, mintX, mint2022, poolFee, TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID);
+
   let mintX: PublicKey;
   let mintY: PublicKey;
   let vaultX: PublicKey;
@@ -43,6 +260,7 @@ describe("anchor-amm-q4-25", () => {
   let userX: PublicKey;
   let userY: PublicKey;
   let userLp: PublicKey;
+  let registry: PublicKey;
 
   before(async () => {
     // Create mint X
@@ -63,6 +281,16 @@ describe("anchor-amm-q4-25", () => {
       6
     );
 
+    // Pools are listed with their mints in canonical order
+    if (Buffer.compare(mintX.toBuffer(), mintY.toBuffer()) > 0) {
+      [mintX, mintY] = [mintY, mintX];
+    }
+
+    [registry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), mintX.toBuffer(), mintY.toBuffer(), new anchor.BN(fee).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+
     // Calculate vault addresses
     vaultX = getAssociatedTokenAddressSync(mintX, config, true);
     vaultY = getAssociatedTokenAddressSync(mintY, config, true);
@@ -89,7 +317,7 @@ describe("anchor-amm-q4-25", () => {
 
   it("Initialize AMM", async () => {
     const tx = await program.methods
-      .initialize(seed, fee, null)
+      .initialize(seed, fee, null, { constantProduct: {} })
       .accountsStrict({
         initializer: wallet.publicKey,
         mintX: mintX,
@@ -98,7 +326,13 @@ describe("anchor-amm-q4-25", () => {
         vaultX: vaultX,
         vaultY: vaultY,
         config: config,
+        registry: registry,
+        observation: observation,
+        dead: dead,
+        lockedLp: lockedLp,
         tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -115,10 +349,14 @@ describe("anchor-amm-q4-25", () => {
     console.log("  Locked:", configAccount.locked);
     console.log("  Mint X:", configAccount.mintX.toBase58());
     console.log("  Mint Y:", configAccount.mintY.toBase58());
+
+    const registryAccount = await program.account.poolRegistry.fetch(registry);
+    console.log("  Registry config:", registryAccount.config.toBase58());
   });
 
   it("Deposit liquidity", async () => {
-    const depositAmount = new anchor.BN(100_000_000); // 100 tokens
+    // sqrt(100 * 100) LP tokens, less the 1_000 locked on the first deposit
+    const depositAmount = new anchor.BN(100_000_000 - 1_000);
     const maxX = new anchor.BN(100_000_000);
     const maxY = new anchor.BN(100_000_000);
 
@@ -137,13 +375,18 @@ describe("anchor-amm-q4-25", () => {
         mintX: mintX,
         mintY: mintY,
         config: config,
+        observation: observation,
         mintLp: mintLp,
         vaultX: vaultX,
         vaultY: vaultY,
         userX: userX,
         userY: userY,
         userLp: userLp,
+        dead: dead,
+        lockedLp: lockedLp,
         tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       })
@@ -191,12 +434,14 @@ describe("anchor-amm-q4-25", () => {
         mintX: mintX,
         mintY: mintY,
         config: config,
+        observation: observation,
         mintLp: mintLp,
         vaultX: vaultX,
         vaultY: vaultY,
         userX: userX,
         userY: userY,
-        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       })
@@ -219,6 +464,46 @@ describe("anchor-amm-q4-25", () => {
     console.log("  Vault Y:", Number(vaultYAfter.amount) / 1e6, "tokens");
   });
 
+  it("Flash loans and repays in one transaction", async () => {
+    const accounts = {
+      user: wallet.publicKey,
+      mintX: mintX,
+      mintY: mintY,
+      config: config,
+      vaultX: vaultX,
+      vaultY: vaultY,
+      userX: userX,
+      userY: userY,
+      instructionSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    };
+    const vaultXBefore = await getAccount(connection, vaultX);
+
+    const repay = await program.methods.flashRepay().accountsStrict(accounts).instruction();
+    const tx = await program.methods
+      .flashLoan(new anchor.BN(10_000_000), new anchor.BN(0))
+      .accountsStrict(accounts)
+      .postInstructions([repay])
+      .rpc();
+
+    const vaultXAfter = await getAccount(connection, vaultX);
+    console.log("\n⚡ Flash Loan");
+    console.log("Transaction signature:", tx);
+    console.log("  Fee paid to vault X:", Number(vaultXAfter.amount - vaultXBefore.amount) / 1e6, "tokens");
+
+    try {
+      await program.methods
+        .flashLoan(new anchor.BN(10_000_000), new anchor.BN(0))
+        .accountsStrict(accounts)
+        .rpc();
+      throw new Error("flash loan without repay should have failed");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      console.log("  Unrepaid loan rejected:", err.error.errorCode.code);
+    }
+  });
+
   it("Withdraw liquidity", async () => {
     const lpToBurn = new anchor.BN(50_000_000); // Burn 50 LP tokens
     const minX = new anchor.BN(40_000_000); // Minimum 40 tokens X out
@@ -241,6 +526,7 @@ describe("anchor-amm-q4-25", () => {
         mintX: mintX,
         mintY: mintY,
         config: config,
+        observation: observation,
         mintLp: mintLp,
         vaultX: vaultX,
         vaultY: vaultY,
@@ -248,6 +534,8 @@ describe("anchor-amm-q4-25", () => {
         userY: userY,
         userLp: userLp,
         tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
       })
@@ -272,4 +560,720 @@ describe("anchor-amm-q4-25", () => {
     console.log("  Vault Y:", Number(vaultYAfter.amount) / 1e6, "tokens");
     console.log("  Total LP Supply:", Number(lpMintAfter.supply) / 1e6, "tokens");
   });
-});
\ No newline at end of file
+
+  it("Reads the pool TWAP up to the current time", async () => {
+    const accounts = {
+      mintX: mintX,
+      mintY: mintY,
+      config: config,
+      observation: observation,
+      vaultX: vaultX,
+      vaultY: vaultY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    };
+
+    // Q64.64 prices averaged since the pool was created
+    const twap = await program.methods.observeTwap(1).accountsStrict(accounts).view();
+    assert.isTrue(twap.priceX.gtn(0) && twap.priceY.gtn(0));
+    console.log("\n📈 TWAP price of X in Y:", Number(twap.priceX.shrn(32)) / 2 ** 32);
+
+    try {
+      await program.methods.observeTwap(24 * 60 * 60).accountsStrict(accounts).rpc();
+      throw new Error("a window older than the pool should have failed");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      assert.equal(err.error.errorCode.code, "InsufficientObservations");
+    }
+  });
+
+  it("Provides concentrated liquidity and swaps through it", async () => {
+    const clFee = 30; // 0.3% tier, tick spacing 60
+    const i32 = (value: number) => new anchor.BN(value).toTwos(32).toArrayLike(Buffer, "le", 4);
+
+    const [pool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("cl_pool"), mintX.toBuffer(), mintY.toBuffer(), new anchor.BN(clFee).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+    const [clRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("cl_registry"), mintX.toBuffer(), mintY.toBuffer(), new anchor.BN(clFee).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+    const [clObservation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("observation"), pool.toBuffer()],
+      program.programId
+    );
+    const poolVaultX = getAssociatedTokenAddressSync(mintX, pool, true);
+    const poolVaultY = getAssociatedTokenAddressSync(mintY, pool, true);
+
+    await program.methods
+      .initializeClPool(clFee, wallet.publicKey, new anchor.BN(1).shln(64)) // price 1
+      .accountsStrict({
+        initializer: wallet.publicKey,
+        mintX: mintX,
+        mintY: mintY,
+        pool: pool,
+        registry: clRegistry,
+        observation: clObservation,
+        vaultX: poolVaultX,
+        vaultY: poolVaultY,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // each array covers 64 * 60 ticks, the range [-600, 600) spans two of them
+    const tickArrays: PublicKey[] = [];
+    for (const start of [0, -3840]) {
+      const [tickArray] = PublicKey.findProgramAddressSync(
+        [Buffer.from("tick_array"), pool.toBuffer(), i32(start)],
+        program.programId
+      );
+      await program.methods
+        .initializeTickArray(start)
+        .accountsStrict({
+          payer: wallet.publicKey,
+          pool: pool,
+          tickArray: tickArray,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      tickArrays.push(tickArray);
+    }
+    const remainingAccounts = tickArrays.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }));
+
+    const [position] = PublicKey.findProgramAddressSync(
+      [Buffer.from("position"), pool.toBuffer(), wallet.publicKey.toBuffer(), i32(-600), i32(600)],
+      program.programId
+    );
+    await program.methods
+      .openPosition(-600, 600)
+      .accountsStrict({
+        owner: wallet.publicKey,
+        pool: pool,
+        position: position,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const positionAccounts = {
+      owner: wallet.publicKey,
+      mintX: mintX,
+      mintY: mintY,
+      pool: pool,
+      position: position,
+      observation: clObservation,
+      vaultX: poolVaultX,
+      vaultY: poolVaultY,
+      userX: userX,
+      userY: userY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    };
+    await program.methods
+      .increaseLiquidity(new anchor.BN(1_000_000_000), new anchor.BN(100_000_000), new anchor.BN(100_000_000))
+      .accountsStrict(positionAccounts)
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    const swapAccounts = {
+      user: wallet.publicKey,
+      mintX: mintX,
+      mintY: mintY,
+      pool: pool,
+      observation: clObservation,
+      vaultX: poolVaultX,
+      vaultY: poolVaultY,
+      userX: userX,
+      userY: userY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    };
+    const userYBefore = await getAccount(connection, userY);
+    const tx = await program.methods
+      .clSwap(true, new anchor.BN(1_000_000), new anchor.BN(990_000))
+      .accountsStrict(swapAccounts)
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+    const userYAfter = await getAccount(connection, userY);
+
+    // the event reports what the user received and the tier fee
+    const [swapped] = await swapEvents(tx);
+    assert.ok(swapped.config.equals(pool));
+    assert.equal(swapped.effectiveFee, clFee);
+    assert.equal(swapped.amountOut.toString(), (userYAfter.amount - userYBefore.amount).toString());
+    assert.isTrue(swapped.feeAmount.gtn(0));
+
+    const observationAccount = await program.account.observationState.fetch(clObservation);
+    assert.ok(observationAccount.config.equals(pool));
+
+    await program.methods
+      .collectPositionFees()
+      .accountsStrict(positionAccounts)
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    const poolAccount = await program.account.clPool.fetch(pool);
+    const positionAccount = await program.account.position.fetch(position);
+    console.log("\n🎯 Concentrated Liquidity Swap");
+    console.log("Transaction signature:", tx);
+    console.log("  Current tick:", poolAccount.tickCurrent);
+    console.log("  Pool liquidity:", poolAccount.liquidity.toString());
+    console.log("  Position fees owed after collect:", positionAccount.feesOwedX.toString());
+
+    // the pool authority can lock the pool like a curve pool
+    const admin = { authority: wallet.publicKey, pool: pool };
+    await program.methods.lockClPool().accountsStrict(admin).rpc();
+    try {
+      await program.methods
+        .clSwap(true, new anchor.BN(1_000_000), new anchor.BN(0))
+        .accountsStrict(swapAccounts)
+        .remainingAccounts(remainingAccounts)
+        .rpc();
+      throw new Error("swap on a locked pool should have failed");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      assert.equal(err.error.errorCode.code, "PoolLocked");
+    }
+    await program.methods.unlockClPool().accountsStrict(admin).rpc();
+    console.log("  Locked swap rejected, pool unlocked again");
+  });
+
+  it("Rejects admin updates without an authority", async () => {
+    try {
+      await program.methods
+        .lock()
+        .accountsStrict({
+          authority: wallet.publicKey,
+          config: config,
+        })
+        .rpc();
+      throw new Error("lock should have failed");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      console.log("\n🔒 Lock rejected:", err.error.errorCode.code);
+    }
+
+    const configAccount = await program.account.config.fetch(config);
+    console.log("  Locked:", configAccount.locked);
+  });
+
+  it("Accrues and collects protocol fees", async () => {
+    // a second pool with an authority, the first one has none
+    const poolSeed = new anchor.BN(12);
+    const [poolConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config"), poolSeed.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [poolLp] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lp"), poolConfig.toBuffer()],
+      program.programId
+    );
+    const [poolObservation] = PublicKey.findProgramAddressSync(
+      [Buffer.from("observation"), poolConfig.toBuffer()],
+      program.programId
+    );
+    const [poolDead] = PublicKey.findProgramAddressSync(
+      [Buffer.from("dead"), poolConfig.toBuffer()],
+      program.programId
+    );
+    const poolLockedLp = getAssociatedTokenAddressSync(poolLp, poolDead, true);
+    // the first pool holds the pair's 2% tier, this one is listed at 0.3%
+    const poolFee = 30;
+    const [poolRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), mintX.toBuffer(), mintY.toBuffer(), new anchor.BN(poolFee).toArrayLike(Buffer, "le", 2)],
+      program.programId
+    );
+    const poolVaultX = getAssociatedTokenAddressSync(mintX, poolConfig, true);
+    const poolVaultY = getAssociatedTokenAddressSync(mintY, poolConfig, true);
+    const poolUserLp = getAssociatedTokenAddressSync(poolLp, wallet.publicKey);
+
+    await program.methods
+      .initialize(poolSeed, poolFee, wallet.publicKey, { constantProduct: {} })
+      .accountsStrict({
+        initializer: wallet.publicKey,
+        mintX: mintX,
+        mintY: mintY,
+        mintLp: poolLp,
+        vaultX: poolVaultX,
+        vaultY: poolVaultY,
+        config: poolConfig,
+        registry: poolRegistry,
+        observation: poolObservation,
+        dead: poolDead,
+        lockedLp: poolLockedLp,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const poolAccounts = {
+      user: wallet.publicKey,
+      mintX: mintX,
+      mintY: mintY,
+      config: poolConfig,
+      observation: poolObservation,
+      mintLp: poolLp,
+      vaultX: poolVaultX,
+      vaultY: poolVaultY,
+      userX: userX,
+      userY: userY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    };
+    await program.methods
+      .deposit(new anchor.BN(1), new anchor.BN(100_000_000), new anchor.BN(100_000_000))
+      .accountsStrict({
+        ...poolAccounts,
+        userLp: poolUserLp,
+        dead: poolDead,
+        lockedLp: poolLockedLp,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const admin = { authority: wallet.publicKey, config: poolConfig };
+    await program.methods.updateProtocolFee(2_000).accountsStrict(admin).rpc(); // 20% of the swap fee
+
+    const tx = await program.methods
+      .swap(true, new anchor.BN(5_000_000), new anchor.BN(0))
+      .accountsStrict(poolAccounts)
+      .rpc();
+    const [swapped] = await swapEvents(tx);
+    assert.equal(swapped.effectiveFee, poolFee);
+
+    // the fee was paid in X, so only X accrues
+    let configAccount = await program.account.config.fetch(poolConfig);
+    const accrued = configAccount.protocolFeesX;
+    assert.isTrue(accrued.gtn(0));
+    assert.equal(accrued.toString(), swapped.feeAmount.muln(2_000).divn(10_000).toString());
+    assert.equal(configAccount.protocolFeesY.toString(), "0");
+
+    const treasuryOwner = Keypair.generate().publicKey;
+    const treasuryX = await createAccount(connection, wallet.payer, mintX, treasuryOwner);
+    const treasuryY = await createAccount(connection, wallet.payer, mintY, treasuryOwner);
+    const collectAccounts = {
+      authority: wallet.publicKey,
+      mintX: mintX,
+      mintY: mintY,
+      config: poolConfig,
+      vaultX: poolVaultX,
+      vaultY: poolVaultY,
+      treasuryX: treasuryX,
+      treasuryY: treasuryY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    };
+
+    // not while a flash loan has the vaults short
+    const flashAccounts = {
+      user: wallet.publicKey,
+      mintX: mintX,
+      mintY: mintY,
+      config: poolConfig,
+      vaultX: poolVaultX,
+      vaultY: poolVaultY,
+      userX: userX,
+      userY: userY,
+      instructionSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+      tokenProgramX: TOKEN_PROGRAM_ID,
+      tokenProgramY: TOKEN_PROGRAM_ID,
+    };
+    const collect = await program.methods.collectProtocolFees().accountsStrict(collectAccounts).instruction();
+    const repay = await program.methods.flashRepay().accountsStrict(flashAccounts).instruction();
+    try {
+      await program.methods
+        .flashLoan(new anchor.BN(1_000_000), new anchor.BN(0))
+        .accountsStrict(flashAccounts)
+        .postInstructions([collect, repay])
+        .rpc();
+      throw new Error("collecting during a flash loan should have failed");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      assert.equal(err.error.errorCode.code, "FlashLoanActive");
+    }
+
+    const vaultXBefore = await getAccount(connection, poolVaultX);
+    await program.methods.collectProtocolFees().accountsStrict(collectAccounts).rpc();
+    const vaultXAfter = await getAccount(connection, poolVaultX);
+    const treasuryXAccount = await getAccount(connection, treasuryX);
+
+    // exactly the accrued fees leave the vault, the LPs' reserves stay
+    assert.equal(treasuryXAccount.amount.toString(), accrued.toString());
+    assert.equal((vaultXBefore.amount - vaultXAfter.amount).toString(), accrued.toString());
+    configAccount = await program.account.config.fetch(poolConfig);
+    assert.equal(configAccount.protocolFeesX.toString(), "0");
+    console.log("\n🏛️  Protocol fees collected:", Number(treasuryXAccount.amount) / 1e6, "X");
+  });
+
+  it("Deposits a single token for no more LP than a swap and deposit", async () => {
+    const [single, twoStep] = await twinPools([16, 17]);
+
+    // 10 A in one instruction, half of it is swapped inside the pool
+    const lpBefore = await balance(single.deposit.userLp);
+    const tx = await program.methods
+      .depositSingle(single.aIsX, new anchor.BN(10_000_000), new anchor.BN(0))
+      .accountsStrict(single.deposit)
+      .rpc();
+    const lpSingle = (await balance(single.deposit.userLp)) - lpBefore;
+
+    // the implied swap pays the pool fee
+    const [implied] = await swapEvents(tx);
+    assert.equal(implied.isX, single.aIsX);
+    assert.equal(implied.effectiveFee, 30);
+    assert.isTrue(implied.feeAmount.gtn(0));
+
+    // the same 10 A in two steps: swap 5, then deposit the rest with what came out
+    const otherBefore = await balance(twoStep.userOther);
+    const swapTx = await program.methods
+      .swap(twoStep.aIsX, new anchor.BN(5_000_000), new anchor.BN(0))
+      .accountsStrict(twoStep.swap)
+      .rpc();
+    const out = (await balance(twoStep.userOther)) - otherBefore;
+    const [swapped] = await swapEvents(swapTx);
+    assert.equal(swapped.feeAmount.toString(), implied.feeAmount.toString());
+
+    const supply = (await getMint(connection, twoStep.pool.mintLp)).supply;
+    const lpForA = (supply * BigInt(5_000_000)) / (await balance(twoStep.vaultA));
+    const lpForOther = (supply * out) / (await balance(twoStep.vaultOther));
+    const lpTwoStep = lpForA < lpForOther ? lpForA : lpForOther;
+    const [maxA, maxOther] = [new anchor.BN(5_000_010), new anchor.BN((out + BigInt(10)).toString())];
+
+    await program.methods
+      .deposit(
+        new anchor.BN(lpTwoStep.toString()),
+        twoStep.aIsX ? maxA : maxOther,
+        twoStep.aIsX ? maxOther : maxA
+      )
+      .accountsStrict(twoStep.deposit)
+      .rpc();
+
+    assert.isTrue(lpSingle > BigInt(0) && lpSingle <= lpTwoStep);
+    console.log("\n➕ Single-sided LP:", lpSingle.toString(), "swap and deposit LP:", lpTwoStep.toString());
+  });
+
+  it("Withdraws a single token for no more than a withdraw and swap", async () => {
+    const [single, twoStep] = await twinPools([18, 19]);
+    const lp = new anchor.BN(10_000_000);
+
+    // the other token's share is swapped inside the pool for more A
+    let aBefore = await balance(single.userA);
+    const tx = await program.methods
+      .withdrawSingle(single.aIsX, lp, new anchor.BN(0))
+      .accountsStrict(single.withdraw)
+      .rpc();
+    const outSingle = (await balance(single.userA)) - aBefore;
+
+    const [implied] = await swapEvents(tx);
+    assert.equal(implied.isX, !single.aIsX);
+    assert.equal(implied.effectiveFee, 30);
+    assert.isTrue(implied.feeAmount.gtn(0));
+
+    // the same LP in two steps: a balanced withdraw, then swap the other token for A
+    aBefore = await balance(twoStep.userA);
+    const otherBefore = await balance(twoStep.userOther);
+    await program.methods
+      .withdraw(lp, new anchor.BN(0), new anchor.BN(0))
+      .accountsStrict(twoStep.withdraw)
+      .rpc();
+    const otherOut = (await balance(twoStep.userOther)) - otherBefore;
+    await program.methods
+      .swap(!twoStep.aIsX, new anchor.BN(otherOut.toString()), new anchor.BN(0))
+      .accountsStrict(twoStep.swap)
+      .rpc();
+    const outTwoStep = (await balance(twoStep.userA)) - aBefore;
+
+    assert.isTrue(outSingle > BigInt(0) && outSingle <= outTwoStep);
+    console.log("\n➖ Single-sided out:", outSingle.toString(), "withdraw and swap out:", outTwoStep.toString());
+  });
+
+  it("Routes a swap through two pools", async () => {
+    // a second pool pairs Y with a new mint Z
+    const mintZ = await createMint(connection, wallet.payer, wallet.publicKey, null, 6);
+    const userZ = await createAccount(connection, wallet.payer, mintZ, wallet.publicKey);
+    await mintTo(connection, wallet.payer, mintZ, userZ, wallet.publicKey, 1_000_000_000);
+
+    const poolSeed = new anchor.BN(15);
+    const poolYZ =
+      Buffer.compare(mintY.toBuffer(), mintZ.toBuffer()) < 0
+        ? curvePool(poolSeed, mintY, mintZ, 30)
+        : curvePool(poolSeed, mintZ, mintY, 30);
+    await program.methods
+      .initialize(poolSeed, 30, null, { constantProduct: {} })
+      .accountsStrict(initializeAccounts(poolYZ))
+      .rpc();
+    const yIsX = poolYZ.mintX.equals(mintY);
+    await program.methods
+      .deposit(new anchor.BN(1), new anchor.BN(100_000_000), new anchor.BN(100_000_000))
+      .accountsStrict({
+        user: wallet.publicKey,
+        mintX: poolYZ.mintX,
+        mintY: poolYZ.mintY,
+        config: poolYZ.config,
+        observation: poolYZ.observation,
+        mintLp: poolYZ.mintLp,
+        vaultX: poolYZ.vaultX,
+        vaultY: poolYZ.vaultY,
+        userX: yIsX ? userY : userZ,
+        userY: yIsX ? userZ : userY,
+        userLp: getAssociatedTokenAddressSync(poolYZ.mintLp, wallet.publicKey),
+        dead: poolYZ.dead,
+        lockedLp: poolYZ.lockedLp,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenProgramX: TOKEN_PROGRAM_ID,
+        tokenProgramY: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const poolXY = curvePool(seed, mintX, mintY, fee);
+    const route = (mintIn: PublicKey, userIn: PublicKey, pools: ReturnType<typeof curvePool>[]) => ({
+      accounts: {
+        user: wallet.publicKey,
+        mintIn: mintIn,
+        mintOut: mintZ,
+        userIn: userIn,
+        userOut: userZ,
+        tokenProgramIn: TOKEN_PROGRAM_ID,
+        tokenProgramOut: TOKEN_PROGRAM_ID,
+      },
+      remaining: pools.flatMap(routeAccounts),
+    });
+    const expectError = async (promise: Promise<string>, code: string) => {
+      try {
+        await promise;
+        throw new Error(`route should have failed with ${code}`);
+      } catch (err) {
+        if (!(err instanceof anchor.AnchorError)) throw err;
+        assert.equal(err.error.errorCode.code, code);
+      }
+    };
+
+    // X -> Y -> Z, the minimum is checked on what reaches the user
+    const xToZ = route(mintX, userX, [poolXY, poolYZ]);
+    await expectError(
+      program.methods
+        .routeSwap(new anchor.BN(1_000_000), new anchor.BN(1_000_000))
+        .accountsStrict(xToZ.accounts)
+        .remainingAccounts(xToZ.remaining)
+        .rpc(),
+      "SlippageExceeded"
+    );
+
+    const userXBefore = await getAccount(connection, userX);
+    const userYBefore = await getAccount(connection, userY);
+    const userZBefore = await getAccount(connection, userZ);
+    const tx = await program.methods
+      .routeSwap(new anchor.BN(1_000_000), new anchor.BN(500_000))
+      .accountsStrict(xToZ.accounts)
+      .remainingAccounts(xToZ.remaining)
+      .rpc();
+    const userXAfter = await getAccount(connection, userX);
+    const userYAfter = await getAccount(connection, userY);
+    const userZAfter = await getAccount(connection, userZ);
+
+    // only X leaves and only Z arrives, Y stays between the pools
+    assert.equal(userXBefore.amount - userXAfter.amount, BigInt(1_000_000));
+    assert.equal(userYAfter.amount, userYBefore.amount);
+    assert.isTrue(userZAfter.amount - userZBefore.amount >= BigInt(500_000));
+
+    // one event per hop, the first hop's output is the second hop's input
+    const hops = await swapEvents(tx);
+    assert.equal(hops.length, 2);
+    assert.ok(hops[0].config.equals(poolXY.config) && hops[1].config.equals(poolYZ.config));
+    assert.equal(hops[1].amountIn.toString(), hops[0].amountOut.toString());
+    assert.equal((userZAfter.amount - userZBefore.amount).toString(), hops[1].amountOut.toString());
+
+    // a pool can only appear once in a route
+    const repeated = route(mintX, userX, [poolXY, poolXY, poolYZ]);
+    await expectError(
+      program.methods
+        .routeSwap(new anchor.BN(1_000_000), new anchor.BN(0))
+        .accountsStrict(repeated.accounts)
+        .remainingAccounts(repeated.remaining)
+        .rpc(),
+      "InvalidRoute"
+    );
+
+    // Y -> X leaves X, which the Y/Z pool does not hold
+    const mismatch = route(mintY, userY, [poolXY, poolYZ]);
+    await expectError(
+      program.methods
+        .routeSwap(new anchor.BN(1_000_000), new anchor.BN(0))
+        .accountsStrict(mismatch.accounts)
+        .remainingAccounts(mismatch.remaining)
+        .rpc(),
+      "InvalidRoute"
+    );
+    console.log("\n🔀 Routed 1 X into", Number(userZAfter.amount - userZBefore.amount) / 1e6, "Z");
+  });
+
+  it("Lists a Token-2022 transfer-fee mint", async () => {
+    // 1% on every transfer, capped at 5 tokens
+    const feeMint = await createMint2022(ExtensionType.TransferFeeConfig, (mint) =>
+      createInitializeTransferFeeConfigInstruction(
+        mint,
+        wallet.publicKey,
+        wallet.publicKey,
+        100,
+        BigInt(5_000_000),
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    const userFee = await createAccount(
+      connection,
+      wallet.payer,
+      feeMint,
+      wallet.publicKey,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(connection, wallet.payer, feeMint, userFee, wallet.publicKey, 1_000_000_000, [], undefined, TOKEN_2022_PROGRAM_ID);
+
+    const poolSeed = new anchor.BN(13);
+    const pool = token2022Pool(poolSeed, feeMint, 30);
+    await program.methods
+      .initialize(poolSeed, 30, null, { constantProduct: {} })
+      .accountsStrict(initializeAccounts(pool))
+      .rpc();
+    const feeIsX = pool.mintX.equals(feeMint);
+    const [vaultFee, userXOrFee, userYOrFee] = feeIsX ? [pool.vaultX, userFee, userX] : [pool.vaultY, userX, userFee];
+
+    const tokenAccounts = {
+      user: wallet.publicKey,
+      mintX: pool.mintX,
+      mintY: pool.mintY,
+      config: pool.config,
+      observation: pool.observation,
+      mintLp: pool.mintLp,
+      vaultX: pool.vaultX,
+      vaultY: pool.vaultY,
+      userX: userXOrFee,
+      userY: userYOrFee,
+      tokenProgramX: pool.tokenProgramX,
+      tokenProgramY: pool.tokenProgramY,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+    };
+
+    // the vault keeps what arrives after the 1% fee
+    await program.methods
+      .deposit(new anchor.BN(1), new anchor.BN(100_000_000), new anchor.BN(100_000_000))
+      .accountsStrict({
+        ...tokenAccounts,
+        userLp: getAssociatedTokenAddressSync(pool.mintLp, wallet.publicKey),
+        dead: pool.dead,
+        lockedLp: pool.lockedLp,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+    const vaultFeeBefore = await getAccount(connection, vaultFee, undefined, TOKEN_2022_PROGRAM_ID);
+    assert.equal(vaultFeeBefore.amount, BigInt(99_000_000));
+
+    // slippage is checked on what arrives, so a minimum just under the vault's
+    // payout fails once the 1% transfer fee is taken from it
+    const grossEstimate = Math.floor((99_000_000 * 9_970_000) / (100_000_000 + 9_970_000));
+    const minOut = grossEstimate - 10_000; // well inside the ~90_000 transfer fee
+    try {
+      await program.methods
+        .swap(!feeIsX, new anchor.BN(10_000_000), new anchor.BN(minOut))
+        .accountsStrict(tokenAccounts)
+        .rpc();
+      throw new Error("minimum above the net payout should have failed");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      assert.equal(err.error.errorCode.code, "SlippageExceeded");
+    }
+
+    // the same swap, the user receives the payout less the transfer fee
+    const userFeeBefore = await getAccount(connection, userFee, undefined, TOKEN_2022_PROGRAM_ID);
+    await program.methods
+      .swap(!feeIsX, new anchor.BN(10_000_000), new anchor.BN(8_000_000))
+      .accountsStrict(tokenAccounts)
+      .rpc();
+    const userFeeAfter = await getAccount(connection, userFee, undefined, TOKEN_2022_PROGRAM_ID);
+    const vaultFeeAfter = await getAccount(connection, vaultFee, undefined, TOKEN_2022_PROGRAM_ID);
+
+    const payout = vaultFeeBefore.amount - vaultFeeAfter.amount;
+    const transferFee = (payout * BigInt(100) + BigInt(9_999)) / BigInt(10_000);
+    assert.equal(userFeeAfter.amount - userFeeBefore.amount, payout - transferFee);
+    assert.isTrue(payout >= BigInt(minOut) && payout - transferFee < BigInt(minOut));
+    console.log("\n🪙 Token-2022 swap paid", Number(payout - transferFee) / 1e6, "after the transfer fee");
+  });
+
+  it("Rejects a Token-2022 mint with a permanent delegate", async () => {
+    const delegateMint = await createMint2022(ExtensionType.PermanentDelegate, (mint) =>
+      createInitializePermanentDelegateInstruction(mint, Keypair.generate().publicKey, TOKEN_2022_PROGRAM_ID)
+    );
+
+    const poolSeed = new anchor.BN(14);
+    const pool = token2022Pool(poolSeed, delegateMint, 30);
+    try {
+      await program.methods
+        .initialize(poolSeed, 30, null, { constantProduct: {} })
+        .accountsStrict(initializeAccounts(pool))
+        .rpc();
+      throw new Error("permanent delegate mint should have been rejected");
+    } catch (err) {
+      if (!(err instanceof anchor.AnchorError)) throw err;
+      assert.equal(err.error.errorCode.code, "UnsupportedMintExtension");
+    }
+  });
+
+  it("Keeps a pool listed under its fee tier after a fee update", async () => {
+    // the protocol fee pool, created at the 0.3% tier with an authority
+    const pool = curvePool(new anchor.BN(12), mintX, mintY, 30);
+    const admin = { authority: wallet.publicKey, config: pool.config };
+
+    await program.methods.updateFee(150).accountsStrict(admin).rpc();
+    let configAccount = await program.account.config.fetch(pool.config);
+    assert.equal(configAccount.fee, 150);
+
+    const registryAccount = await program.account.poolRegistry.fetch(pool.registry);
+    assert.equal(configAccount.feeTier, 30);
+    assert.equal(registryAccount.feeTier, 30);
+    assert.ok(registryAccount.config.equals(pool.config));
+
+    await program.methods.updateFee(30).accountsStrict(admin).rpc();
+    configAccount = await program.account.config.fetch(pool.config);
+    assert.equal(configAccount.fee, 30);
+    console.log("\n🎚️  Fee restored to", configAccount.fee, "basis points");
+  });
+
+  it("Prices swaps with a dynamic fee between its floor and cap", async () => {
+    const pool = curvePool(new anchor.BN(12), mintX, mintY, 30);
+    const admin = { authority: wallet.publicKey, config: pool.config };
+
+    // 0.3% to 3%, +1 bp of fee per 10 bps of volatility, calm again after 10 minutes
+    await program.methods.enableDynamicFee(30, 300, 1_000, 600).accountsStrict(admin).rpc();
+    let configAccount = await program.account.config.fetch(pool.config);
+    assert.equal(configAccount.dynamicFee.feeFloor, 30);
+    assert.equal(configAccount.dynamicFee.feeCap, 300);
+
+    // a large swap moves the price and pays for its own move
+    const tx = await program.methods
+      .swap(true, new anchor.BN(20_000_000), new anchor.BN(0))
+      .accountsStrict(poolAccounts(pool).swap)
+      .rpc();
+    const [swapped] = await swapEvents(tx);
+    assert.isTrue(swapped.effectiveFee > 30 && swapped.effectiveFee <= 300);
+
+    configAccount = await program.account.config.fetch(pool.config);
+    assert.isTrue(configAccount.dynamicFee.volatilityAccumulator.gtn(0));
+
+    await program.methods.disableDynamicFee().accountsStrict(admin).rpc();
+    configAccount = await program.account.config.fetch(pool.config);
+    assert.isNull(configAccount.dynamicFee);
+    console.log("\n🌪️  Dynamic fee charged", swapped.effectiveFee, "basis points");
+  });
+});
\ No newline at end of file